@@ -178,6 +178,8 @@ pub struct PregenAttacks {
     rook_masks: [Bitboard; 64],
     rook_indices: [usize; 64],
     rook: Box<[Bitboard; ROOK_ATTACK_SIZE]>,
+    between: Box<[[Bitboard; 64]; 64]>,
+    line: Box<[[Bitboard; 64]; 64]>,
 }
 
 impl Default for PregenAttacks {
@@ -193,6 +195,8 @@ impl Default for PregenAttacks {
             rook_masks: [Bitboard::new_empty(); 64],
             rook_indices: [0; 64],
             rook: vec![Bitboard::new_empty(); ROOK_ATTACK_SIZE].into_boxed_slice().try_into().unwrap(),
+            between: Box::new([[Bitboard::new_empty(); 64]; 64]),
+            line: Box::new([[Bitboard::new_empty(); 64]; 64]),
         }
     }
 }
@@ -203,12 +207,13 @@ impl PregenAttacks {
         let mut attacks = PregenAttacks::default();
         init_nonsliding_attacks(&mut attacks);
         init_sliding_attacks(&mut attacks);
+        init_line_tables(&mut attacks);
         attacks
     }
 
     #[inline(always)]
     pub fn get_pawn_attacks(&self, color: Color, square: Square) -> Bitboard {
-        self.pawn[63 * color as usize + square as usize]
+        self.pawn[64 * color as usize + square as usize]
     }
 
     #[inline(always)]
@@ -238,13 +243,25 @@ impl PregenAttacks {
         self.get_bishop_attacks(square, occupancy)
             .combine(self.get_rook_attacks(square, occupancy))
     }
+
+    /// Squares strictly between two squares on a shared rank, file or diagonal (empty if not aligned)
+    #[inline(always)]
+    pub fn get_between(&self, from: Square, to: Square) -> Bitboard {
+        self.between[from][to]
+    }
+
+    /// The full rank, file or diagonal through two squares (empty if not aligned)
+    #[inline(always)]
+    pub fn get_line(&self, from: Square, to: Square) -> Bitboard {
+        self.line[from][to]
+    }
 }
 
 #[inline]
 fn init_nonsliding_attacks(attacks: &mut PregenAttacks) {
     for s in SQUARES {
-        attacks.pawn[63 * Color::White as usize + s as usize] = gen_pawn_attack(Color::White, s);
-        attacks.pawn[63 * Color::Black as usize + s as usize] = gen_pawn_attack(Color::Black, s);
+        attacks.pawn[64 * Color::White as usize + s as usize] = gen_pawn_attack(Color::White, s);
+        attacks.pawn[64 * Color::Black as usize + s as usize] = gen_pawn_attack(Color::Black, s);
         attacks.knight[s] = gen_knight_attack(s);
         attacks.king[s] = gen_king_attack(s);
     }
@@ -287,6 +304,38 @@ fn init_sliding_attacks(attacks: &mut PregenAttacks) {
     }
 }
 
+#[inline]
+fn init_line_tables(attacks: &mut PregenAttacks) {
+    let empty = Bitboard::new_empty();
+    for from in SQUARES {
+        for to in SQUARES {
+            if from == to {
+                continue;
+            }
+            let (from_bb, to_bb) = (from.to_bitboard(), to.to_bitboard());
+
+            // Rays from both ends overlap on the line between them only when the squares are aligned
+            if attacks.get_rook_attacks(from, &empty).is_occupied(to) {
+                attacks.between[from][to] = attacks.get_rook_attacks(from, &to_bb).intersect(attacks.get_rook_attacks(to, &from_bb));
+                attacks.line[from][to] = attacks
+                    .get_rook_attacks(from, &empty)
+                    .intersect(attacks.get_rook_attacks(to, &empty))
+                    .combine(from_bb)
+                    .combine(to_bb);
+            } else if attacks.get_bishop_attacks(from, &empty).is_occupied(to) {
+                attacks.between[from][to] = attacks
+                    .get_bishop_attacks(from, &to_bb)
+                    .intersect(attacks.get_bishop_attacks(to, &from_bb));
+                attacks.line[from][to] = attacks
+                    .get_bishop_attacks(from, &empty)
+                    .intersect(attacks.get_bishop_attacks(to, &empty))
+                    .combine(from_bb)
+                    .combine(to_bb);
+            }
+        }
+    }
+}
+
 #[inline(always)]
 fn gen_pawn_attack(color: Color, square: Square) -> Bitboard {
     let board = Bitboard::new_from_square(square);
@@ -337,7 +386,7 @@ fn gen_bishop_masks() -> [Bitboard; 64] {
             let mut x = file + dx;
             let mut y = rank + dy;
 
-            while x >= 1 && x <= 6 && y >= 1 && y <= 6 {
+            while (1..=6).contains(&x) && (1..=6).contains(&y) {
                 masks[s].set_square(SQUARES[(y as u8 * 8 + x as u8) as usize]);
                 x += dx;
                 y += dy;
@@ -388,7 +437,7 @@ fn bishop_attack_otf(square: Square, occupancy: &Bitboard) -> Bitboard {
         let mut x = file + dx;
         let mut y = rank + dy;
 
-        while x >= 0 && x <= 7 && y >= 0 && y <= 7 {
+        while (0..=7).contains(&x) && (0..=7).contains(&y) {
             let sq = SQUARES[(y as u8 * 8 + x as u8) as usize];
             let sq_bb = Bitboard::new_from_square(sq);
 
@@ -415,7 +464,7 @@ fn rook_attack_otf(square: Square, occupancy: &Bitboard) -> Bitboard {
         let mut x = file + dx;
         let mut y = rank + dy;
 
-        while x >= 0 && x <= 7 && y >= 0 && y <= 7 {
+        while (0..=7).contains(&x) && (0..=7).contains(&y) {
             let sq = SQUARES[(y as u8 * 8 + x as u8) as usize];
             let sq_bb = Bitboard::new_from_square(sq);
 
@@ -571,4 +620,29 @@ mod tests {
         );
         assert_eq!(attacks.get_rook_attacks(Square::D4, &bb), Bitboard::new_from_u64(0x80876080800));
     }
+
+    #[test]
+    fn test_pawn_attacks_do_not_overlap() {
+        let attacks = PregenAttacks::init();
+        assert!(attacks.get_pawn_attacks(Color::White, Square::H8).is_empty());
+        assert_eq!(attacks.get_pawn_attacks(Color::Black, Square::A1), Bitboard::new_empty());
+        assert_eq!(attacks.get_pawn_attacks(Color::Black, Square::B2), Bitboard::new_from_u64(0x5));
+    }
+
+    #[test]
+    fn test_between_and_line() {
+        let attacks = PregenAttacks::init();
+
+        // Rank, file and diagonal
+        assert_eq!(attacks.get_between(Square::A1, Square::D1), Bitboard::new_from_u64(0x6));
+        assert_eq!(attacks.get_between(Square::D4, Square::D1), Bitboard::new_from_u64(0x80800));
+        assert_eq!(attacks.get_between(Square::A1, Square::D4), Bitboard::new_from_u64(0x40200));
+        assert_eq!(attacks.get_line(Square::B2, Square::C3), Bitboard::new_from_u64(0x8040201008040201));
+        assert_eq!(attacks.get_line(Square::E1, Square::E4), FILE_E_BB);
+
+        // Adjacent and unaligned squares
+        assert!(attacks.get_between(Square::E1, Square::E2).is_empty());
+        assert!(attacks.get_between(Square::A1, Square::B3).is_empty());
+        assert!(attacks.get_line(Square::A1, Square::B3).is_empty());
+    }
 }
@@ -32,9 +32,6 @@ pub const RANK_8_BB: Bitboard = Bitboard(0xff00000000000000);
 
 pub const RANKS_BB: [Bitboard; 8] = [RANK_1_BB, RANK_2_BB, RANK_3_BB, RANK_4_BB, RANK_5_BB, RANK_6_BB, RANK_7_BB, RANK_8_BB];
 
-pub const DARK_SQUARES_BB: Bitboard = Bitboard(0xAA55AA55AA55AA55);
-pub const LIGHT_SQUARES_BB: Bitboard = Bitboard(0xAA55AA55AA55AA55);
-
 impl Bitboard {
     /// Creates an empty bitboard
     #[inline(always)]
@@ -243,6 +240,43 @@ impl Bitboard {
         }
     }
 
+    /// Extends every square up to rank 8
+    #[inline(always)]
+    pub fn fill_up(&self) -> Self {
+        let mut bb = self.0;
+        bb |= bb << 8;
+        bb |= bb << 16;
+        bb |= bb << 32;
+        Bitboard(bb)
+    }
+
+    /// Extends every square down to rank 1
+    #[inline(always)]
+    pub fn fill_down(&self) -> Self {
+        let mut bb = self.0;
+        bb |= bb >> 8;
+        bb |= bb >> 16;
+        bb |= bb >> 32;
+        Bitboard(bb)
+    }
+
+    /// Squares in front of every square from `color`'s point of view, not including the squares themselves
+    #[inline(always)]
+    pub fn front_span(&self, color: Color) -> Self {
+        match color {
+            Color::White => self.shift_up(1).fill_up(),
+            Color::Black => self.shift_down(1).fill_down(),
+        }
+    }
+
+    /// Squares on the files next to every square, on the same ranks
+    #[inline(always)]
+    pub fn adjacent_files(&self) -> Self {
+        self.shift_left(1)
+            .intersect(!FILE_H_BB)
+            .combine(self.shift_right(1).intersect(!FILE_A_BB))
+    }
+
     /// Gets knight attack squares
     #[inline(always)]
     pub fn shift_knight_attack(&self) -> Self {
@@ -382,4 +416,14 @@ mod tests {
         let board = Bitboard(0b0000_0100);
         assert_eq!(board.shift_down_right(1), Bitboard(0b0000_0000_0000));
     }
+
+    #[test]
+    fn test_spans() {
+        let board = Square::E4.to_bitboard();
+        assert_eq!(board.front_span(Color::White), Bitboard(FILE_E_BB.0 & 0xFFFF_FFFF_0000_0000));
+        assert_eq!(board.front_span(Color::Black), Bitboard(FILE_E_BB.0 & 0x0000_0000_00FF_FFFF));
+        assert_eq!(board.fill_up().fill_down(), FILE_E_BB);
+        assert_eq!(Square::A4.to_bitboard().adjacent_files(), Square::B4.to_bitboard());
+        assert_eq!(board.adjacent_files(), Bitboard(Square::D4.to_bitboard().0 | Square::F4.to_bitboard().0));
+    }
 }
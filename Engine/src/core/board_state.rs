@@ -1,7 +1,11 @@
+use std::sync::Arc;
+
 use super::attack_pregen::PregenAttacks;
-use super::fen_parser::parse_fen;
-use super::piece_square_table::Phase;
-use super::piece_square_table::PieceSquareTable;
+use super::eval_params::EvalParams;
+use super::eval_trace::{EvalTerm, EvalTrace, EvalTracer, NoTrace};
+use super::fen_parser::{parse_fen, to_fen};
+use super::pawn_table::{PawnEntry, PawnTable};
+use super::score::{Score, MAX_PHASE};
 use super::zobrist::ZobristHasher;
 use crate::core::bitboard::*;
 use crate::core::piece::*;
@@ -10,6 +14,19 @@ use crate::move_logic::move_encode::Move;
 
 const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
+/// State that cannot be recomputed when taking a move back, returned by `BoardState::make_move`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UndoInfo {
+    captured_index: u8,      // Index of the captured piece in its piece list
+    promoted_pawn_index: u8, // Index of the promoting pawn in its piece list
+    castling_rights: u8,
+    en_passant: Option<Square>,
+    half_moves: u8,
+    zobrist_hash: u64,
+    pawn_hash: u64,
+    psq_score: [Score; 2],
+}
+
 #[derive(Debug, PartialEq, Clone)]
 #[repr(C)]
 pub struct BoardState {
@@ -27,9 +44,10 @@ pub struct BoardState {
     full_moves: u32,            // Total full moves in the game
 
     // Evaluation information
-    material: [i32; 2],     // Material value for each side
-    piece_counts: [u8; 12], // Count of each type of piece
-    psqt: PieceSquareTable, // Piece-square tables for each piece
+    piece_counts: [u8; 12],       // Count of each type of piece
+    phase: i32,                   // Sum of the phase weights of all pieces
+    eval_params: Arc<EvalParams>, // Weights of the evaluation terms
+    psq_score: [Score; 2],        // Piece-square table score of each side
 
     // Search information
     zobrist_hash: u64, // Zobrist hash of the position
@@ -42,6 +60,13 @@ impl BoardState {
         parse_fen(fen_str.unwrap_or(DEFAULT_FEN), zobrist)
     }
 
+    /// Returns the position as a FEN string
+    #[inline]
+    pub fn to_fen(&self) -> String {
+        to_fen(self)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     #[inline]
     pub fn init(
         piece_bb: [Bitboard; 12],
@@ -53,13 +78,12 @@ impl BoardState {
         castling_rights: u8,
         half_moves: u8,
         full_moves: u32,
-        material: [i32; 2],
         piece_counts: [u8; 12],
-        psqt: PieceSquareTable,
+        eval_params: Arc<EvalParams>,
         zobrist_hash: u64,
         pawn_hash: u64,
     ) -> BoardState {
-        BoardState {
+        let mut board_state = BoardState {
             piece_bb,
             position_bb,
             board,
@@ -69,15 +93,19 @@ impl BoardState {
             castling_rights,
             half_moves,
             full_moves,
-            material,
             piece_counts,
-            psqt,
+            phase: 0,
+            eval_params,
+            psq_score: [Score::ZERO; 2],
             zobrist_hash,
             pawn_hash,
-        }
+        };
+        board_state.phase = board_state.compute_phase();
+        board_state.psq_score = board_state.compute_psq_score();
+        board_state
     }
 
-    #[inline(always)]
+    #[cfg(test)]
     pub fn get_board(&self) -> &[Option<Piece>; 64] {
         &self.board
     }
@@ -97,11 +125,6 @@ impl BoardState {
         self.position_bb[0].combine(self.position_bb[1])
     }
 
-    #[inline(always)]
-    pub fn get_piece_lists(&self) -> &[Vec<Square>; 12] {
-        &self.piece_lists
-    }
-
     #[inline(always)]
     pub fn get_piece_squares(&self, piece: Piece) -> &[Square] {
         &self.piece_lists[piece]
@@ -143,74 +166,104 @@ impl BoardState {
     }
 
     #[inline(always)]
-    pub fn get_material(&self) -> &[i32; 2] {
-        &self.material
+    pub fn get_piece_count(&self, piece: Piece) -> u8 {
+        self.piece_counts[piece]
     }
 
     #[inline(always)]
-    pub fn get_material_difference(&self) -> i32 {
-        self.material[Color::White] - self.material[Color::Black]
+    pub fn get_num_pieces(&self) -> u8 {
+        self.piece_counts.iter().sum()
     }
 
     #[inline(always)]
-    pub fn get_white_material(&self) -> i32 {
-        self.material[0]
+    pub fn get_zobrist_hash(&self) -> u64 {
+        self.zobrist_hash
     }
 
     #[inline(always)]
-    pub fn get_black_material(&self) -> i32 {
-        self.material[1]
+    pub fn get_pawn_hash(&self) -> u64 {
+        self.pawn_hash
     }
 
     #[inline(always)]
-    pub fn get_piece_counts(&self) -> &[u8; 12] {
-        &self.piece_counts
+    pub fn get_piece_on_square(&self, sq: Square) -> Option<Piece> {
+        self.board[sq]
     }
 
     #[inline(always)]
-    pub fn get_piece_count(&self, piece: Piece) -> u8 {
-        self.piece_counts[piece]
+    pub fn get_psq_value(&self, piece: Piece, sq: Square) -> Score {
+        self.eval_params.psqt.get_value(piece, sq)
     }
 
     #[inline(always)]
-    pub fn get_num_pieces(&self) -> u8 {
-        self.piece_counts.iter().sum()
+    pub fn get_eval_params(&self) -> &Arc<EvalParams> {
+        &self.eval_params
     }
 
-    #[inline(always)]
-    pub fn get_zobrist_hash(&self) -> u64 {
-        self.zobrist_hash
+    /// Evaluates the position with `eval_params` from now on
+    pub fn set_eval_params(&mut self, eval_params: Arc<EvalParams>) {
+        self.eval_params = eval_params;
+        self.psq_score = self.compute_psq_score();
     }
 
+    /// Value of `piece_type` in the evaluation at the current game phase, used to weigh exchanges.
+    /// The king is worth more than any exchange can win, so it is never traded.
     #[inline(always)]
-    pub fn get_pawn_hash(&self) -> u64 {
-        self.pawn_hash
+    pub fn get_piece_value(&self, piece_type: PieceType) -> i32 {
+        match piece_type {
+            PieceType::King => KING_VALUE,
+            _ => self.eval_params.get_piece_value(piece_type).taper(self.get_phase()),
+        }
     }
 
-    #[inline(always)]
-    pub fn get_piece_on_square(&self, sq: Square) -> Option<Piece> {
-        self.board[sq]
+    /// Sums the phase weights of all pieces from scratch
+    fn compute_phase(&self) -> i32 {
+        PIECES
+            .iter()
+            .map(|&piece| piece.get_phase_weight() * self.piece_counts[piece] as i32)
+            .sum()
+    }
+
+    /// Sums the piece-square table score of every piece of each side from scratch
+    fn compute_psq_score(&self) -> [Score; 2] {
+        let mut score = [Score::ZERO; 2];
+        for (piece, sqs) in self.piece_lists.iter().enumerate() {
+            let piece = Piece::from_index(piece);
+            for &sq in sqs {
+                score[piece.get_color()] += self.get_psq_value(piece, sq);
+            }
+        }
+        score
     }
 
+    /// Adds (or removes, if `sign` is -1) the piece-square table value of `piece` on `sq`
     #[inline(always)]
-    pub fn get_psq_value(&self, piece: Piece, sq: Square, phase: Phase) -> i32 {
-        self.psqt.get_value(phase, piece, sq)
+    fn update_psq_score(&mut self, piece: Piece, sq: Square, sign: i32) {
+        self.psq_score[piece.get_color()] += self.eval_params.psqt.get_value(piece, sq) * sign;
     }
 
+    /// Passes the turn without moving. The en passant square is cleared since it belonged to the skipped move.
     #[inline]
-    pub fn make_null_move(&mut self, zobrist: &ZobristHasher) {
+    pub fn make_null_move(&mut self, zobrist: &ZobristHasher) -> UndoInfo {
+        let undo = self.undo_info();
+        zobrist.update_zobrist_hash_en_passant(&mut self.zobrist_hash, self.en_passant, None);
+        self.en_passant = None;
         self.side = self.side.opposite();
         zobrist.update_zobrist_hash_side(&mut self.zobrist_hash);
+        undo
     }
 
     #[inline]
-    pub fn unmake_null_move(&mut self, zobrist: &ZobristHasher) {
+    pub fn unmake_null_move(&mut self, undo: &UndoInfo) {
         self.side = self.side.opposite();
-        zobrist.update_zobrist_hash_side(&mut self.zobrist_hash);
+        self.en_passant = undo.en_passant;
+        self.zobrist_hash = undo.zobrist_hash;
     }
 
+    /// Makes a move in place, returning what `unmake_move` needs to restore the position
     #[inline]
-    pub fn make_move(&mut self, c_move: Move, zobrist: &ZobristHasher) {
+    pub fn make_move(&mut self, c_move: Move, zobrist: &ZobristHasher) -> UndoInfo {
+        let mut undo = self.undo_info();
         let piece = c_move.get_piece();
         let from = c_move.get_from();
         let to = c_move.get_to();
@@ -219,62 +272,48 @@ impl BoardState {
         let is_castle = c_move.is_castling();
         let is_double_push = c_move.is_double_pawn_push();
 
-        self.update_bitboards(piece, from, Some(to));
-        self.update_board(piece, from, to);
-        self.update_piece_lists(piece, from, Some(to));
-
-        zobrist.update_zobrist_hash_move(&mut self.zobrist_hash, &mut self.pawn_hash, piece, from, to);
-
         if let Some(captured_piece) = c_move.get_capture() {
-            if is_en_passant {
-                let sq = match self.get_opposite_side() {
-                    Color::White => self.en_passant.unwrap().move_up(1),
-                    Color::Black => self.en_passant.unwrap().move_down(1),
-                };
-
-                self.update_bitboards(captured_piece, sq, None);
-                self.board[sq] = None;
-                self.update_piece_lists(captured_piece, sq, None);
-                zobrist.update_zobrist_hash_capture(&mut self.zobrist_hash, &mut self.pawn_hash, captured_piece, sq);
+            let sq = if is_en_passant {
+                en_passant_capture_square(to, piece.get_color())
             } else {
-                self.update_bitboards(captured_piece, to, None);
-                self.update_piece_lists(captured_piece, to, None);
-                zobrist.update_zobrist_hash_capture(&mut self.zobrist_hash, &mut self.pawn_hash, captured_piece, to);
-            }
+                to
+            };
+
+            self.update_bitboards(captured_piece, sq, None);
+            self.board[sq] = None;
+            undo.captured_index = self.remove_from_piece_list(captured_piece, sq);
+            zobrist.update_zobrist_hash_capture(&mut self.zobrist_hash, &mut self.pawn_hash, captured_piece, sq);
 
             self.piece_counts[captured_piece] -= 1;
-            self.material[captured_piece.get_color()] -= captured_piece.get_value();
+            self.phase -= captured_piece.get_phase_weight();
+            self.update_psq_score(captured_piece, sq, -1);
         }
 
+        self.update_bitboards(piece, from, Some(to));
+        self.update_board(piece, from, to);
+        self.move_in_piece_list(piece, from, to);
+        self.update_psq_score(piece, from, -1);
+        self.update_psq_score(piece, to, 1);
+        zobrist.update_zobrist_hash_move(&mut self.zobrist_hash, &mut self.pawn_hash, piece, from, to);
+
         if is_castle {
             let rook = Piece::new(piece.get_color(), PieceType::Rook);
-            let (rook_from, rook_to) = match to {
-                Square::C1 => (Square::A1, Square::D1),
-                Square::C8 => (Square::A8, Square::D8),
-                Square::G1 => (Square::H1, Square::F1),
-                Square::G8 => (Square::H8, Square::F8),
-                _ => panic!("Invalid castle move"),
-            };
+            let (rook_from, rook_to) = castle_rook_squares(to);
 
             self.update_bitboards(rook, rook_from, Some(rook_to));
             self.update_board(rook, rook_from, rook_to);
-            self.update_piece_lists(rook, rook_from, Some(rook_to));
+            self.move_in_piece_list(rook, rook_from, rook_to);
+            self.update_psq_score(rook, rook_from, -1);
+            self.update_psq_score(rook, rook_to, 1);
 
             zobrist.update_zobrist_hash_move(&mut self.zobrist_hash, &mut self.pawn_hash, rook, rook_from, rook_to);
         }
 
-        // Update castling rights
-        if piece.get_type() == PieceType::King {
-            let old_rights = self.castling_rights;
-            self.castling_rights &= if self.side == Color::White { 0b1100 } else { 0b0011 };
-            zobrist.update_zobrist_hash_castling(&mut self.zobrist_hash, old_rights, self.castling_rights);
-        } else if piece.get_type() == PieceType::Rook {
-            const ROOK_START_SQUARES: [Square; 4] = [Square::A1, Square::H1, Square::A8, Square::H8];
-            if let Some(i) = ROOK_START_SQUARES.iter().position(|&sq| sq == from) {
-                let old_rights = self.castling_rights;
-                self.castling_rights &= !(1 << i);
-                zobrist.update_zobrist_hash_castling(&mut self.zobrist_hash, old_rights, self.castling_rights);
-            }
+        // Update castling rights: moving the king or a rook, or capturing a rook on its start square
+        let new_rights = self.castling_rights & castling_rights_mask(from) & castling_rights_mask(to);
+        if new_rights != self.castling_rights {
+            zobrist.update_zobrist_hash_castling(&mut self.zobrist_hash, self.castling_rights, new_rights);
+            self.castling_rights = new_rights;
         }
 
         if is_double_push {
@@ -291,16 +330,16 @@ impl BoardState {
         if let Some(promotion) = promotion {
             let pawn = Piece::new(piece.get_color(), PieceType::Pawn);
             self.update_bitboards(pawn, to, None);
-            self.update_piece_lists(pawn, to, None);
+            undo.promoted_pawn_index = self.remove_from_piece_list(pawn, to);
 
-            self.update_bitboards(promotion, to, Some(to));
-            self.update_board(promotion, to, to);
-            self.update_piece_lists(promotion, to, Some(to));
+            self.put_piece(promotion, to);
+            self.piece_lists[promotion].push(to);
 
             self.piece_counts[pawn] -= 1;
-            self.material[pawn.get_color()] -= pawn.get_value();
             self.piece_counts[promotion] += 1;
-            self.material[promotion.get_color()] += promotion.get_value();
+            self.phase += promotion.get_phase_weight();
+            self.update_psq_score(pawn, to, -1);
+            self.update_psq_score(promotion, to, 1);
 
             zobrist.update_zobrist_hash_promotion(&mut self.zobrist_hash, &mut self.pawn_hash, pawn, promotion, to);
         }
@@ -308,7 +347,7 @@ impl BoardState {
         if piece.get_type() == PieceType::Pawn || c_move.is_capture() {
             self.half_moves = 0;
         } else {
-            self.half_moves += 1;
+            self.half_moves = self.half_moves.saturating_add(1);
         }
 
         if self.side == Color::Black {
@@ -316,6 +355,92 @@ impl BoardState {
         }
 
         self.side = self.side.opposite();
+        zobrist.update_zobrist_hash_side(&mut self.zobrist_hash);
+        debug_assert_eq!(self.phase, self.compute_phase(), "phase out of sync after {}", c_move);
+        debug_assert_eq!(
+            self.psq_score,
+            self.compute_psq_score(),
+            "piece-square score out of sync after {}",
+            c_move
+        );
+        undo
+    }
+
+    /// Takes back `c_move`, which must be the last move made, using the record returned by `make_move`
+    #[inline]
+    pub fn unmake_move(&mut self, c_move: Move, undo: &UndoInfo) {
+        let piece = c_move.get_piece();
+        let from = c_move.get_from();
+        let to = c_move.get_to();
+
+        self.side = self.side.opposite();
+        if self.side == Color::Black {
+            self.full_moves -= 1;
+        }
+
+        if let Some(promotion) = c_move.get_promotion() {
+            let pawn = Piece::new(piece.get_color(), PieceType::Pawn);
+            self.update_bitboards(promotion, to, None);
+            let promoted_sq = self.piece_lists[promotion].pop();
+            debug_assert_eq!(promoted_sq, Some(to));
+
+            self.put_piece(pawn, to);
+            self.piece_lists[pawn].insert(undo.promoted_pawn_index as usize, to);
+
+            self.piece_counts[promotion] -= 1;
+            self.phase -= promotion.get_phase_weight();
+            self.piece_counts[pawn] += 1;
+        }
+
+        self.update_bitboards(piece, to, Some(from));
+        self.board[to] = None;
+        self.board[from] = Some(piece);
+        self.move_in_piece_list(piece, to, from);
+
+        if c_move.is_castling() {
+            let rook = Piece::new(piece.get_color(), PieceType::Rook);
+            let (rook_from, rook_to) = castle_rook_squares(to);
+
+            self.update_bitboards(rook, rook_to, Some(rook_from));
+            self.update_board(rook, rook_to, rook_from);
+            self.move_in_piece_list(rook, rook_to, rook_from);
+        }
+
+        if let Some(captured_piece) = c_move.get_capture() {
+            let sq = if c_move.is_en_passant() {
+                en_passant_capture_square(to, piece.get_color())
+            } else {
+                to
+            };
+
+            self.put_piece(captured_piece, sq);
+            self.piece_lists[captured_piece].insert(undo.captured_index as usize, sq);
+
+            self.piece_counts[captured_piece] += 1;
+            self.phase += captured_piece.get_phase_weight();
+        }
+
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.half_moves = undo.half_moves;
+        self.zobrist_hash = undo.zobrist_hash;
+        self.pawn_hash = undo.pawn_hash;
+        self.psq_score = undo.psq_score;
+    }
+
+    /// Snapshot of the irreversible state, taken before a move is made
+    #[inline(always)]
+    fn undo_info(&self) -> UndoInfo {
+        UndoInfo {
+            captured_index: 0,
+            promoted_pawn_index: 0,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            half_moves: self.half_moves,
+            zobrist_hash: self.zobrist_hash,
+            pawn_hash: self.pawn_hash,
+            psq_score: self.psq_score,
+        }
     }
 
     #[inline(always)]
@@ -338,114 +463,285 @@ impl BoardState {
         self.board[to] = Some(piece);
     }
 
+    /// Places `piece` on `sq` in the bitboards and board array
     #[inline(always)]
-    fn update_piece_lists(&mut self, piece: Piece, from: Square, to: Option<Square>) {
-        if let Some(from_index) = self.piece_lists[piece].iter().position(|&x| x == from) {
-            self.piece_lists[piece].remove(from_index);
-        }
+    fn put_piece(&mut self, piece: Piece, sq: Square) {
+        self.piece_bb[piece].set_square(sq);
+        self.position_bb[piece.get_color()].set_square(sq);
+        self.board[sq] = Some(piece);
+    }
 
-        if let Some(to) = to {
-            self.piece_lists[piece].push(to);
+    /// Moves a square within a piece list, keeping the order of the list
+    #[inline(always)]
+    fn move_in_piece_list(&mut self, piece: Piece, from: Square, to: Square) {
+        if let Some(entry) = self.piece_lists[piece].iter_mut().find(|sq| **sq == from) {
+            *entry = to;
         }
     }
 
-    #[inline]
-    pub fn get_hanging_bb(&self, side: Color, pregen_attacks: &PregenAttacks) -> Bitboard {
-        let enemy_color = side.opposite();
-        let combined_bb = self.get_combined_bb();
-
-        let mut enemy_attack_bb = Bitboard::new_empty();
-        let mut my_defense_bb = Bitboard::new_empty();
-
-        for piece_type in PIECE_TYPES {
-            let enemy_piece = Piece::new(enemy_color, piece_type);
-            let my_piece = Piece::new(side, piece_type);
+    /// Removes a square from a piece list, returning its index so it can be reinserted in place
+    #[inline(always)]
+    fn remove_from_piece_list(&mut self, piece: Piece, sq: Square) -> u8 {
+        let index = self.piece_lists[piece]
+            .iter()
+            .position(|&x| x == sq)
+            .expect("piece missing from its piece list");
+        self.piece_lists[piece].remove(index);
+        index as u8
+    }
 
-            let enemy_piece_squares = &self.piece_lists[enemy_piece];
-            let my_piece_squares = &self.piece_lists[my_piece];
+    /// Static exchange evaluation: checks if `c_move` gains at least `threshold` once every capture
+    /// on its destination square has been played out, each side recapturing with its least valuable piece
+    #[inline]
+    pub fn see(&self, c_move: Move, threshold: i32, pregen_attacks: &PregenAttacks) -> bool {
+        self.see_value(c_move, pregen_attacks) >= threshold
+    }
 
-            for &sq in enemy_piece_squares {
-                let attacks = match piece_type {
-                    PieceType::Pawn => pregen_attacks.get_pawn_attacks(enemy_color, sq),
-                    PieceType::Knight => pregen_attacks.get_knight_attacks(sq),
-                    PieceType::Bishop => pregen_attacks.get_bishop_attacks(sq, &combined_bb),
-                    PieceType::Rook => pregen_attacks.get_rook_attacks(sq, &combined_bb),
-                    PieceType::Queen => pregen_attacks.get_queen_attacks(sq, &combined_bb),
-                    PieceType::King => pregen_attacks.get_king_attacks(sq),
-                };
+    /// Material won (or lost, if negative) by `c_move` and the exchange it starts on its destination square
+    pub fn see_value(&self, c_move: Move, pregen_attacks: &PregenAttacks) -> i32 {
+        if c_move.is_castling() {
+            return 0;
+        }
+        let piece = c_move.get_piece();
+        let to = c_move.get_to();
+        let mut occupancy = self.get_combined_bb().diff(c_move.get_from().to_bitboard());
+        if c_move.is_en_passant() {
+            occupancy = occupancy.diff(en_passant_capture_square(to, piece.get_color()).to_bitboard());
+        }
 
-                enemy_attack_bb = enemy_attack_bb.combine(attacks);
-            }
+        let mut gain = c_move.get_capture().map_or(0, |p| self.get_piece_value(p.get_type()));
+        let mut moved = piece;
+        if let Some(promotion) = c_move.get_promotion() {
+            gain += self.get_piece_value(promotion.get_type()) - self.get_piece_value(PieceType::Pawn);
+            moved = promotion;
+        }
+        self.exchange(to, gain, moved, piece.get_color().opposite(), occupancy, pregen_attacks)
+    }
 
-            for &sq in my_piece_squares {
-                let defenses = match piece_type {
-                    PieceType::Pawn => pregen_attacks.get_pawn_attacks(side, sq),
-                    PieceType::Knight => pregen_attacks.get_knight_attacks(sq),
-                    PieceType::Bishop => pregen_attacks.get_bishop_attacks(sq, &combined_bb),
-                    PieceType::Rook => pregen_attacks.get_rook_attacks(sq, &combined_bb),
-                    PieceType::Queen => pregen_attacks.get_queen_attacks(sq, &combined_bb),
-                    PieceType::King => pregen_attacks.get_king_attacks(sq),
-                };
+    /// Plays out the captures on `sq`, starting with `side` taking `target` after a first capture worth `first_gain`.
+    /// Removed pieces are cleared from `occupancy`, so sliders behind them join the exchange.
+    fn exchange(
+        &self,
+        sq: Square,
+        first_gain: i32,
+        mut target: Piece,
+        mut side: Color,
+        mut occupancy: Bitboard,
+        pregen_attacks: &PregenAttacks,
+    ) -> i32 {
+        let mut gains = [0; 32];
+        gains[0] = first_gain;
+        let mut depth = 0;
+
+        while depth + 1 < gains.len() {
+            let Some((attacker_sq, attacker)) = self.get_least_valuable_attacker(sq, side, occupancy, pregen_attacks) else {
+                break;
+            };
+            depth += 1;
+            gains[depth] = self.get_piece_value(target.get_type()) - gains[depth - 1];
+            target = attacker;
+            occupancy = occupancy.diff(attacker_sq.to_bitboard());
+            side = side.opposite();
+        }
 
-                my_defense_bb = my_defense_bb.combine(defenses);
-            }
+        // Each side may stop capturing when continuing would lose material
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
         }
+        gains[0]
+    }
 
-        enemy_attack_bb = enemy_attack_bb.intersect(self.position_bb[side]);
-        enemy_attack_bb
-            .intersect(my_defense_bb.invert())
-            .intersect(self.piece_bb[Piece::new(side, PieceType::King)].invert())
+    /// Returns the least valuable piece of color `by` attacking `sq` through `occupancy`
+    #[inline]
+    fn get_least_valuable_attacker(&self, sq: Square, by: Color, occupancy: Bitboard, pregen_attacks: &PregenAttacks) -> Option<(Square, Piece)> {
+        let attackers = self.get_attackers(sq, by, occupancy, pregen_attacks);
+        if attackers.is_empty() {
+            return None;
+        }
+        PIECE_TYPES.iter().find_map(|&piece_type| {
+            let piece = Piece::new(by, piece_type);
+            let pieces = attackers.intersect(self.piece_bb[piece]);
+            (!pieces.is_empty()).then(|| (pieces.get_ls_square(), piece))
+        })
     }
 
+    /// Value of the most valuable piece of `side` the opponent can win by capturing it, judged by static exchange evaluation
     #[inline]
-    pub fn count_double_pawns(&self, side: Color) -> i32 {
-        let piece = Piece::new(side, PieceType::Pawn);
-        FILES_BB
-            .iter()
-            .filter(|&file| self.piece_bb[piece].intersect(*file).count_squares() > 1)
-            .count() as i32
+    pub fn get_threatened_value(&self, side: Color, pregen_attacks: &PregenAttacks) -> i32 {
+        let enemy = side.opposite();
+        let occupancy = self.get_combined_bb();
+        let targets = self.position_bb[side].diff(self.piece_bb[Piece::new(side, PieceType::King)]);
+
+        let mut threat = 0;
+        for sq in targets.get_occupied_squares() {
+            let (Some(target), Some((attacker_sq, attacker))) =
+                (self.board[sq], self.get_least_valuable_attacker(sq, enemy, occupancy, pregen_attacks))
+            else {
+                continue;
+            };
+            let value = self.exchange(
+                sq,
+                self.get_piece_value(target.get_type()),
+                attacker,
+                side,
+                occupancy.diff(attacker_sq.to_bitboard()),
+                pregen_attacks,
+            );
+            threat = threat.max(value);
+        }
+        threat
     }
 
     #[inline]
     pub fn is_check(&self, side: Color, pregen_attacks: &PregenAttacks) -> bool {
         let king_sq = self.piece_lists[Piece::new(side, PieceType::King)][0];
-        let enemy_color = side.opposite();
-        let combined_bb = self.get_combined_bb();
-
-        for piece_type in [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
-            let piece = Piece::new(enemy_color, piece_type);
-            let piece_squares = &self.piece_lists[piece];
+        self.is_square_attacked(king_sq, side.opposite(), pregen_attacks)
+    }
 
-            for &sq in piece_squares {
-                let attacks = match piece_type {
-                    PieceType::Pawn => pregen_attacks.get_pawn_attacks(enemy_color, sq),
-                    PieceType::Knight => pregen_attacks.get_knight_attacks(sq),
-                    PieceType::Bishop => pregen_attacks.get_bishop_attacks(sq, &combined_bb),
-                    PieceType::Rook => pregen_attacks.get_rook_attacks(sq, &combined_bb),
-                    PieceType::Queen => pregen_attacks.get_queen_attacks(sq, &combined_bb),
-                    PieceType::King => continue,
-                };
+    /// Checks if neither side can possibly mate: lone kings, a single minor piece, or only bishops on squares of one colour
+    pub fn is_insufficient_material(&self) -> bool {
+        let count = |piece_type| self.piece_counts[Piece::new(Color::White, piece_type)] + self.piece_counts[Piece::new(Color::Black, piece_type)];
+        if count(PieceType::Pawn) + count(PieceType::Rook) + count(PieceType::Queen) > 0 {
+            return false;
+        }
+        match (count(PieceType::Knight), count(PieceType::Bishop)) {
+            (0, 0) | (1, 0) => true,
+            (0, _) => {
+                let mut bishops = self.piece_lists[Piece::new(Color::White, PieceType::Bishop)]
+                    .iter()
+                    .chain(&self.piece_lists[Piece::new(Color::Black, PieceType::Bishop)])
+                    .map(|sq| sq.is_dark());
+                let first = bishops.next();
+                bishops.all(|dark| Some(dark) == first)
+            }
+            _ => false,
+        }
+    }
 
-                if attacks.is_occupied(king_sq) {
-                    return true;
-                }
+    /// Checks if `side` could mate by some series of legal moves, however unlikely.
+    /// A lone minor piece needs the opponent's own pieces to block its king, so the opponent's material counts too:
+    /// a knight can mate when the opponent has a pawn, knight, bishop or rook, and bishops of one colour when it has a pawn,
+    /// a knight or a bishop on squares of the other colour.
+    pub fn can_mate(&self, side: Color) -> bool {
+        let count = |color, piece_type| self.get_piece_count(Piece::new(color, piece_type));
+        let bishop_colors = |color| {
+            let bishops = &self.piece_lists[Piece::new(color, PieceType::Bishop)];
+            (bishops.iter().any(|sq| sq.is_dark()), bishops.iter().any(|sq| !sq.is_dark()))
+        };
+        let other = side.opposite();
+        if count(side, PieceType::Pawn) + count(side, PieceType::Rook) + count(side, PieceType::Queen) > 0 {
+            return true;
+        }
+        match (count(side, PieceType::Knight), count(side, PieceType::Bishop)) {
+            (0, 0) => false,
+            (1, 0) => {
+                count(other, PieceType::Pawn) + count(other, PieceType::Knight) + count(other, PieceType::Bishop) + count(other, PieceType::Rook) > 0
             }
+            (0, _) => {
+                let (dark, light) = bishop_colors(side);
+                let (other_dark, other_light) = bishop_colors(other);
+                (dark && light)
+                    || count(other, PieceType::Pawn) + count(other, PieceType::Knight) > 0
+                    || (dark && other_light)
+                    || (light && other_dark)
+            }
+            _ => true,
         }
-        false
     }
 
+    /// Checks if `sq` is attacked by any piece of color `by`
     #[inline]
-    pub fn get_phase(&self) -> Phase {
-        let mut phase = 0;
-        for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
-            phase += self.piece_counts[Piece::new(Color::White, piece_type)] as i32;
-            phase += self.piece_counts[Piece::new(Color::Black, piece_type)] as i32;
+    pub fn is_square_attacked(&self, sq: Square, by: Color, pregen_attacks: &PregenAttacks) -> bool {
+        let combined_bb = self.get_combined_bb();
+        let bishops_queens = self.piece_bb[Piece::new(by, PieceType::Bishop)].combine(self.piece_bb[Piece::new(by, PieceType::Queen)]);
+        let rooks_queens = self.piece_bb[Piece::new(by, PieceType::Rook)].combine(self.piece_bb[Piece::new(by, PieceType::Queen)]);
+
+        // Attacks are symmetric: look from the target square for an attacker of each kind
+        !pregen_attacks
+            .get_pawn_attacks(by.opposite(), sq)
+            .intersect(self.piece_bb[Piece::new(by, PieceType::Pawn)])
+            .is_empty()
+            || !pregen_attacks
+                .get_knight_attacks(sq)
+                .intersect(self.piece_bb[Piece::new(by, PieceType::Knight)])
+                .is_empty()
+            || !pregen_attacks
+                .get_king_attacks(sq)
+                .intersect(self.piece_bb[Piece::new(by, PieceType::King)])
+                .is_empty()
+            || !pregen_attacks.get_bishop_attacks(sq, &combined_bb).intersect(bishops_queens).is_empty()
+            || !pregen_attacks.get_rook_attacks(sq, &combined_bb).intersect(rooks_queens).is_empty()
+    }
+
+    /// Returns the pieces of color `by` attacking `sq`, with sliders seeing through `occupancy`.
+    /// Only attackers that are themselves in `occupancy` are returned, so a piece can be removed by clearing its square.
+    #[inline]
+    pub fn get_attackers(&self, sq: Square, by: Color, occupancy: Bitboard, pregen_attacks: &PregenAttacks) -> Bitboard {
+        let queens = self.piece_bb[Piece::new(by, PieceType::Queen)];
+        let bishops_queens = self.piece_bb[Piece::new(by, PieceType::Bishop)].combine(queens);
+        let rooks_queens = self.piece_bb[Piece::new(by, PieceType::Rook)].combine(queens);
+
+        pregen_attacks
+            .get_pawn_attacks(by.opposite(), sq)
+            .intersect(self.piece_bb[Piece::new(by, PieceType::Pawn)])
+            .combine(
+                pregen_attacks
+                    .get_knight_attacks(sq)
+                    .intersect(self.piece_bb[Piece::new(by, PieceType::Knight)]),
+            )
+            .combine(
+                pregen_attacks
+                    .get_king_attacks(sq)
+                    .intersect(self.piece_bb[Piece::new(by, PieceType::King)]),
+            )
+            .combine(pregen_attacks.get_bishop_attacks(sq, &occupancy).intersect(bishops_queens))
+            .combine(pregen_attacks.get_rook_attacks(sq, &occupancy).intersect(rooks_queens))
+            .intersect(occupancy)
+    }
+
+    /// Checks if the legal move `m` gives check, directly or by discovery, without making it
+    pub fn gives_check(&self, m: Move, pregen_attacks: &PregenAttacks) -> bool {
+        let side = self.get_side();
+        let king_sq = self.piece_lists[Piece::new(side.opposite(), PieceType::King)][0];
+        let (from, to) = (m.get_from(), m.get_to());
+        let mut occupancy = self.get_combined_bb();
+        occupancy.clear_square(from);
+        occupancy.set_square(to);
+
+        if m.is_castling() {
+            // Only the rook can give check, from the square the king passed over
+            let (rook_from, rook_to) = match to.get_file() {
+                File::FG => (to.move_right(1), to.move_left(1)),
+                _ => (to.move_left(2), to.move_right(1)),
+            };
+            occupancy.clear_square(rook_from);
+            occupancy.set_square(rook_to);
+            return pregen_attacks.get_rook_attacks(rook_to, &occupancy).is_occupied(king_sq);
         }
-        if phase > 12 {
-            Phase::Opening
-        } else {
-            Phase::Endgame
+        if m.is_en_passant() {
+            // The captured pawn is beside the destination, on the rank the pawn moved from
+            occupancy.clear_square(Square::from_file_rank(to.get_file(), from.get_rank()));
         }
+
+        let piece_type = m.get_promotion().unwrap_or(m.get_piece()).get_type();
+        let attacks = match piece_type {
+            PieceType::Pawn => pregen_attacks.get_pawn_attacks(side, to),
+            PieceType::Knight => pregen_attacks.get_knight_attacks(to),
+            PieceType::Bishop => pregen_attacks.get_bishop_attacks(to, &occupancy),
+            PieceType::Rook => pregen_attacks.get_rook_attacks(to, &occupancy),
+            PieceType::Queen => pregen_attacks.get_queen_attacks(to, &occupancy),
+            PieceType::King => Bitboard::new_empty(),
+        };
+        // The moved piece is still on `from` in the bitboards, so clearing it from the occupancy leaves only discovered attackers
+        attacks.is_occupied(king_sq) || !self.get_attackers(king_sq, side, occupancy, pregen_attacks).is_empty()
+    }
+
+    /// Returns the game phase, from 0 with only pawns and kings left up to `MAX_PHASE` with all pieces on the board.
+    /// Promotions can push the piece count past the starting position, so the phase is capped.
+    #[inline(always)]
+    pub fn get_phase(&self) -> i32 {
+        self.phase.min(MAX_PHASE)
     }
 
     #[inline]
@@ -471,48 +767,8 @@ impl BoardState {
     }
 
     #[inline]
-    pub fn get_pawn_structure_score(&self, side: Color) -> i32 {
-        let mut score = 0;
-        let piece = Piece::new(side, PieceType::Pawn);
-        let pawn_bb = self.piece_bb[piece];
-
-        // Doubled pawns
-        score -= 10 * self.count_double_pawns(side);
-
-        // Isolated pawns
-        for &file in FILES.iter() {
-            let file_bb = FILES_BB[file];
-            if pawn_bb.intersect(file_bb).is_empty() {
-                continue;
-            }
-            let adjacent_files = match file {
-                File::FA => FILES_BB[File::FB],
-                File::FH => FILES_BB[File::FG],
-                _ => FILES_BB[file.get_prev()].combine(FILES_BB[file.get_next()]),
-            };
-            if pawn_bb.intersect(adjacent_files).is_empty() {
-                score -= 10;
-            }
-        }
-
-        // Passed pawns
-        let enemy_pawn = Piece::new(side.opposite(), PieceType::Pawn);
-        let enemy_pawn_bb = self.piece_bb[enemy_pawn];
-        for sq in pawn_bb.get_occupied_squares() {
-            let file = sq.get_file();
-            let file_bb = FILES_BB[file];
-            let enemy_pawns_in_file = enemy_pawn_bb.intersect(file_bb);
-            if enemy_pawns_in_file.is_empty() {
-                score += 20;
-            }
-        }
-
-        score
-    }
-
-    #[inline]
-    pub fn get_king_safety_score(&self, side: Color, pregen_attacks: &PregenAttacks) -> i32 {
-        let mut score = 0;
+    pub fn get_king_safety_score(&self, side: Color, pregen_attacks: &PregenAttacks, pawns: &PawnEntry) -> Score {
+        let mut score = Score::ZERO;
         let king_sq = self.piece_lists[Piece::new(side, PieceType::King)][0];
         let enemy_color = side.opposite();
         let combined_bb = self.get_combined_bb();
@@ -527,16 +783,13 @@ impl BoardState {
                     _ => continue,
                 };
                 if attacks.is_occupied(king_sq) {
-                    score -= 10;
+                    score += self.eval_params.king_tropism;
                 }
             }
         }
 
         // Pawn shield
         let king_file = king_sq.get_file();
-        let pawn = Piece::new(side, PieceType::Pawn);
-        let pawn_bb = self.piece_bb[pawn];
-
         let adjacent_files = match king_file {
             File::FA => vec![File::FB],
             File::FH => vec![File::FG],
@@ -544,68 +797,86 @@ impl BoardState {
         };
 
         for &file in &adjacent_files {
-            let file_bb = FILES_BB[file];
-            let pawns_in_file = pawn_bb.intersect(file_bb);
-            if !pawns_in_file.is_empty() {
-                score += 5;
+            if pawns.has_pawn_on_file(side, file as usize) {
+                score += self.eval_params.pawn_shield;
             }
         }
 
         score
     }
 
+    /// Evaluates the position from White's point of view, blending the middlegame and endgame scores by the game phase
     #[inline]
     pub fn evaluate(&self, pregen_attacks: &PregenAttacks) -> i32 {
-        let mut score = self.get_material_difference();
-        let side = self.side;
-        let opposite_side = self.get_opposite_side();
-        let phase = self.get_phase();
+        self.evaluate_with(pregen_attacks, &PawnEntry::new(self), &mut NoTrace)
+    }
 
-        // Piece-square table evaluation
-        for (piece, sqs) in self.piece_lists.iter().enumerate() {
-            let piece = Piece::from_index(piece);
-            let color = piece.get_color();
-            for &sq in sqs {
-                score += color.get_factor() * (piece.get_value() + self.get_psq_value(piece, sq, phase));
-            }
-        }
+    /// Same as `evaluate`, with the pawn structure terms looked up in `pawn_table`
+    #[inline]
+    pub fn evaluate_cached(&self, pregen_attacks: &PregenAttacks, pawn_table: &mut PawnTable) -> i32 {
+        self.evaluate_with(pregen_attacks, pawn_table.probe(self), &mut NoTrace)
+    }
+
+    /// Evaluates the position and returns the score of every term; its total is the value returned by `evaluate`
+    pub fn trace_eval(&self, pregen_attacks: &PregenAttacks) -> EvalTrace {
+        let mut trace = EvalTrace::new(self.get_phase());
+        let total = self.evaluate_with(pregen_attacks, &PawnEntry::new(self), &mut trace);
+        debug_assert_eq!(total, trace.get_total());
+        trace
+    }
+
+    /// Computes every evaluation term for both sides and passes it to `tracer`.
+    /// Each term is tapered on its own, so the terms of a trace add up to exactly the returned value.
+    #[inline(always)]
+    fn evaluate_with<T: EvalTracer>(&self, pregen_attacks: &PregenAttacks, pawns: &PawnEntry, tracer: &mut T) -> i32 {
+        let params = &*self.eval_params;
+        let phase = self.get_phase();
+        let mut total = 0;
+        let mut add = |term: EvalTerm, white: Score, black: Score| {
+            tracer.add(term, white, black);
+            total += (white - black).taper(phase);
+        };
+        let per_side = |f: &dyn Fn(Color) -> Score| (f(Color::White), f(Color::Black));
+
+        // Material without the kings, and piece-square tables kept up to date by `make_move`
+        let (white, black) = per_side(&|color| {
+            PIECE_TYPES
+                .iter()
+                .map(|&piece_type| params.get_piece_value(piece_type) * self.piece_counts[Piece::new(color, piece_type)] as i32)
+                .fold(Score::ZERO, |sum, value| sum + value)
+        });
+        add(EvalTerm::Material, white, black);
+        add(EvalTerm::PieceSquare, self.psq_score[Color::White], self.psq_score[Color::Black]);
 
         // Mobility evaluation
-        let mobility = self.get_mobility(side, pregen_attacks) - self.get_mobility(opposite_side, pregen_attacks);
-        score += side.get_factor() * mobility;
+        let (white, black) = per_side(&|color| params.mobility * self.get_mobility(color, pregen_attacks));
+        add(EvalTerm::Mobility, white, black);
 
         // Pawn structure evaluation
-        let pawn_structure = self.get_pawn_structure_score(side) - self.get_pawn_structure_score(opposite_side);
-        score += side.get_factor() * pawn_structure;
+        add(EvalTerm::PawnStructure, pawns.get_score(Color::White), pawns.get_score(Color::Black));
 
         // King safety evaluation
-        let king_safety = self.get_king_safety_score(side, pregen_attacks) - self.get_king_safety_score(opposite_side, pregen_attacks);
-        score += side.get_factor() * king_safety;
+        let (white, black) = per_side(&|color| self.get_king_safety_score(color, pregen_attacks, pawns));
+        add(EvalTerm::KingSafety, white, black);
 
         // Check evaluation
-        if self.is_check(side, pregen_attacks) {
-            score -= side.get_factor() * 100;
-        }
-        if self.is_check(opposite_side, pregen_attacks) {
-            score += side.get_factor() * 100;
-        }
-
-        // Hanging pieces evaluation
-        let hanging_bb = self.get_hanging_bb(side, pregen_attacks);
-        for sq in hanging_bb.get_occupied_squares() {
-            if let Some(piece) = self.board[sq] {
-                score -= side.get_factor() * piece.get_value();
+        let (white, black) = per_side(&|color| {
+            if self.is_check(color, pregen_attacks) {
+                params.check
+            } else {
+                Score::ZERO
             }
-        }
+        });
+        add(EvalTerm::Check, white, black);
 
-        let enemy_hanging = self.get_hanging_bb(opposite_side, pregen_attacks);
-        for sq in enemy_hanging.get_occupied_squares() {
-            if let Some(piece) = self.board[sq] {
-                score += side.get_factor() * piece.get_value();
-            }
-        }
+        // Threats: the most valuable piece each side would lose to a capture sequence
+        let (white, black) = per_side(&|color| {
+            let threatened = self.get_threatened_value(color, pregen_attacks);
+            Score::new(-threatened * params.threats.get_mg() / 100, -threatened * params.threats.get_eg() / 100)
+        });
+        add(EvalTerm::Threats, white, black);
 
-        score
+        total
     }
 
     pub fn print_board(&self) {
@@ -626,15 +897,312 @@ impl BoardState {
 
     pub fn display_info(&self, pregen_attacks: &PregenAttacks) {
         println!("--------------------");
-        println!("Side: {:?}", self.side);
-        println!("Enpas: {:?}", self.en_passant);
-        println!("Cast Perm: {}", self.castling_rights);
-        println!("Fifty Move: {}", self.half_moves);
-        println!("Full Moves: {}", self.full_moves);
-        println!("Zobrist Hash: {}", self.zobrist_hash);
-        println!("Pawn Hash: {}", self.pawn_hash);
+        println!("FEN: {}", self.to_fen());
         println!("Eval: {}", self.evaluate(pregen_attacks));
         println!("--------------------");
         self.print_board();
     }
 }
+
+impl std::fmt::Display for BoardState {
+    /// Formats the position as FEN
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_fen())
+    }
+}
+
+impl std::str::FromStr for BoardState {
+    type Err = String;
+
+    /// Parses a FEN string; every `ZobristHasher` produces the same keys, so the hashes match those of a `GameState`
+    fn from_str(fen: &str) -> Result<Self, Self::Err> {
+        parse_fen(fen, &ZobristHasher::new())
+    }
+}
+
+/// Rook origin and destination for a castling move with the king landing on `king_to`
+#[inline(always)]
+fn castle_rook_squares(king_to: Square) -> (Square, Square) {
+    match king_to {
+        Square::C1 => (Square::A1, Square::D1),
+        Square::C8 => (Square::A8, Square::D8),
+        Square::G1 => (Square::H1, Square::F1),
+        Square::G8 => (Square::H8, Square::F8),
+        _ => panic!("Invalid castle move"),
+    }
+}
+
+/// Square of the pawn captured en passant by a pawn of `color` landing on `to`
+#[inline(always)]
+fn en_passant_capture_square(to: Square, color: Color) -> Square {
+    match color {
+        Color::White => to.move_down(1),
+        Color::Black => to.move_up(1),
+    }
+}
+
+/// Castling rights that survive a move touching `sq` (as the from or to square)
+#[inline(always)]
+fn castling_rights_mask(sq: Square) -> u8 {
+    match sq {
+        Square::A1 => !(CastlePerms::WQC as u8),
+        Square::H1 => !(CastlePerms::WKC as u8),
+        Square::E1 => !(CastlePerms::WKC as u8 | CastlePerms::WQC as u8),
+        Square::A8 => !(CastlePerms::BQC as u8),
+        Square::H8 => !(CastlePerms::BKC as u8),
+        Square::E8 => !(CastlePerms::BKC as u8 | CastlePerms::BQC as u8),
+        _ => 0b1111,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::move_logic::legal_move_gen;
+
+    #[test]
+    fn test_unmake_restores_every_move() {
+        let zobrist = ZobristHasher::new();
+        let pregen_attacks = PregenAttacks::init();
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1",
+        ] {
+            let mut board_state = BoardState::new(Some(fen), &zobrist).unwrap();
+            let before = board_state.clone();
+            let mut moves = Vec::new();
+            legal_move_gen::get_legal_moves(&board_state, &pregen_attacks, &mut moves);
+
+            for m in moves {
+                let undo = board_state.make_move(m, &zobrist);
+                board_state.unmake_move(m, &undo);
+                assert_eq!(board_state, before, "{} in {}", m, fen);
+            }
+        }
+    }
+
+    #[test]
+    fn test_make_move_hashes_match_fresh_position() {
+        let zobrist = ZobristHasher::new();
+        let pregen_attacks = PregenAttacks::init();
+        let mut board_state = BoardState::new(Some("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"), &zobrist).unwrap();
+        let mut moves = Vec::new();
+        legal_move_gen::get_legal_moves(&board_state, &pregen_attacks, &mut moves);
+
+        // Castling moves the king and rook but must flip the side to move only once
+        let castle = moves.into_iter().find(|m| m.to_string() == "e1g1").unwrap();
+        board_state.make_move(castle, &zobrist);
+        let expected = BoardState::new(Some("r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1"), &zobrist).unwrap();
+        assert_eq!(board_state.get_zobrist_hash(), expected.get_zobrist_hash());
+        assert_eq!(board_state.get_pawn_hash(), expected.get_pawn_hash());
+    }
+
+    #[test]
+    fn test_eval_terms_are_updated_incrementally() {
+        let zobrist = ZobristHasher::new();
+        let pregen_attacks = PregenAttacks::init();
+        assert_eq!(BoardState::new(None, &zobrist).unwrap().get_phase(), MAX_PHASE);
+        assert_eq!(
+            BoardState::new(Some("4k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1"), &zobrist)
+                .unwrap()
+                .get_phase(),
+            0
+        );
+
+        // Castling, captures and promotions of every kind
+        let fen = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R3K2R w KQkq - 0 1";
+        let board_state = BoardState::new(Some(fen), &zobrist).unwrap();
+        let mut moves = Vec::new();
+        legal_move_gen::get_legal_moves(&board_state, &pregen_attacks, &mut moves);
+        for m in moves {
+            let mut after = board_state.clone();
+            after.make_move(m, &zobrist);
+            let expected = BoardState::new(Some(&after.to_fen()), &zobrist).unwrap();
+            assert_eq!(after.phase, expected.phase, "{} in {}", m, fen);
+            assert_eq!(after.psq_score, expected.psq_score, "{} in {}", m, fen);
+        }
+    }
+
+    #[test]
+    fn test_eval_trace_adds_up_to_evaluate() {
+        let zobrist = ZobristHasher::new();
+        let pregen_attacks = PregenAttacks::init();
+        let mut pawn_table = PawnTable::default();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/8/8/8/8/8/4Q3/4K3 b - - 0 1",
+        ] {
+            let board_state = BoardState::new(Some(fen), &zobrist).unwrap();
+            let trace = board_state.trace_eval(&pregen_attacks);
+            assert_eq!(trace.get_total(), board_state.evaluate(&pregen_attacks), "{}", fen);
+            assert_eq!(
+                trace.get_total(),
+                board_state.evaluate_cached(&pregen_attacks, &mut pawn_table),
+                "{}",
+                fen
+            );
+            assert_eq!(trace.get_phase(), board_state.get_phase());
+        }
+
+        // Black is a queen down and in check
+        let board_state = BoardState::new(Some("4k3/8/8/8/8/8/4Q3/4K3 b - - 0 1"), &zobrist).unwrap();
+        let trace = board_state.trace_eval(&pregen_attacks);
+        assert_eq!(trace.get_score(EvalTerm::Material, Color::White), Score::new(QUEEN_VALUE, QUEEN_VALUE));
+        assert_eq!(trace.get_score(EvalTerm::Material, Color::Black), Score::ZERO);
+        assert_eq!(trace.get_value(EvalTerm::Check), 100);
+    }
+
+    #[test]
+    fn test_null_move_clears_en_passant() {
+        let zobrist = ZobristHasher::new();
+        let mut board_state = BoardState::new(Some("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1"), &zobrist).unwrap();
+        let before = board_state.clone();
+
+        let undo = board_state.make_null_move(&zobrist);
+        assert_eq!(board_state.get_en_passant(), None);
+        let expected = BoardState::new(Some("4k3/8/8/8/3pP3/8/8/4K3 w - - 0 1"), &zobrist).unwrap();
+        assert_eq!(board_state.get_zobrist_hash(), expected.get_zobrist_hash());
+
+        board_state.unmake_null_move(&undo);
+        assert_eq!(board_state, before);
+    }
+
+    fn find_move(board_state: &BoardState, pregen_attacks: &PregenAttacks, s: &str) -> Move {
+        let mut moves = Vec::new();
+        legal_move_gen::get_legal_moves(board_state, pregen_attacks, &mut moves);
+        moves.into_iter().find(|m| m.to_string() == s).unwrap()
+    }
+
+    #[test]
+    fn test_see() {
+        let zobrist = ZobristHasher::new();
+        let pregen_attacks = PregenAttacks::init();
+        let see_value = |fen: &str, m: &str| {
+            let board_state = BoardState::new(Some(fen), &zobrist).unwrap();
+            board_state.see_value(find_move(&board_state, &pregen_attacks, m), &pregen_attacks)
+        };
+
+        // Undefended pawn
+        assert_eq!(see_value("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), PAWN_VALUE);
+        // Defended pawn, with the queen x-raying through the bishop
+        assert_eq!(
+            see_value("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5"),
+            PAWN_VALUE - KNIGHT_VALUE
+        );
+        // Knight takes a pawn defended once, backed up by a rook behind the other rook
+        assert_eq!(see_value("4k3/8/3p4/4p3/8/5N2/8/4K3 w - - 0 1", "f3e5"), PAWN_VALUE - KNIGHT_VALUE);
+        assert_eq!(see_value("4k3/4r3/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5"), PAWN_VALUE);
+        // A quiet move onto a square attacked by a pawn loses the piece
+        assert_eq!(see_value("4k3/8/8/3p4/8/8/8/4KQ2 w - - 0 1", "f1c4"), -QUEEN_VALUE);
+        assert_eq!(see_value("4k3/8/8/3p4/8/8/8/4KQ2 w - - 0 1", "f1f5"), 0);
+        // The king only recaptures when the square is no longer defended
+        assert_eq!(see_value("8/8/4k3/3p4/8/8/8/3RK3 w - - 0 1", "d1d5"), PAWN_VALUE - ROOK_VALUE);
+        assert_eq!(see_value("8/8/4k3/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), PAWN_VALUE);
+
+        let board_state = BoardState::new(Some("4k3/8/8/3p4/8/8/8/4KQ2 w - - 0 1"), &zobrist).unwrap();
+        let m = find_move(&board_state, &pregen_attacks, "f1c4");
+        assert!(board_state.see(m, -QUEEN_VALUE, &pregen_attacks));
+        assert!(!board_state.see(m, 0, &pregen_attacks));
+    }
+
+    #[test]
+    fn test_gives_check() {
+        let zobrist = ZobristHasher::new();
+        let pregen_attacks = PregenAttacks::init();
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            // Discovered checks by the bishop, rook and a pawn push
+            "4k3/8/8/8/1B6/2N5/3P4/4RK2 w - - 0 1",
+            "3k4/8/3P4/8/3R4/8/8/4K3 w - - 0 1",
+            // Checks by promotions, a discovery through an en passant capture and castling
+            "2rk4/1P6/8/8/8/8/8/4K3 w - - 0 1",
+            "8/8/8/R2pP2k/8/8/8/4K3 w - d6 0 1",
+            "5k2/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+            "3k4/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+        ] {
+            let board_state = BoardState::new(Some(fen), &zobrist).unwrap();
+            let mut moves = Vec::new();
+            legal_move_gen::get_legal_moves(&board_state, &pregen_attacks, &mut moves);
+
+            for m in moves {
+                let mut after = board_state.clone();
+                after.make_move(m, &zobrist);
+                let king_sq = after.piece_lists[Piece::new(after.get_side(), PieceType::King)][0];
+                let expected = after.is_square_attacked(king_sq, board_state.get_side(), &pregen_attacks);
+                assert_eq!(board_state.gives_check(m, &pregen_attacks), expected, "{} in {}", m, fen);
+            }
+        }
+    }
+
+    #[test]
+    fn test_threatened_value() {
+        let zobrist = ZobristHasher::new();
+        let pregen_attacks = PregenAttacks::init();
+
+        // A defended knight attacked by a pawn still loses material
+        let board_state = BoardState::new(Some("4k3/8/8/2p5/3N4/4P3/8/4K3 w - - 0 1"), &zobrist).unwrap();
+        assert_eq!(board_state.get_threatened_value(Color::White, &pregen_attacks), KNIGHT_VALUE - PAWN_VALUE);
+        assert_eq!(board_state.get_threatened_value(Color::Black, &pregen_attacks), 0);
+
+        // A defended knight attacked by a rook is safe
+        let board_state = BoardState::new(Some("3rk3/8/8/8/3N4/4P3/8/4K3 w - - 0 1"), &zobrist).unwrap();
+        assert_eq!(board_state.get_threatened_value(Color::White, &pregen_attacks), 0);
+
+        // Exchanges are weighed with the piece values of the evaluation parameters, tapered by the game phase
+        let mut board_state = BoardState::new(Some("4k3/8/8/2p5/3N4/4P3/8/4K3 w - - 0 1"), &zobrist).unwrap();
+        let mut params = EvalParams::default();
+        params.piece_values[PieceType::Knight.to_index()] = Score::new(300, 400);
+        board_state.set_eval_params(Arc::new(params));
+        let knight_value = Score::new(300, 400).taper(board_state.get_phase());
+        assert_eq!(board_state.get_piece_value(PieceType::Knight), knight_value);
+        assert_eq!(board_state.get_threatened_value(Color::White, &pregen_attacks), knight_value - PAWN_VALUE);
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let zobrist = ZobristHasher::new();
+        let insufficient = |fen| BoardState::new(Some(fen), &zobrist).unwrap().is_insufficient_material();
+
+        assert!(insufficient("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(insufficient("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(insufficient("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1"));
+        assert!(insufficient("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(insufficient("4k3/8/8/8/8/8/8/B1B1K3 w - - 0 1"));
+
+        // Bishops on different colours, two knights and any pawn, rook or queen can still mate
+        assert!(!insufficient("4k3/8/8/8/8/8/8/2BBK3 w - - 0 1"));
+        assert!(!insufficient("4kb2/8/8/8/8/8/8/3BK3 w - - 0 1"));
+        assert!(!insufficient("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1"));
+        assert!(!insufficient("4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1"));
+        assert!(!insufficient("4k3/8/8/8/8/8/p7/4K3 w - - 0 1"));
+        assert!(!insufficient("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_can_mate() {
+        let zobrist = ZobristHasher::new();
+        let can_mate = |fen, side| BoardState::new(Some(fen), &zobrist).unwrap().can_mate(side);
+
+        assert!(!can_mate("4k3/8/8/8/8/8/8/4K3 w - - 0 1", Color::White));
+        assert!(!can_mate("3qk3/8/8/8/8/8/8/4K3 w - - 0 1", Color::White));
+        assert!(can_mate("3qk3/8/8/8/8/8/8/4K3 w - - 0 1", Color::Black));
+
+        // A lone knight can mate with the help of the opponent's pieces, except a queen
+        assert!(!can_mate("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1", Color::White));
+        assert!(can_mate("4k2r/8/8/8/8/8/8/1N2K3 w - - 0 1", Color::White));
+        assert!(can_mate("4k3/7p/8/8/8/8/8/1N2K3 w - - 0 1", Color::White));
+        assert!(!can_mate("3qk3/8/8/8/8/8/8/1N2K3 w - - 0 1", Color::White));
+
+        // Bishops on one colour need a pawn, a knight or a bishop on the other colour to block the king
+        assert!(!can_mate("4k2r/8/8/8/8/8/8/2B1K3 w - - 0 1", Color::White));
+        assert!(!can_mate("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", Color::White));
+        assert!(can_mate("4k1b1/8/8/8/8/8/8/2B1K3 w - - 0 1", Color::White));
+        assert!(can_mate("4k1n1/8/8/8/8/8/8/2B1K3 w - - 0 1", Color::White));
+        assert!(can_mate("4k3/8/8/8/8/8/8/2BBK3 w - - 0 1", Color::White));
+        assert!(can_mate("4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1", Color::White));
+    }
+}
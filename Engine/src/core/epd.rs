@@ -0,0 +1,201 @@
+use super::{board_state::BoardState, fen_parser::parse_fen, zobrist::ZobristHasher};
+
+/// An operation of an EPD record: an opcode such as `bm` followed by its operands
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EpdOperation {
+    pub opcode: String,
+    pub operands: Vec<String>,
+}
+
+/// A position in Extended Position Description: the first four FEN fields followed by operations,
+/// e.g. `r1b1k2r/... w kq - bm Nxe5; id "WAC.002";`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EpdRecord {
+    /// Piece placement, side to move, castling rights and en passant square
+    position: String,
+    operations: Vec<EpdOperation>,
+}
+
+impl EpdRecord {
+    /// Parses one EPD line
+    pub fn parse(line: &str) -> Result<EpdRecord, String> {
+        let line = line.trim();
+        let mut rest = line;
+        let mut fields = Vec::with_capacity(4);
+        for _ in 0..4 {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(format!("Invalid EPD: expected 4 position fields: {}", line));
+            }
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+
+        let record = EpdRecord {
+            position: fields.join(" "),
+            operations: parse_operations(rest)?,
+        };
+        // Validate the position now rather than when it is first used
+        record.to_board_state(&ZobristHasher::new())?;
+        Ok(record)
+    }
+
+    /// Returns the position as a FEN string, with the move counters from the `hmvc` and `fmvn` operations if present
+    pub fn get_fen(&self) -> String {
+        let half_moves = self.get_operand("hmvc").unwrap_or("0");
+        let full_moves = self.get_operand("fmvn").unwrap_or("1");
+        format!("{} {} {}", self.position, half_moves, full_moves)
+    }
+
+    /// Builds the position of the record
+    pub fn to_board_state(&self, zobrist: &ZobristHasher) -> Result<BoardState, String> {
+        parse_fen(&self.get_fen(), zobrist)
+    }
+
+    /// Returns the operands of the first operation with the given opcode
+    pub fn get_operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.iter().find(|op| op.opcode == opcode).map(|op| op.operands.as_slice())
+    }
+
+    /// Returns the first operand of the first operation with the given opcode
+    pub fn get_operand(&self, opcode: &str) -> Option<&str> {
+        self.get_operands(opcode)?.first().map(String::as_str)
+    }
+
+    /// Position identifier (`id`)
+    #[inline]
+    pub fn get_id(&self) -> Option<&str> {
+        self.get_operand("id")
+    }
+
+    /// Comment (`c0`)
+    #[inline]
+    pub fn get_comment(&self) -> Option<&str> {
+        self.get_operand("c0")
+    }
+
+    /// Best moves in SAN (`bm`); finding any of them solves the position
+    #[inline]
+    pub fn get_best_moves(&self) -> &[String] {
+        self.get_operands("bm").unwrap_or_default()
+    }
+
+    /// Moves to avoid in SAN (`am`)
+    #[inline]
+    pub fn get_avoid_moves(&self) -> &[String] {
+        self.get_operands("am").unwrap_or_default()
+    }
+
+    /// Expected evaluation in centipawns from the side to move's point of view (`ce`)
+    pub fn get_centipawn_eval(&self) -> Option<i32> {
+        self.get_operand("ce")?.parse().ok()
+    }
+
+    /// Number of moves to a forced mate (`dm`)
+    pub fn get_direct_mate(&self) -> Option<u32> {
+        self.get_operand("dm")?.parse().ok()
+    }
+}
+
+impl std::fmt::Display for EpdRecord {
+    /// Writes the record as an EPD line
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.position)?;
+        for op in &self.operations {
+            write!(f, " {}", op.opcode)?;
+            for operand in &op.operands {
+                if operand.is_empty() || operand.contains(|c: char| c.is_whitespace() || c == ';' || c == '"') {
+                    write!(f, " \"{}\"", operand.replace('"', "'"))?;
+                } else {
+                    write!(f, " {}", operand)?;
+                }
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the operations following the position fields; each ends with `;`, and quoted operands may contain spaces and `;`
+fn parse_operations(text: &str) -> Result<Vec<EpdOperation>, String> {
+    let mut operations = Vec::new();
+    let mut current: Option<EpdOperation> = None;
+    let mut rest = text;
+
+    loop {
+        rest = rest.trim_start();
+        let Some(c) = rest.chars().next() else { break };
+        match c {
+            ';' => {
+                operations.extend(current.take());
+                rest = &rest[1..];
+            }
+            '"' => {
+                let end = rest[1..].find('"').ok_or_else(|| format!("Invalid EPD: unterminated string: {}", rest))?;
+                let op = current.as_mut().ok_or_else(|| format!("Invalid EPD: operand without opcode: {}", rest))?;
+                op.operands.push(rest[1..end + 1].to_string());
+                rest = &rest[end + 2..];
+            }
+            _ => {
+                let end = rest.find(|c: char| c.is_whitespace() || c == ';').unwrap_or(rest.len());
+                let token = rest[..end].to_string();
+                match current.as_mut() {
+                    Some(op) => op.operands.push(token),
+                    None => {
+                        current = Some(EpdOperation {
+                            opcode: token,
+                            operands: Vec::new(),
+                        })
+                    }
+                }
+                rest = &rest[end..];
+            }
+        }
+    }
+    // The `;` after the last operation is often left out
+    operations.extend(current);
+    Ok(operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_epd() {
+        let record =
+            EpdRecord::parse(r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001"; c0 "mate; in 3"; ce +32000; dm 3;"#)
+                .unwrap();
+        assert_eq!(record.get_fen(), "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1");
+        assert_eq!(record.get_best_moves(), ["Qg6"]);
+        assert!(record.get_avoid_moves().is_empty());
+        assert_eq!(record.get_id(), Some("WAC.001"));
+        assert_eq!(record.get_comment(), Some("mate; in 3"));
+        assert_eq!(record.get_centipawn_eval(), Some(32000));
+        assert_eq!(record.get_direct_mate(), Some(3));
+
+        let record = EpdRecord::parse("4k3/8/8/8/8/8/8/R3K3 b Q - am Kd7 Kf7; bm Ke7 ;hmvc 12; fmvn 40").unwrap();
+        assert_eq!(record.get_fen(), "4k3/8/8/8/8/8/8/R3K3 b Q - 12 40");
+        assert_eq!(record.get_avoid_moves(), ["Kd7", "Kf7"]);
+        assert_eq!(record.get_best_moves(), ["Ke7"]);
+        assert_eq!(record.get_id(), None);
+        assert!(record.to_board_state(&ZobristHasher::new()).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_epd() {
+        assert!(EpdRecord::parse("").is_err());
+        assert!(EpdRecord::parse("8/8/8/8 w").is_err());
+        assert!(EpdRecord::parse("4k3/8/8/8/8/8/8/4K3 x - - bm Kd2;").is_err());
+        assert!(EpdRecord::parse(r#"4k3/8/8/8/8/8/8/4K3 w - - id "unterminated;"#).is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let line = r#"4k3/8/8/8/8/8/8/R3K3 w Q - bm Ra8+ O-O-O; id "suite 1"; c0 "";"#;
+        let record = EpdRecord::parse(line).unwrap();
+        assert_eq!(record.to_string(), line);
+        assert_eq!(EpdRecord::parse(&record.to_string()).unwrap(), record);
+    }
+}
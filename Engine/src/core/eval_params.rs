@@ -0,0 +1,252 @@
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{Arc, OnceLock},
+};
+
+use super::{
+    piece::{PieceType, BISHOP_VALUE, KNIGHT_VALUE, PAWN_VALUE, QUEEN_VALUE, ROOK_VALUE},
+    piece_square_table::PieceSquareTable,
+    score::Score,
+};
+
+/// Names of the piece types in parameter files, in `PieceType` order
+const PIECE_NAMES: [&str; 6] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+
+/// Parameters new positions start with, see `EvalParams::get_default`
+static DEFAULT_PARAMS: OnceLock<Arc<EvalParams>> = OnceLock::new();
+
+/// Every weight used by `BoardState::evaluate`.
+/// The default values are compiled in; others can be read from a text file with one `name value...` line per parameter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvalParams {
+    /// Value of each piece type except the king, in `PieceType` order; also weighs exchanges and orders captures in the search
+    pub piece_values: [Score; 5],
+    pub psqt: PieceSquareTable,
+    /// Bonus per square a knight, bishop, rook or queen can move to
+    pub mobility: Score,
+    pub doubled_pawn: Score,
+    pub isolated_pawn: Score,
+    /// A pawn that can no longer be defended by pawns and cannot advance safely
+    pub backward_pawn: Score,
+    /// Bonus for passed pawns by rank, from the pawn's side
+    pub passed_pawn: [Score; 8],
+    /// Bonus by rank for unopposed pawns with at least as many pawns supporting their advance as stopping it
+    pub candidate_pawn: [Score; 8],
+    /// Per enemy rook or queen attacking the king
+    pub king_tropism: Score,
+    /// Per file next to the king, or its own, with a pawn of the king's side
+    pub pawn_shield: Score,
+    /// Score of the side in check
+    pub check: Score,
+    /// Percentage of the material each side would lose to a capture sequence that is counted against it
+    pub threats: Score,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        let value = |v| Score::new(v, v);
+        let score = |(mg, eg)| Score::new(mg, eg);
+        Self {
+            piece_values: [
+                value(PAWN_VALUE),
+                value(KNIGHT_VALUE),
+                value(BISHOP_VALUE),
+                value(ROOK_VALUE),
+                value(QUEEN_VALUE),
+            ],
+            psqt: PieceSquareTable::new(),
+            mobility: Score::new(1, 1),
+            doubled_pawn: Score::new(-10, -20),
+            isolated_pawn: Score::new(-10, -15),
+            backward_pawn: Score::new(-8, -12),
+            passed_pawn: [(0, 0), (5, 10), (5, 10), (10, 20), (20, 35), (30, 60), (50, 90), (0, 0)].map(score),
+            candidate_pawn: [(0, 0), (2, 5), (2, 5), (5, 10), (10, 20), (15, 30), (0, 0), (0, 0)].map(score),
+            king_tropism: Score::new(-10, -5),
+            pawn_shield: Score::new(5, 0),
+            check: Score::new(-100, -100),
+            threats: Score::new(100, 100),
+        }
+    }
+}
+
+/// Values of one parameter
+enum Slot<'a> {
+    Score(&'a mut Score),
+    Scores(&'a mut [Score]),
+    Table(&'a mut [i32; 64]),
+}
+
+impl Slot<'_> {
+    fn get(&self) -> Vec<i32> {
+        match self {
+            Slot::Score(score) => vec![score.get_mg(), score.get_eg()],
+            Slot::Scores(scores) => scores.iter().flat_map(|s| [s.get_mg(), s.get_eg()]).collect(),
+            Slot::Table(table) => table.to_vec(),
+        }
+    }
+
+    /// Sets the values; `values` must have as many entries as `get` returns
+    fn set(&mut self, values: &[i32]) {
+        match self {
+            Slot::Score(score) => **score = Score::new(values[0], values[1]),
+            Slot::Scores(scores) => {
+                for (score, pair) in scores.iter_mut().zip(values.chunks(2)) {
+                    *score = Score::new(pair[0], pair[1]);
+                }
+            }
+            Slot::Table(table) => table.copy_from_slice(values),
+        }
+    }
+}
+
+impl EvalParams {
+    /// Returns the parameters new positions start with: the compiled defaults unless `set_default` was called first
+    pub fn get_default() -> Arc<EvalParams> {
+        DEFAULT_PARAMS.get_or_init(|| Arc::new(EvalParams::default())).clone()
+    }
+
+    /// Replaces the compiled defaults for every position created afterwards.
+    /// Only possible at startup, before the first position is created.
+    pub fn set_default(params: EvalParams) -> Result<(), String> {
+        DEFAULT_PARAMS
+            .set(Arc::new(params))
+            .map_err(|_| "evaluation parameters are already in use".to_string())
+    }
+
+    /// Returns the value of a piece type; kings have no value since both sides always have one
+    #[inline(always)]
+    pub fn get_piece_value(&self, piece_type: PieceType) -> Score {
+        match piece_type {
+            PieceType::King => Score::ZERO,
+            _ => self.piece_values[piece_type.to_index()],
+        }
+    }
+
+    /// Reads parameters from a file; parameters the file does not mention keep their default value
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        text.parse().map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Writes every parameter to a file, in the format read by `load`
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_string()).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Every parameter with its name, in file order
+    fn slots(&mut self) -> Vec<(String, Slot<'_>)> {
+        let mut slots = Vec::new();
+        for (name, value) in PIECE_NAMES.iter().zip(self.piece_values.iter_mut()) {
+            slots.push((format!("{}_value", name), Slot::Score(value)));
+        }
+        let (openings, endgames) = self.psqt.get_tables_mut();
+        for ((name, opening), endgame) in PIECE_NAMES.iter().zip(openings.iter_mut()).zip(endgames.iter_mut()) {
+            slots.push((format!("psqt_{}_mg", name), Slot::Table(opening)));
+            slots.push((format!("psqt_{}_eg", name), Slot::Table(endgame)));
+        }
+        slots.extend([
+            ("mobility".to_string(), Slot::Score(&mut self.mobility)),
+            ("doubled_pawn".to_string(), Slot::Score(&mut self.doubled_pawn)),
+            ("isolated_pawn".to_string(), Slot::Score(&mut self.isolated_pawn)),
+            ("backward_pawn".to_string(), Slot::Score(&mut self.backward_pawn)),
+            ("passed_pawn".to_string(), Slot::Scores(&mut self.passed_pawn)),
+            ("candidate_pawn".to_string(), Slot::Scores(&mut self.candidate_pawn)),
+            ("king_tropism".to_string(), Slot::Score(&mut self.king_tropism)),
+            ("pawn_shield".to_string(), Slot::Score(&mut self.pawn_shield)),
+            ("check".to_string(), Slot::Score(&mut self.check)),
+            ("threats".to_string(), Slot::Score(&mut self.threats)),
+        ]);
+        slots
+    }
+}
+
+impl fmt::Display for EvalParams {
+    /// Formats the parameters as a parameter file
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# Evaluation parameters: scores are middlegame and endgame pairs,")?;
+        writeln!(f, "# piece-square tables list the squares from a8 to h1 as seen by White")?;
+        for (name, slot) in self.clone().slots() {
+            let values: Vec<String> = slot.get().iter().map(i32::to_string).collect();
+            writeln!(f, "{} {}", name, values.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for EvalParams {
+    type Err = String;
+
+    /// Parses a parameter file; blank lines and lines starting with `#` are skipped
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut params = EvalParams::default();
+        let mut slots = params.slots();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", line_number + 1, message);
+            let mut tokens = line.split_whitespace();
+            let name = tokens.next().unwrap_or_default();
+            let values = tokens
+                .map(|token| token.parse::<i32>().map_err(|_| error(format!("invalid value: {}", token))))
+                .collect::<Result<Vec<i32>, String>>()?;
+
+            let (_, slot) = slots
+                .iter_mut()
+                .find(|(slot_name, _)| slot_name == name)
+                .ok_or_else(|| error(format!("unknown parameter: {}", name)))?;
+            let expected = slot.get().len();
+            if values.len() != expected {
+                return Err(error(format!("{} expects {} values, got {}", name, expected, values.len())));
+            }
+            slot.set(&values);
+        }
+        drop(slots);
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{piece::Piece, square::Square};
+
+    #[test]
+    fn test_round_trip() {
+        let mut params = EvalParams {
+            mobility: Score::new(3, 4),
+            ..Default::default()
+        };
+        params.passed_pawn[6] = Score::new(70, 120);
+        let text = params.to_string();
+        assert!(text.contains("\nmobility 3 4\n"));
+        assert!(text.contains("\npawn_value 100 100\n"));
+        assert_eq!(text.parse::<EvalParams>(), Ok(params));
+    }
+
+    #[test]
+    fn test_partial_file() {
+        let params: EvalParams = "# only the knight changes\n\nknight_value 300 280\n".parse().unwrap();
+        assert_eq!(params.get_piece_value(PieceType::Knight), Score::new(300, 280));
+        assert_eq!(
+            params.get_piece_value(PieceType::Rook),
+            EvalParams::default().get_piece_value(PieceType::Rook)
+        );
+
+        let table = format!("psqt_king_eg {}", vec!["7"; 64].join(" "));
+        let params: EvalParams = table.parse().unwrap();
+        assert_eq!(params.psqt.get_value(Piece::BKing, Square::E5).get_eg(), 7);
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        assert_eq!(
+            "mobility 1".parse::<EvalParams>(),
+            Err("line 1: mobility expects 2 values, got 1".to_string())
+        );
+        assert_eq!("\nspeed 1 2".parse::<EvalParams>(), Err("line 2: unknown parameter: speed".to_string()));
+        assert_eq!("check -1 x".parse::<EvalParams>(), Err("line 1: invalid value: x".to_string()));
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+
+use super::{
+    piece::Color,
+    score::{Score, MAX_PHASE},
+};
+
+/// Number of terms in `EvalTerm`
+pub const EVAL_TERM_COUNT: usize = 7;
+
+/// A term of the static evaluation
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EvalTerm {
+    Material,
+    PieceSquare,
+    Mobility,
+    PawnStructure,
+    KingSafety,
+    /// Penalty for being in check
+    Check,
+    /// Material each side would lose to a capture sequence against its most valuable hanging piece
+    Threats,
+}
+
+pub const EVAL_TERMS: [EvalTerm; EVAL_TERM_COUNT] = [
+    EvalTerm::Material,
+    EvalTerm::PieceSquare,
+    EvalTerm::Mobility,
+    EvalTerm::PawnStructure,
+    EvalTerm::KingSafety,
+    EvalTerm::Check,
+    EvalTerm::Threats,
+];
+
+impl EvalTerm {
+    #[inline(always)]
+    pub const fn to_index(self) -> usize {
+        self as usize
+    }
+
+    /// Name of the term in `EvalTrace` tables
+    pub const fn get_name(self) -> &'static str {
+        match self {
+            EvalTerm::Material => "Material",
+            EvalTerm::PieceSquare => "PSQT",
+            EvalTerm::Mobility => "Mobility",
+            EvalTerm::PawnStructure => "Pawn structure",
+            EvalTerm::KingSafety => "King safety",
+            EvalTerm::Check => "Check",
+            EvalTerm::Threats => "Threats",
+        }
+    }
+
+    /// Key of the term in `EvalTrace::to_json`
+    pub const fn get_key(self) -> &'static str {
+        match self {
+            EvalTerm::Material => "material",
+            EvalTerm::PieceSquare => "psqt",
+            EvalTerm::Mobility => "mobility",
+            EvalTerm::PawnStructure => "pawn_structure",
+            EvalTerm::KingSafety => "king_safety",
+            EvalTerm::Check => "check",
+            EvalTerm::Threats => "threats",
+        }
+    }
+}
+
+/// Receives every term computed by `BoardState::evaluate`
+pub trait EvalTracer {
+    /// Records the scores of both sides for `term`, each from its own side's point of view
+    fn add(&mut self, term: EvalTerm, white: Score, black: Score);
+}
+
+/// Tracer for the normal evaluation, which keeps nothing
+pub struct NoTrace;
+
+impl EvalTracer for NoTrace {
+    #[inline(always)]
+    fn add(&mut self, _term: EvalTerm, _white: Score, _black: Score) {}
+}
+
+/// Breakdown of a static evaluation into its terms, per side and per phase.
+/// Each term contributes its tapered White minus Black score, and these contributions add up to `BoardState::evaluate`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvalTrace {
+    phase: i32,
+    scores: [[Score; 2]; EVAL_TERM_COUNT],
+}
+
+impl EvalTracer for EvalTrace {
+    fn add(&mut self, term: EvalTerm, white: Score, black: Score) {
+        self.scores[term.to_index()] = [white, black];
+    }
+}
+
+impl EvalTrace {
+    /// Creates an empty trace for a position at game phase `phase`
+    pub fn new(phase: i32) -> Self {
+        Self {
+            phase,
+            scores: [[Score::ZERO; 2]; EVAL_TERM_COUNT],
+        }
+    }
+
+    #[cfg(test)]
+    #[inline(always)]
+    pub fn get_phase(&self) -> i32 {
+        self.phase
+    }
+
+    /// Returns the score of `term` for `color`, from that side's point of view
+    #[inline(always)]
+    pub fn get_score(&self, term: EvalTerm, color: Color) -> Score {
+        self.scores[term.to_index()][color]
+    }
+
+    /// Returns the tapered contribution of `term` to the evaluation, from White's point of view
+    pub fn get_value(&self, term: EvalTerm) -> i32 {
+        (self.get_score(term, Color::White) - self.get_score(term, Color::Black)).taper(self.phase)
+    }
+
+    /// Returns the evaluation from White's point of view, the sum of all terms
+    pub fn get_total(&self) -> i32 {
+        EVAL_TERMS.iter().map(|&term| self.get_value(term)).sum()
+    }
+
+    /// Formats the trace as a JSON object
+    pub fn to_json(&self) -> String {
+        let score = |s: Score| format!("{{\"mg\": {}, \"eg\": {}}}", s.get_mg(), s.get_eg());
+        let terms: Vec<String> = EVAL_TERMS
+            .iter()
+            .map(|&term| {
+                format!(
+                    "\"{}\": {{\"white\": {}, \"black\": {}, \"total\": {}}}",
+                    term.get_key(),
+                    score(self.get_score(term, Color::White)),
+                    score(self.get_score(term, Color::Black)),
+                    self.get_value(term)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"phase\": {}, \"max_phase\": {}, \"terms\": {{{}}}, \"total\": {}}}",
+            self.phase,
+            MAX_PHASE,
+            terms.join(", "),
+            self.get_total()
+        )
+    }
+}
+
+impl fmt::Display for EvalTrace {
+    /// Formats the trace as a table with the middlegame and endgame scores of each side and the tapered total
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<16}|{:^15}|{:^15}|{:^23}", "Term", "White", "Black", "Total")?;
+        writeln!(
+            f,
+            "{:<16}|{:>7}{:>7} |{:>7}{:>7} |{:>7}{:>7}{:>8}",
+            "", "MG", "EG", "MG", "EG", "MG", "EG", "Eval"
+        )?;
+        writeln!(f, "{:-<16}+{:-<15}+{:-<15}+{:-<23}", "", "", "", "")?;
+        for term in EVAL_TERMS {
+            let (white, black) = (self.get_score(term, Color::White), self.get_score(term, Color::Black));
+            let total = white - black;
+            writeln!(
+                f,
+                "{:<16}|{:>7}{:>7} |{:>7}{:>7} |{:>7}{:>7}{:>8}",
+                term.get_name(),
+                white.get_mg(),
+                white.get_eg(),
+                black.get_mg(),
+                black.get_eg(),
+                total.get_mg(),
+                total.get_eg(),
+                self.get_value(term)
+            )?;
+        }
+        writeln!(f, "{:-<16}+{:-<15}+{:-<15}+{:-<23}", "", "", "", "")?;
+        writeln!(f, "Phase: {}/{}", self.phase, MAX_PHASE)?;
+        write!(f, "Total: {} (White's point of view)", self.get_total())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_totals() {
+        let mut trace = EvalTrace::new(MAX_PHASE / 2);
+        trace.add(EvalTerm::Material, Score::new(100, 100), Score::ZERO);
+        trace.add(EvalTerm::Mobility, Score::new(10, 20), Score::new(5, 5));
+        assert_eq!(trace.get_value(EvalTerm::Material), 100);
+        assert_eq!(trace.get_value(EvalTerm::Mobility), 10);
+        assert_eq!(trace.get_total(), 110);
+        assert_eq!(trace.get_score(EvalTerm::Mobility, Color::Black), Score::new(5, 5));
+
+        let json = trace.to_json();
+        assert!(json.starts_with("{\"phase\": 12, \"max_phase\": 24, \"terms\": {\"material\": {\"white\": {\"mg\": 100, \"eg\": 100}"));
+        assert!(json.ends_with("\"total\": 110}"));
+
+        let table = trace.to_string();
+        assert_eq!(table.lines().count(), EVAL_TERM_COUNT + 6);
+        assert!(table.ends_with("Total: 110 (White's point of view)"));
+    }
+}
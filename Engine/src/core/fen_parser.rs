@@ -1,9 +1,11 @@
+use std::fmt::Write;
+
 use super::{
     bitboard::Bitboard,
     board_state::BoardState,
-    piece::{CastlePerms, Color, Piece},
-    piece_square_table::*,
-    square::{File, Rank, Square},
+    eval_params::EvalParams,
+    piece::{CastlePerms, Color, Piece, PieceType},
+    square::{File, Rank, Square, FILES, RANKS},
     zobrist::ZobristHasher,
 };
 
@@ -27,7 +29,6 @@ pub fn parse_fen(fen: &str, zobrist: &ZobristHasher) -> Result<BoardState, Strin
     let mut position_bb = [Bitboard::new_empty(); 2];
     let mut board = [None; 64];
     let mut piece_lists: [Vec<Square>; 12] = Default::default();
-    let mut material: [i32; 2] = [0; 2];
     let mut piece_counts: [u8; 12] = [0; 12];
 
     // Parse piece placement
@@ -37,7 +38,6 @@ pub fn parse_fen(fen: &str, zobrist: &ZobristHasher) -> Result<BoardState, Strin
         &mut position_bb,
         &mut board,
         &mut piece_lists,
-        &mut material,
         &mut piece_counts,
     )?;
 
@@ -53,8 +53,6 @@ pub fn parse_fen(fen: &str, zobrist: &ZobristHasher) -> Result<BoardState, Strin
     // Parse move counters
     let (half_moves, full_moves) = parse_move_counters(fen_parts[4], fen_parts[5])?;
 
-    let psqt = PieceSquareTable::new();
-
     Ok(BoardState::init(
         piece_bb,
         position_bb,
@@ -65,9 +63,8 @@ pub fn parse_fen(fen: &str, zobrist: &ZobristHasher) -> Result<BoardState, Strin
         castling_rights,
         half_moves,
         full_moves,
-        material,
         piece_counts,
-        psqt,
+        EvalParams::get_default(),
         zobrist.init_hash(&board, side, en_passant, castling_rights),
         zobrist.init_hash_pawns(&board),
     ))
@@ -81,7 +78,6 @@ fn parse_piece_placement(
     position_bb: &mut [Bitboard; 2],
     board: &mut [Option<Piece>; 64],
     piece_list: &mut [Vec<Square>; 12],
-    material: &mut [i32; 2],
     piece_count: &mut [u8; 12],
 ) -> Result<(), String> {
     let mut file_index: usize = 0;
@@ -96,7 +92,6 @@ fn parse_piece_placement(
                 let piece = Piece::from_char(piece_char).map_err(|_| format!("Invalid piece placement: invalid piece character: {}", piece_char))?;
                 let sq = Square::from_file_rank(File::from_index(file_index), Rank::from_index(rank_index));
                 init_square(piece_bb, position_bb, board, piece_list, sq, piece, piece.get_color());
-                material[piece.get_color()] += piece.get_value();
                 piece_count[piece] += 1;
                 file_index += 1;
             }
@@ -198,6 +193,81 @@ fn parse_move_counters(half_moves: &str, full_moves: &str) -> Result<(u8, u32),
     Ok((half, full))
 }
 
+/// Writes a BoardState as a FEN string, the inverse of `parse_fen`
+///
+/// The en passant square is only written when a pawn of the side to move could capture on it,
+/// so positions that only differ by an unusable en passant square get the same FEN.
+pub fn to_fen(board_state: &BoardState) -> String {
+    let mut fen = String::with_capacity(90);
+
+    for rank in RANKS.iter().rev() {
+        let mut empty = 0;
+        for file in FILES {
+            match board_state.get_piece_on_square(Square::from_file_rank(file, *rank)) {
+                Some(piece) => {
+                    if empty > 0 {
+                        write!(fen, "{}", empty).unwrap();
+                        empty = 0;
+                    }
+                    fen.push(piece.to_char());
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            write!(fen, "{}", empty).unwrap();
+        }
+        if *rank != Rank::R1 {
+            fen.push('/');
+        }
+    }
+
+    fen.push_str(match board_state.get_side() {
+        Color::White => " w ",
+        Color::Black => " b ",
+    });
+
+    let castling: String = [
+        (CastlePerms::WKC, 'K'),
+        (CastlePerms::WQC, 'Q'),
+        (CastlePerms::BKC, 'k'),
+        (CastlePerms::BQC, 'q'),
+    ]
+    .into_iter()
+    .filter(|(perm, _)| board_state.check_castle(*perm as u8))
+    .map(|(_, c)| c)
+    .collect();
+    fen.push_str(if castling.is_empty() { "-" } else { &castling });
+
+    match capturable_en_passant(board_state) {
+        Some(sq) => write!(fen, " {}", sq).unwrap(),
+        None => fen.push_str(" -"),
+    }
+
+    write!(fen, " {} {}", board_state.get_half_moves(), board_state.get_full_moves()).unwrap();
+    fen
+}
+
+/// Returns the en passant square if a pawn of the side to move stands next to the pawn that just moved
+#[inline(always)]
+fn capturable_en_passant(board_state: &BoardState) -> Option<Square> {
+    let en_passant = board_state.get_en_passant()?;
+    let side = board_state.get_side();
+    let pawn_rank = match side {
+        Color::White => en_passant.move_down(1),
+        Color::Black => en_passant.move_up(1),
+    }
+    .get_rank();
+    let file = en_passant.get_file() as usize;
+    let pawn = Some(Piece::new(side, PieceType::Pawn));
+
+    [file.checked_sub(1), (file < 7).then_some(file + 1)]
+        .into_iter()
+        .flatten()
+        .any(|f| board_state.get_piece_on_square(Square::from_file_rank(File::from_index(f), pawn_rank)) == pawn)
+        .then_some(en_passant)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -225,4 +295,64 @@ mod tests {
         let result = parse_fen(fen, &zobrist);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_to_fen() {
+        let zobrist = ZobristHasher::new();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            "rnbqkbnr/ppp1pppp/8/8/2Pp4/8/PP1PPPPP/RNBQKBNR b Kq c3 0 3",
+            "4k3/8/8/8/8/8/8/4K3 b - - 99 120",
+        ] {
+            assert_eq!(to_fen(&parse_fen(fen, &zobrist).unwrap()), fen);
+        }
+
+        // An en passant square no pawn can capture on is left out
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        assert_eq!(
+            to_fen(&parse_fen(fen, &zobrist).unwrap()),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_random_play() {
+        use crate::{core::attack_pregen::PregenAttacks, move_logic::legal_move_gen};
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let zobrist = ZobristHasher::new();
+        let pregen_attacks = PregenAttacks::init();
+        let mut rng = StdRng::seed_from_u64(0x5EED);
+        let mut moves = Vec::new();
+
+        for game in 0..40 {
+            let start = match game % 2 {
+                0 => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                _ => "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            };
+            let mut board_state = parse_fen(start, &zobrist).unwrap();
+            for _ in 0..150 {
+                let fen = to_fen(&board_state);
+                let parsed: BoardState = fen.parse().unwrap();
+                assert_eq!(to_fen(&parsed), fen);
+                assert_eq!(parsed.get_board(), board_state.get_board(), "{}", fen);
+                assert_eq!(parsed.get_castling_rights(), board_state.get_castling_rights(), "{}", fen);
+                // Hashes also match unless an en passant square was left out
+                if parsed.get_en_passant() == board_state.get_en_passant() {
+                    assert_eq!(parsed.get_zobrist_hash(), board_state.get_zobrist_hash(), "{}", fen);
+                }
+
+                moves.clear();
+                legal_move_gen::get_legal_moves(&board_state, &pregen_attacks, &mut moves);
+                if moves.is_empty() || board_state.get_half_moves() >= 100 {
+                    break;
+                }
+                board_state.make_move(moves[rng.gen_range(0..moves.len())], &zobrist);
+            }
+        }
+    }
 }
@@ -1,8 +1,13 @@
 pub mod attack_pregen;
 pub mod bitboard;
 pub mod board_state;
+pub mod epd;
+pub mod eval_params;
+pub mod eval_trace;
 pub mod fen_parser;
+pub mod pawn_table;
 pub mod piece;
 pub mod piece_square_table;
+pub mod score;
 pub mod square;
 pub mod zobrist;
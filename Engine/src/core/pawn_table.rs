@@ -0,0 +1,233 @@
+use super::{
+    bitboard::FILES_BB,
+    board_state::BoardState,
+    piece::{Color, Piece, PieceType},
+    score::Score,
+    square::Square,
+};
+
+/// Number of entries in a pawn hash table
+pub const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+/// Pawn structure terms of a position, which only depend on where the pawns are
+#[derive(Clone, Debug, PartialEq)]
+pub struct PawnEntry {
+    key: u64,
+    /// Pawn structure score of each side
+    scores: [Score; 2],
+    /// Files with at least one pawn, one bit per file starting with the A file, used for the king shelter
+    pawn_files: [u8; 2],
+}
+
+impl PawnEntry {
+    /// Computes the pawn structure terms of `board_state`
+    pub fn new(board_state: &BoardState) -> Self {
+        let mut entry = Self {
+            key: board_state.get_pawn_hash(),
+            scores: [Score::ZERO; 2],
+            pawn_files: [0; 2],
+        };
+        for color in [Color::White, Color::Black] {
+            let pawns = board_state.get_piece_bb(Piece::new(color, PieceType::Pawn));
+            entry.scores[color] = evaluate_pawns(board_state, color);
+            entry.pawn_files[color] = (0..8)
+                .filter(|&file| !pawns.intersect(FILES_BB[file]).is_empty())
+                .fold(0, |files, file| files | 1 << file);
+        }
+        entry
+    }
+
+    /// Returns the pawn structure score of `color`, from its own point of view
+    #[inline(always)]
+    pub fn get_score(&self, color: Color) -> Score {
+        self.scores[color]
+    }
+
+    /// Returns whether `color` has a pawn on the file with the given index
+    #[inline(always)]
+    pub fn has_pawn_on_file(&self, color: Color, file: usize) -> bool {
+        self.pawn_files[color] & (1 << file) != 0
+    }
+}
+
+/// Scores the doubled, isolated, backward, passed and candidate pawns of `color`
+fn evaluate_pawns(board_state: &BoardState, color: Color) -> Score {
+    let pawns = board_state.get_piece_bb(Piece::new(color, PieceType::Pawn));
+    let enemy_pawns = board_state.get_piece_bb(Piece::new(color.opposite(), PieceType::Pawn));
+    let enemy_attacks = enemy_pawns.shift_pawn_attack(color.opposite());
+    let params = board_state.get_eval_params();
+    let mut score = Score::ZERO;
+
+    let mut remaining = pawns;
+    while !remaining.is_empty() {
+        let sq = remaining.pop_ls_square();
+        let bb = sq.to_bitboard();
+        let front = bb.front_span(color);
+        let stop_square = front.diff(front.front_span(color));
+        let front_adjacent = front.adjacent_files();
+        let adjacent_files = FILES_BB[sq.get_file()].adjacent_files();
+        // Own pawns beside or behind this one, which can defend it or support its advance
+        let supporters = pawns.intersect(adjacent_files.diff(front_adjacent));
+        let opposed = !enemy_pawns.intersect(front).is_empty();
+        let sentries = enemy_pawns.intersect(front_adjacent);
+        let rank = relative_rank(sq, color);
+
+        if !pawns.intersect(front).is_empty() {
+            score += params.doubled_pawn;
+        }
+        if pawns.intersect(adjacent_files).is_empty() {
+            score += params.isolated_pawn;
+        } else if supporters.is_empty() && !enemy_attacks.intersect(stop_square).is_empty() {
+            score += params.backward_pawn;
+        }
+
+        if !opposed && sentries.is_empty() {
+            score += params.passed_pawn[rank];
+        } else if !opposed && supporters.count_squares() >= sentries.count_squares() {
+            score += params.candidate_pawn[rank];
+        }
+    }
+    score
+}
+
+/// Hash table of pawn structure terms indexed by the pawn hash, private to one search thread
+#[derive(Clone, Debug)]
+pub struct PawnTable {
+    entries: Vec<Option<PawnEntry>>,
+    probes: u64,
+    hits: u64,
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new(PAWN_TABLE_SIZE)
+    }
+}
+
+impl PawnTable {
+    /// Creates a table with room for `size` entries
+    pub fn new(size: usize) -> Self {
+        Self {
+            entries: vec![None; size.max(1)],
+            probes: 0,
+            hits: 0,
+        }
+    }
+
+    /// Returns the pawn structure terms of `board_state`, computing and storing them if they are not in the table
+    pub fn probe(&mut self, board_state: &BoardState) -> &PawnEntry {
+        let key = board_state.get_pawn_hash();
+        let index = (key % self.entries.len() as u64) as usize;
+        self.probes += 1;
+        let slot = &mut self.entries[index];
+        if slot.as_ref().is_some_and(|entry| entry.key == key) {
+            self.hits += 1;
+        } else {
+            *slot = Some(PawnEntry::new(board_state));
+        }
+        slot.as_ref().unwrap()
+    }
+
+    /// Resets the statistics for a new search, keeping the entries
+    pub fn new_search(&mut self) {
+        self.probes = 0;
+        self.hits = 0;
+    }
+
+    /// Removes all entries and resets the statistics
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.probes = 0;
+        self.hits = 0;
+    }
+
+    #[cfg(test)]
+    #[inline(always)]
+    pub fn get_probes(&self) -> u64 {
+        self.probes
+    }
+
+    #[cfg(test)]
+    #[inline(always)]
+    pub fn get_hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Permille of probes answered from the table
+    pub fn hit_rate(&self) -> u32 {
+        (self.hits * 1000 / self.probes.max(1)) as u32
+    }
+}
+
+/// Rank of `sq` counted from `color`'s side of the board, 0 to 7
+#[inline(always)]
+fn relative_rank(sq: Square, color: Color) -> usize {
+    match color {
+        Color::White => sq.get_rank() as usize,
+        Color::Black => 7 - sq.get_rank() as usize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{attack_pregen::PregenAttacks, eval_params::EvalParams};
+
+    fn entry(fen: &str) -> PawnEntry {
+        PawnEntry::new(&fen.parse().unwrap())
+    }
+
+    #[test]
+    fn test_pawn_terms() {
+        let params = EvalParams::default();
+
+        // Without enemy pawns every pawn is passed
+        let pawns = entry("4k3/8/8/3P4/8/6P1/7P/4K3 w - - 0 1");
+        assert!(pawns.get_score(Color::White).get_eg() > 0);
+        let passed = entry("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1");
+        assert_eq!(passed.get_score(Color::White), params.isolated_pawn + params.passed_pawn[4]);
+        let blocked = entry("4k3/8/3p4/3P4/8/8/8/4K3 w - - 0 1");
+        assert_eq!(blocked.get_score(Color::White), params.isolated_pawn);
+
+        // Doubled and isolated pawns are worse than connected ones
+        let doubled = entry("4k3/8/8/8/8/4P3/4P3/4K3 w - - 0 1");
+        let connected = entry("4k3/8/8/8/8/4P3/3P4/4K3 w - - 0 1");
+        assert!(doubled.get_score(Color::White).get_mg() < connected.get_score(Color::White).get_mg());
+
+        // The d6 pawn is backward once the e4 pawn controls d5; nothing else changes between the two positions
+        let backward = entry("4k3/8/3p4/2p1p3/4P3/8/8/4K3 w - - 0 1");
+        let not_backward = entry("4k3/8/3p4/2p1p3/8/4P3/8/4K3 w - - 0 1");
+        assert_eq!(
+            backward.get_score(Color::Black) - not_backward.get_score(Color::Black),
+            params.backward_pawn
+        );
+
+        // Mirrored positions score the same for each side
+        let white = entry("4k3/8/8/8/2P5/8/PP3PPP/4K3 w - - 0 1");
+        let black = entry("4k3/pp3ppp/8/2p5/8/8/8/4K3 w - - 0 1");
+        assert_eq!(white.get_score(Color::White), black.get_score(Color::Black));
+        assert_eq!(white.get_score(Color::Black), Score::ZERO);
+        assert!(white.has_pawn_on_file(Color::White, 2));
+        assert!(!white.has_pawn_on_file(Color::White, 3));
+    }
+
+    #[test]
+    fn test_probe_hits_after_first_lookup() {
+        let mut table = PawnTable::new(64);
+        let board_state: BoardState = "4k3/pp3ppp/8/8/8/8/PP3PPP/4K3 w - - 0 1".parse().unwrap();
+        let first = table.probe(&board_state).clone();
+        assert_eq!(table.probe(&board_state), &first);
+        assert_eq!((table.get_probes(), table.get_hits()), (2, 1));
+        assert_eq!(table.hit_rate(), 500);
+        assert_eq!(first, PawnEntry::new(&board_state));
+
+        let pregen_attacks = PregenAttacks::init();
+        assert_eq!(
+            board_state.evaluate_cached(&pregen_attacks, &mut table),
+            board_state.evaluate(&pregen_attacks)
+        );
+
+        table.clear();
+        assert_eq!(table.get_probes(), 0);
+    }
+}
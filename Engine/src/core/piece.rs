@@ -1,4 +1,4 @@
-/// Piece values for evaluation
+/// Default piece values of the evaluation, see `EvalParams::piece_values`
 pub const PAWN_VALUE: i32 = 100;
 pub const KNIGHT_VALUE: i32 = 325;
 pub const BISHOP_VALUE: i32 = 350;
@@ -12,6 +12,7 @@ pub const PIECE_CHARS_FANCY: [char; 13] = ['♟', '♞', '♝', '♜', '♛', '
 pub const PIECE_CHARS: [char; 13] = ['P', 'N', 'B', 'R', 'Q', 'K', 'p', 'n', 'b', 'r', 'q', 'k', ' '];
 
 /// Represents castle permissions for both sides
+#[allow(clippy::upper_case_acronyms)]
 #[repr(u8)]
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
 pub enum CastlePerms {
@@ -171,16 +172,14 @@ impl Piece {
         }
     }
 
-    /// Returns the value of the piece for evaluation
+    /// Returns how much the piece counts towards the game phase; pawns and kings count nothing
     #[inline(always)]
-    pub const fn get_value(self) -> i32 {
+    pub const fn get_phase_weight(self) -> i32 {
         match self {
-            Piece::WPawn | Piece::BPawn => PAWN_VALUE,
-            Piece::WKnight | Piece::BKnight => KNIGHT_VALUE,
-            Piece::WBishop | Piece::BBishop => BISHOP_VALUE,
-            Piece::WRook | Piece::BRook => ROOK_VALUE,
-            Piece::WQueen | Piece::BQueen => QUEEN_VALUE,
-            Piece::WKing | Piece::BKing => KING_VALUE,
+            Piece::WKnight | Piece::BKnight | Piece::WBishop | Piece::BBishop => 1,
+            Piece::WRook | Piece::BRook => 2,
+            Piece::WQueen | Piece::BQueen => 4,
+            Piece::WPawn | Piece::BPawn | Piece::WKing | Piece::BKing => 0,
             Piece::None => panic!("WRONG"),
         }
     }
@@ -250,19 +249,6 @@ impl PieceType {
     pub const fn from_index(index: usize) -> PieceType {
         PIECE_TYPES[index]
     }
-
-    /// Returns the value of the piece type for evaluation
-    #[inline(always)]
-    pub const fn get_value(self) -> i32 {
-        match self {
-            PieceType::Pawn => PAWN_VALUE,
-            PieceType::Knight => KNIGHT_VALUE,
-            PieceType::Bishop => BISHOP_VALUE,
-            PieceType::Rook => ROOK_VALUE,
-            PieceType::Queen => QUEEN_VALUE,
-            PieceType::King => KING_VALUE,
-        }
-    }
 }
 
 impl Color {
@@ -352,9 +338,9 @@ mod tests {
 
     #[test]
     fn piece_from_char() {
-        assert_eq!(Piece::from_char('P').is_ok(), true);
-        assert_eq!(Piece::from_char('n').is_ok(), true);
-        assert_eq!(Piece::from_char('Z').is_err(), true);
+        assert!(Piece::from_char('P').is_ok());
+        assert!(Piece::from_char('n').is_ok());
+        assert!(Piece::from_char('Z').is_err());
     }
 
     #[test]
@@ -377,23 +363,23 @@ mod tests {
 
     #[test]
     fn piece_is_same_color() {
-        assert_eq!(Piece::WPawn.is_same_color(Piece::WRook), true);
-        assert_eq!(Piece::WKing.is_same_color(Piece::BRook), false);
+        assert!(Piece::WPawn.is_same_color(Piece::WRook));
+        assert!(!Piece::WKing.is_same_color(Piece::BRook));
     }
 
     #[test]
     fn piece_is_piece_type() {
-        assert_eq!(Piece::WBishop.is_bishop(), true);
-        assert_eq!(Piece::BQueen.is_queen(), true);
-        assert_eq!(Piece::BPawn.is_pawn(), true);
-        assert_eq!(Piece::WQueen.is_pawn(), false);
+        assert!(Piece::WBishop.is_bishop());
+        assert!(Piece::BQueen.is_queen());
+        assert!(Piece::BPawn.is_pawn());
+        assert!(!Piece::WQueen.is_pawn());
     }
 
     #[test]
     fn piece_is_slider() {
-        assert_eq!(Piece::WKing.is_slider(), false);
-        assert_eq!(Piece::BRook.is_slider(), true);
-        assert_eq!(Piece::WQueen.is_slider(), true);
+        assert!(!Piece::WKing.is_slider());
+        assert!(Piece::BRook.is_slider());
+        assert!(Piece::WQueen.is_slider());
     }
 
     #[test]
@@ -411,6 +397,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn color_index_test() {
         let vec = vec!['a', 'b', 'c'];
         assert_eq!(vec[Color::White], 'a');
@@ -418,6 +405,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn color_index_mut_test() {
         let mut vec = vec![0, 1, 2];
         vec[Color::Black] = 99;
@@ -1,15 +1,9 @@
 use super::{
-    piece::{Color, Piece, PieceType, PIECES},
-    square::{Square, SQUARES},
+    piece::{Color, Piece},
+    score::Score,
+    square::Square,
 };
 
-/// Represents the game phase for piece-square table evaluation
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub enum Phase {
-    Opening,
-    Endgame,
-}
-
 /// Piece-square tables for both opening and endgame phases
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct PieceSquareTable {
@@ -29,20 +23,23 @@ impl PieceSquareTable {
         }
     }
 
-    /// Gets the piece-square table value for a given piece, square, and phase
+    /// Returns the opening and endgame tables of every piece type, for changing them
+    #[inline]
+    pub fn get_tables_mut(&mut self) -> (&mut [[i32; 64]; 6], &mut [[i32; 64]; 6]) {
+        (&mut self.openings, &mut self.endgames)
+    }
+
+    /// Gets the opening and endgame piece-square table values for a given piece and square
     #[inline(always)]
-    pub fn get_value(&self, phase: Phase, piece: Piece, square: Square) -> i32 {
-        let piece_type = piece.get_type();
-        let color = piece.get_color();
-        let table = match phase {
-            Phase::Opening => &self.openings[piece_type.to_index()],
-            Phase::Endgame => &self.endgames[piece_type.to_index()],
-        };
+    pub fn get_value(&self, piece: Piece, square: Square) -> Score {
+        let piece_type = piece.get_type().to_index();
 
-        match color {
-            Color::White => table[square.flip()],
-            Color::Black => table[square],
-        }
+        // Tables are laid out from White's point of view with rank 8 first
+        let index = match piece.get_color() {
+            Color::White => square.to_index() ^ 56,
+            Color::Black => square.to_index(),
+        };
+        Score::new(self.openings[piece_type][index], self.endgames[piece_type][index])
     }
 }
 
@@ -208,15 +205,15 @@ mod tests {
         let pst = PieceSquareTable::new();
 
         // Test white pawn in opening
-        assert_eq!(pst.get_value(Phase::Opening, Piece::WPawn, Square::A2), 50);
+        assert_eq!(pst.get_value(Piece::WPawn, Square::A7).get_mg(), 50);
 
         // Test black pawn in opening
-        assert_eq!(pst.get_value(Phase::Opening, Piece::BPawn, Square::A7), 50);
+        assert_eq!(pst.get_value(Piece::BPawn, Square::A2).get_mg(), 50);
 
         // Test white king in endgame
-        assert_eq!(pst.get_value(Phase::Endgame, Piece::WKing, Square::E4), 40);
+        assert_eq!(pst.get_value(Piece::WKing, Square::E4).get_eg(), 40);
 
         // Test black king in endgame
-        assert_eq!(pst.get_value(Phase::Endgame, Piece::BKing, Square::E5), 40);
+        assert_eq!(pst.get_value(Piece::BKing, Square::E5).get_eg(), 40);
     }
 }
@@ -0,0 +1,101 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// Game phase with every minor and major piece still on the board
+pub const MAX_PHASE: i32 = 24;
+
+/// A pair of middlegame and endgame evaluation values, blended by the game phase
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Score {
+    mg: i32,
+    eg: i32,
+}
+
+impl Score {
+    pub const ZERO: Score = Score::new(0, 0);
+
+    #[inline(always)]
+    pub const fn new(mg: i32, eg: i32) -> Self {
+        Self { mg, eg }
+    }
+
+    #[inline(always)]
+    pub const fn get_mg(self) -> i32 {
+        self.mg
+    }
+
+    #[inline(always)]
+    pub const fn get_eg(self) -> i32 {
+        self.eg
+    }
+
+    /// Interpolates between the endgame value at phase 0 and the middlegame value at `MAX_PHASE`
+    #[inline(always)]
+    pub const fn taper(self, phase: i32) -> i32 {
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    #[inline(always)]
+    fn add(self, other: Score) -> Score {
+        Score::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl AddAssign for Score {
+    #[inline(always)]
+    fn add_assign(&mut self, other: Score) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    #[inline(always)]
+    fn sub(self, other: Score) -> Score {
+        Score::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl SubAssign for Score {
+    #[inline(always)]
+    fn sub_assign(&mut self, other: Score) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    #[inline(always)]
+    fn neg(self) -> Score {
+        Score::new(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+
+    #[inline(always)]
+    fn mul(self, factor: i32) -> Score {
+        Score::new(self.mg * factor, self.eg * factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taper() {
+        let score = Score::new(100, -20);
+        assert_eq!(score.taper(MAX_PHASE), 100);
+        assert_eq!(score.taper(0), -20);
+        assert_eq!(score.taper(MAX_PHASE / 2), 40);
+        assert_eq!((score * 2 - Score::new(50, 0)).taper(MAX_PHASE), 150);
+        assert_eq!(-score + score, Score::ZERO);
+    }
+}
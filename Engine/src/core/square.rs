@@ -106,16 +106,8 @@ impl Square {
     }
 
     #[inline(always)]
-    pub fn to_bitboard(&self) -> Bitboard {
-        Bitboard::new_from_square(*self)
-    }
-
-    #[inline]
-    pub fn to_string(self) -> String {
-        let mut s = String::with_capacity(2);
-        s.push((self.get_file() as u8 + b'a') as char);
-        s.push((self.get_rank() as u8 + b'1') as char);
-        s
+    pub fn to_bitboard(self) -> Bitboard {
+        Bitboard::new_from_square(self)
     }
 
     #[inline(always)]
@@ -125,42 +117,42 @@ impl Square {
 
     #[inline(always)]
     pub const fn is_light(self) -> bool {
-        ((self as u8) / 8 + (self as u8) % 8) % 2 == 1
+        !self.is_dark()
     }
 
     #[inline(always)]
     pub const fn is_dark(self) -> bool {
-        ((self as u8) / 8 + (self as u8) % 8) % 2 == 0
+        ((self as u8) / 8 + (self as u8) % 8) & 1 == 0
     }
 
     #[inline(always)]
     pub const fn get_next(self) -> Square {
-        unsafe { std::mem::transmute(((self as u8 + 1) % 8) as u8) }
+        unsafe { std::mem::transmute((self as u8 + 1) % 8) }
     }
 
     #[inline(always)]
     pub const fn get_prev(self) -> Square {
-        unsafe { std::mem::transmute(((self as u8).wrapping_sub(1) % 8) as u8) }
+        unsafe { std::mem::transmute((self as u8).wrapping_sub(1) % 8) }
     }
 
     #[inline(always)]
     pub const fn move_up(self, n: u8) -> Square {
-        unsafe { std::mem::transmute(((self as u8).wrapping_add(n * 8) % 64) as u8) }
+        unsafe { std::mem::transmute((self as u8).wrapping_add(n * 8) % 64) }
     }
 
     #[inline(always)]
     pub const fn move_down(self, n: u8) -> Square {
-        unsafe { std::mem::transmute(((self as u8).wrapping_sub(n * 8) % 64) as u8) }
+        unsafe { std::mem::transmute((self as u8).wrapping_sub(n * 8) % 64) }
     }
 
     #[inline(always)]
     pub const fn move_left(self, n: u8) -> Square {
-        unsafe { std::mem::transmute(((self as u8).wrapping_sub(n) % 64) as u8) }
+        unsafe { std::mem::transmute((self as u8).wrapping_sub(n) % 64) }
     }
 
     #[inline(always)]
     pub const fn move_right(self, n: u8) -> Square {
-        unsafe { std::mem::transmute(((self as u8).wrapping_add(n) % 64) as u8) }
+        unsafe { std::mem::transmute((self as u8).wrapping_add(n) % 64) }
     }
 
     #[inline(always)]
@@ -176,6 +168,12 @@ impl Square {
     }
 }
 
+impl std::fmt::Display for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}{}", (self.get_file() as u8 + b'a') as char, (self.get_rank() as u8 + b'1') as char)
+    }
+}
+
 impl File {
     pub fn from_index(index: usize) -> File {
         FILES[index]
@@ -370,6 +368,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn test_index_operations() {
         let vec = vec![0, 1, 2, 3, 4, 5, 6, 7];
         let array = [0, 1, 2, 3, 4, 5, 6, 7];
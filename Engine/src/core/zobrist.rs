@@ -2,7 +2,10 @@ use super::{
     piece::{Color, Piece, PieceType},
     square::{Square, SQUARES},
 };
-use rand::Rng;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Seed of the random keys, fixed so that every hasher, and every run of the engine, hashes positions the same way
+const ZOBRIST_SEED: u64 = 0x0B57_AC1E_C4E5_5000;
 
 /// A Zobrist hashing implementation for chess positions.
 ///
@@ -22,10 +25,10 @@ pub struct ZobristHasher {
 }
 
 impl ZobristHasher {
-    /// Creates a new Zobrist hasher with randomly initialized values.
+    /// Creates a new Zobrist hasher with pseudo-randomly initialized values.
     #[inline]
     pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
+        let mut rng = StdRng::seed_from_u64(ZOBRIST_SEED);
         let mut hasher = Self {
             pieces: [0; 768],
             pawns: [0; 128],
@@ -108,7 +111,6 @@ impl ZobristHasher {
         // Update piece hash
         *zobrist_hash ^= self.pieces[64 * piece_idx + from_idx];
         *zobrist_hash ^= self.pieces[64 * piece_idx + to_idx];
-        *zobrist_hash ^= self.turn;
 
         // Update pawn hash if needed
         if piece.get_type() == PieceType::Pawn {
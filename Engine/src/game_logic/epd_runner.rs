@@ -0,0 +1,355 @@
+use std::{
+    io::{self, BufRead, Write},
+    time::Duration,
+};
+
+use crate::{
+    core::epd::EpdRecord,
+    move_logic::{
+        move_encode::Move,
+        move_eval::{self, SearchLimits, SearchReport, SearchSignals},
+    },
+};
+
+use super::game::GameState;
+
+/// Outcome of searching one test-suite position
+#[derive(Clone, Debug)]
+pub struct EpdResult {
+    pub id: String,
+    pub fen: String,
+    /// Best moves (`bm`) the position expects, in SAN
+    pub best_moves: Vec<String>,
+    /// Moves to avoid (`am`), in SAN
+    pub avoid_moves: Vec<String>,
+    /// Expected evaluation in centipawns (`ce`), to compare with `score`
+    pub expected_score: Option<i32>,
+    /// Comment of the position (`c0`)
+    pub comment: Option<String>,
+    /// Move the engine chose, in SAN
+    pub engine_move: Option<String>,
+    pub solved: bool,
+    /// Time of the iteration from which the engine kept choosing a correct move
+    pub solve_time: Option<Duration>,
+    pub depth: u8,
+    pub score: i32,
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+/// Totals over the positions of a suite
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpdSummary {
+    pub solved: usize,
+    pub total: usize,
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+impl EpdSummary {
+    pub fn new(results: &[EpdResult]) -> Self {
+        Self {
+            solved: results.iter().filter(|r| r.solved).count(),
+            total: results.len(),
+            nodes: results.iter().map(|r| r.nodes).sum(),
+            time: results.iter().map(|r| r.time).sum(),
+        }
+    }
+}
+
+/// Moves a position is judged by, resolved from the SAN operands of its record
+struct Expectation {
+    best_moves: Vec<Move>,
+    avoid_moves: Vec<Move>,
+    /// Moves to a forced mate (`dm`)
+    direct_mate: Option<u32>,
+}
+
+impl Expectation {
+    fn is_correct(&self, m: Move) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(&m)) && !self.avoid_moves.contains(&m)
+    }
+
+    /// Checks the final search result; a `dm` position also needs a mate found in at most that many moves
+    fn is_solved(&self, m: Move, report: Option<&SearchReport>) -> bool {
+        let mate_found = match self.direct_mate {
+            Some(moves) => report.and_then(|r| r.mate_in()).is_some_and(|n| n > 0 && n as u32 <= moves),
+            None => true,
+        };
+        self.is_correct(m) && mate_found
+    }
+}
+
+/// Searches the position of `record` within `limits` and checks the engine's move against its `bm`, `am` and `dm` operations.
+/// The transposition table is cleared first, so results do not depend on the previous positions.
+pub fn run_position(game_state: &mut GameState, record: &EpdRecord, limits: &SearchLimits) -> Result<EpdResult, String> {
+    let fen = record.get_fen();
+    game_state.set_position(Some(&fen))?;
+    game_state.clear_transposition_table();
+
+    let resolve = |sans: &[String]| -> Result<Vec<Move>, String> {
+        sans.iter()
+            .map(|san| Move::from_san(san, game_state.get_board_state(), game_state.get_pregen_attacks()).map_err(|e| format!("{}: {}", san, e)))
+            .collect()
+    };
+    let expectation = Expectation {
+        best_moves: resolve(record.get_best_moves())?,
+        avoid_moves: resolve(record.get_avoid_moves())?,
+        direct_mate: record.get_direct_mate(),
+    };
+
+    let mut last_report: Option<SearchReport> = None;
+    let mut solve_time = None;
+    let mut report = |r: &SearchReport| {
+        match r.pv.first() {
+            Some(&m) if expectation.is_correct(m) => solve_time = solve_time.or(Some(r.time)),
+            _ => solve_time = None,
+        }
+        last_report = Some(r.clone());
+    };
+    let board_state = game_state.get_board_state().clone();
+    let best_move = move_eval::find_best_move(game_state, limits, &SearchSignals::default(), &mut report);
+
+    let solved = best_move.is_some_and(|m| expectation.is_solved(m, last_report.as_ref()));
+    let last_report = last_report.as_ref();
+    Ok(EpdResult {
+        id: record.get_id().unwrap_or_default().to_string(),
+        fen,
+        best_moves: record.get_best_moves().to_vec(),
+        avoid_moves: record.get_avoid_moves().to_vec(),
+        expected_score: record.get_centipawn_eval(),
+        comment: record.get_comment().map(str::to_string),
+        engine_move: best_move.map(|m| m.to_san(&board_state, game_state.get_pregen_attacks())),
+        solved,
+        solve_time: solve_time.filter(|_| solved),
+        depth: last_report.map_or(0, |r| r.depth),
+        score: last_report.map_or(0, |r| r.score),
+        nodes: last_report.map_or(0, |r| r.nodes),
+        time: last_report.map_or(Duration::ZERO, |r| r.time),
+    })
+}
+
+/// Runs every position of an EPD suite, calling `on_result` after each one.
+/// Blank lines and lines starting with `#` are skipped; invalid records are reported on stderr and skipped.
+pub fn run_suite<R: BufRead>(
+    reader: R,
+    game_state: &mut GameState,
+    limits: &SearchLimits,
+    on_result: &mut dyn FnMut(&EpdResult),
+) -> io::Result<Vec<EpdResult>> {
+    let mut results = Vec::new();
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match EpdRecord::parse(&line).and_then(|record| run_position(game_state, &record, limits)) {
+            Ok(result) => {
+                on_result(&result);
+                results.push(result);
+            }
+            Err(e) => eprintln!("line {}: {}", line_number + 1, e),
+        }
+    }
+    Ok(results)
+}
+
+/// Formats a result as one line of console output
+pub fn format_result(result: &EpdResult) -> String {
+    let expected = match (result.best_moves.is_empty(), result.avoid_moves.is_empty()) {
+        (false, _) => format!("bm {}", result.best_moves.join(" ")),
+        (true, false) => format!("am {}", result.avoid_moves.join(" ")),
+        (true, true) => "-".to_string(),
+    };
+    let solve_time = result.solve_time.map_or("-".to_string(), |t| format!("{}ms", t.as_millis()));
+    format!(
+        "{:<16} {:<7} {:<16} got {:<8} depth {:>2} score {:>6} nodes {:>10} solved in {}",
+        result.id,
+        if result.solved { "solved" } else { "FAILED" },
+        expected,
+        result.engine_move.as_deref().unwrap_or("-"),
+        result.depth,
+        result.score,
+        result.nodes,
+        solve_time
+    )
+}
+
+/// Writes the results as CSV with a header row
+pub fn write_csv(results: &[EpdResult], out: &mut dyn Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "id,fen,best_moves,avoid_moves,engine_move,solved,solve_time_ms,depth,score,nodes,time_ms,expected_score,comment"
+    )?;
+    for r in results {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            csv_field(&r.id),
+            csv_field(&r.fen),
+            csv_field(&r.best_moves.join(" ")),
+            csv_field(&r.avoid_moves.join(" ")),
+            csv_field(r.engine_move.as_deref().unwrap_or("")),
+            r.solved,
+            r.solve_time.map_or(String::new(), |t| t.as_millis().to_string()),
+            r.depth,
+            r.score,
+            r.nodes,
+            r.time.as_millis(),
+            r.expected_score.map_or(String::new(), |ce| ce.to_string()),
+            csv_field(r.comment.as_deref().unwrap_or(""))
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the results and their summary as a JSON object
+pub fn write_json(results: &[EpdResult], out: &mut dyn Write) -> io::Result<()> {
+    let moves = |moves: &[String]| format!("[{}]", moves.iter().map(|m| json_string(m)).collect::<Vec<_>>().join(","));
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"results\": [")?;
+    for (i, r) in results.iter().enumerate() {
+        writeln!(
+            out,
+            "    {{\"id\": {}, \"fen\": {}, \"best_moves\": {}, \"avoid_moves\": {}, \"engine_move\": {}, \"solved\": {}, \
+             \"solve_time_ms\": {}, \"depth\": {}, \"score\": {}, \"nodes\": {}, \"time_ms\": {}, \
+             \"expected_score\": {}, \"comment\": {}}}{}",
+            json_string(&r.id),
+            json_string(&r.fen),
+            moves(&r.best_moves),
+            moves(&r.avoid_moves),
+            r.engine_move.as_deref().map_or("null".to_string(), json_string),
+            r.solved,
+            r.solve_time.map_or("null".to_string(), |t| t.as_millis().to_string()),
+            r.depth,
+            r.score,
+            r.nodes,
+            r.time.as_millis(),
+            r.expected_score.map_or("null".to_string(), |ce| ce.to_string()),
+            r.comment.as_deref().map_or("null".to_string(), json_string),
+            if i + 1 < results.len() { "," } else { "" }
+        )?;
+    }
+    writeln!(out, "  ],")?;
+    let summary = EpdSummary::new(results);
+    writeln!(
+        out,
+        "  \"summary\": {{\"solved\": {}, \"total\": {}, \"nodes\": {}, \"time_ms\": {}}}",
+        summary.solved,
+        summary.total,
+        summary.nodes,
+        summary.time.as_millis()
+    )?;
+    writeln!(out, "}}")
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUITE: &str = r#"# Mate in one, a move to avoid, a direct mate and a failure
+6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id "mate.1"; c0 "back rank mate";
+3r2k1/5ppp/8/8/8/8/5PPP/R5K1 w - - am Ra8; id "avoid.1"; ce 0;
+k7/8/1K6/8/8/8/8/7R w - - dm 1; id "dm.1";
+k7/8/1K6/8/8/8/8/7R w - - am Rh8; id "avoid.fail";
+
+r1bqkbnr/pppp1ppp/2n5/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - bm Qxf7; id "illegal";
+"#;
+
+    fn run(limits: &SearchLimits) -> Vec<EpdResult> {
+        let mut game_state = GameState::new(None, Duration::ZERO);
+        run_suite(SUITE.as_bytes(), &mut game_state, limits, &mut |_| {}).unwrap()
+    }
+
+    #[test]
+    fn test_run_suite() {
+        let results = run(&SearchLimits {
+            depth: Some(4),
+            ..Default::default()
+        });
+        // The record with an illegal best move is skipped
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["mate.1", "avoid.1", "dm.1", "avoid.fail"]);
+
+        assert!(results[0].solved);
+        assert_eq!(results[0].engine_move.as_deref(), Some("Ra8#"));
+        assert!(results[0].solve_time.is_some());
+        assert_eq!(results[0].comment.as_deref(), Some("back rank mate"));
+        assert_eq!(results[0].expected_score, None);
+
+        assert!(results[1].solved, "{:?}", results[1]);
+        assert_ne!(results[1].engine_move.as_deref(), Some("Ra8"));
+        assert_eq!(results[1].expected_score, Some(0));
+        assert_eq!(results[1].comment, None);
+
+        assert!(results[2].solved);
+        assert_eq!(results[2].engine_move.as_deref(), Some("Rh8#"));
+
+        assert!(!results[3].solved);
+        assert_eq!(results[3].solve_time, None);
+
+        let summary = EpdSummary::new(&results);
+        assert_eq!((summary.solved, summary.total), (3, 4));
+        assert_eq!(summary.nodes, results.iter().map(|r| r.nodes).sum::<u64>());
+    }
+
+    #[test]
+    fn test_output_formats() {
+        let result = EpdResult {
+            id: "a,\"b\"".to_string(),
+            fen: "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string(),
+            best_moves: vec!["Ra8#".to_string()],
+            avoid_moves: Vec::new(),
+            expected_score: Some(32000),
+            comment: Some("back rank".to_string()),
+            engine_move: Some("Ra8#".to_string()),
+            solved: true,
+            solve_time: Some(Duration::from_millis(3)),
+            depth: 2,
+            score: 31999,
+            nodes: 120,
+            time: Duration::from_millis(5),
+        };
+
+        let mut csv = Vec::new();
+        write_csv(std::slice::from_ref(&result), &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("\"a,\"\"b\"\"\",6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1,Ra8#,,Ra8#,true,3,2,31999,120,5,32000,back rank")
+        );
+
+        let mut json = Vec::new();
+        write_json(&[result], &mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains("{\"id\": \"a,\\\"b\\\"\", \"fen\": \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\", \"best_moves\": [\"Ra8#\"]"));
+        assert!(json.contains("\"avoid_moves\": [], \"engine_move\": \"Ra8#\", \"solved\": true, \"solve_time_ms\": 3"));
+        assert!(json.contains("\"time_ms\": 5, \"expected_score\": 32000, \"comment\": \"back rank\"}"));
+        assert!(json.contains("\"summary\": {\"solved\": 1, \"total\": 1, \"nodes\": 120, \"time_ms\": 5}"));
+    }
+}
@@ -1,51 +1,83 @@
-use core::time;
-use std::collections::HashMap;
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use crate::{
-    core::{attack_pregen::PregenAttacks, board_state::BoardState, piece::Color, zobrist::ZobristHasher},
-    move_logic::{move_encode::Move, move_eval, pseudo_move_gen},
+    core::{
+        attack_pregen::PregenAttacks,
+        board_state::{BoardState, UndoInfo},
+        eval_params::EvalParams,
+        pawn_table::PawnTable,
+        piece::Color,
+        zobrist::ZobristHasher,
+    },
+    move_logic::{
+        legal_move_gen,
+        move_encode::Move,
+        move_eval::{self, SearchLimits, SearchReport, SearchSignals},
+        time_management::DEFAULT_MOVE_OVERHEAD_MS,
+        transposition_table::{TTEntry, TranspositionTable},
+    },
 };
 
-use super::user_input;
+use super::{
+    game_result::{GameResult, Termination},
+    pgn::{self, PgnGame},
+    user_input::{self, PlayerAction},
+};
 
-/// Entry stored in the transposition table
-#[derive(Clone, Debug)]
-pub struct TTEntry {
-    pub score: i32,
-    pub depth: u8,
-    pub entry_type: move_eval::EntryType,
-}
+/// Maximum number of search threads
+pub const MAX_THREADS: usize = 256;
+/// File console games are appended to
+const PGN_FILE: &str = "games.pgn";
+/// Name of the engine in game records
+pub const ENGINE_NAME: &str = "RustyChessEngine";
 
-/// Represents the current state of a chess game, with history and repetition tracking
+/// Represents the current state of a chess game, with history and repetition tracking.
+/// Clones share the attack tables and the transposition and pawn hash tables, so each search thread can work on its own clone.
+#[derive(Clone)]
 pub struct GameState {
     /// The current board state
     board_state: BoardState,
     /// Pre-generated attack patterns for all pieces
-    pregen_attacks: PregenAttacks,
+    pregen_attacks: Arc<PregenAttacks>,
     /// Zobrist hasher for position hashing
     zobrist: ZobristHasher,
-    /// Transposition table for caching evaluated positions
-    transposition_table: HashMap<u64, TTEntry>,
-    /// Move history for undoing moves (old board states)
-    history: Vec<BoardState>,
+    /// Transposition table for caching evaluated positions, shared by all search threads
+    transposition_table: Arc<TranspositionTable>,
+    /// Number of threads used by the search
+    threads: usize,
+    /// Pawn hash table of each search thread, kept from one search to the next
+    pawn_tables: Arc<Mutex<Vec<PawnTable>>>,
+    /// FEN of the position the game started from, None for the standard start position
+    start_fen: Option<String>,
+    /// Moves played with the records needed to undo them
+    history: Vec<(Move, UndoInfo)>,
+    /// Undo records of the null moves currently made by the search, with the index in `zobrist_history` of the position after each
+    null_move_history: Vec<(UndoInfo, usize)>,
+    /// Board states before each move, used to check that `unmake_move` restores them exactly
+    #[cfg(debug_assertions)]
+    debug_history: Vec<BoardState>,
     /// Zobrist history for repetition detection
     zobrist_history: Vec<u64>,
     ///clock time (for time management)
-    clock_time: std::time::Duration,
-    /// Number of moves remaining in the game (for time management)
-    moves_to_go: u32,
+    clock_time: Duration,
+    /// Number of moves remaining until the next time control, None for sudden death
+    moves_to_go: Option<u32>,
+    /// Time reserved per move for communication delays
+    move_overhead: Duration,
+    /// Centipawns the engine considers a draw to be worth less than an equal position; negative values make it seek draws
+    contempt: i32,
+    /// Side that lost by resignation or timeout, with how it lost
+    forfeit: Option<(Color, Termination)>,
 }
 
 impl GameState {
     /// Creates a new game state from an optional FEN string and time limit
-    pub fn new(fen_str: Option<&str>, time_limit: std::time::Duration) -> Self {
+    pub fn new(fen_str: Option<&str>, time_limit: Duration) -> Self {
         let zobrist = ZobristHasher::new();
-        let moves_to_go = match time_limit.as_secs() {
-            0..=59 => 20,
-            60..=179 => 30,
-            180..=599 => 40,
-            _ => 50,
-        };
 
         // Initialize board state and compute initial hash
         let board = BoardState::new(fen_str, &zobrist).unwrap();
@@ -53,16 +85,97 @@ impl GameState {
 
         Self {
             board_state: board,
-            pregen_attacks: PregenAttacks::init(),
+            pregen_attacks: Arc::new(PregenAttacks::init()),
             zobrist,
-            transposition_table: HashMap::with_capacity(1024),
+            transposition_table: Arc::new(TranspositionTable::default()),
+            threads: 1,
+            pawn_tables: Arc::new(Mutex::new(Vec::new())),
+            start_fen: fen_str.map(str::to_string),
             history: Vec::with_capacity(256),
+            null_move_history: Vec::with_capacity(16),
+            #[cfg(debug_assertions)]
+            debug_history: Vec::with_capacity(256),
             zobrist_history: vec![initial_hash],
             clock_time: time_limit,
-            moves_to_go,
+            moves_to_go: None,
+            move_overhead: Duration::from_millis(DEFAULT_MOVE_OVERHEAD_MS),
+            contempt: 0,
+            forfeit: None,
         }
     }
 
+    /// Replaces the current position with the one described by `fen_str` (or the start position),
+    /// clearing move and repetition history. The transposition table and evaluation parameters are kept.
+    pub fn set_position(&mut self, fen_str: Option<&str>) -> Result<(), String> {
+        let mut board = BoardState::new(fen_str, &self.zobrist)?;
+        board.set_eval_params(self.board_state.get_eval_params().clone());
+        self.zobrist_history.clear();
+        self.zobrist_history.push(board.get_zobrist_hash());
+        self.history.clear();
+        self.null_move_history.clear();
+        #[cfg(debug_assertions)]
+        self.debug_history.clear();
+        self.board_state = board;
+        self.start_fen = fen_str.map(str::to_string);
+        self.forfeit = None;
+        Ok(())
+    }
+
+    /// Returns the FEN of the position the game started from, None for the standard start position
+    #[inline]
+    pub fn get_start_fen(&self) -> Option<&str> {
+        self.start_fen.as_deref()
+    }
+
+    /// Returns the moves played since the start position, oldest first
+    #[inline]
+    pub fn get_moves(&self) -> impl Iterator<Item = Move> + '_ {
+        self.history.iter().map(|(m, _)| *m)
+    }
+
+    /// Clears all cached search results, e.g. when a new game starts
+    pub fn clear_transposition_table(&mut self) {
+        self.transposition_table.clear();
+    }
+
+    /// Takes the pawn hash tables of `threads` search threads, creating the missing ones, with their statistics reset.
+    /// They are given back with `return_pawn_tables` when the search ends.
+    pub fn take_pawn_tables(&self, threads: usize) -> Vec<PawnTable> {
+        let mut tables = std::mem::take(&mut *self.pawn_tables.lock().unwrap());
+        tables.resize_with(threads, PawnTable::default);
+        tables.iter_mut().for_each(PawnTable::new_search);
+        tables
+    }
+
+    pub fn return_pawn_tables(&self, tables: Vec<PawnTable>) {
+        *self.pawn_tables.lock().unwrap() = tables;
+    }
+
+    /// Discards the pawn hash tables, e.g. when a new game starts or the evaluation parameters change
+    pub fn clear_pawn_tables(&self) {
+        self.pawn_tables.lock().unwrap().iter_mut().for_each(PawnTable::clear);
+    }
+
+    /// Resizes the transposition table to about `size_mb` megabytes, discarding its entries
+    pub fn resize_transposition_table(&mut self, size_mb: usize) {
+        match Arc::get_mut(&mut self.transposition_table) {
+            Some(table) => table.resize(size_mb),
+            None => self.transposition_table = Arc::new(TranspositionTable::new(size_mb)),
+        }
+    }
+
+    /// Returns the number of threads used by the search
+    #[inline]
+    pub fn get_threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Sets the number of search threads, clamped to 1..=MAX_THREADS
+    #[inline]
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.clamp(1, MAX_THREADS);
+    }
+
     /// Returns a reference to the current board state
     #[inline(always)]
     pub fn get_board_state(&self) -> &BoardState {
@@ -75,63 +188,127 @@ impl GameState {
         &self.pregen_attacks
     }
 
-    /// Transposition table lookup: returns Some(&TTEntry) if present
-    pub fn tt_lookup(&self, key: u64) -> Option<&TTEntry> {
-        self.transposition_table.get(&key)
+    /// Transposition table lookup: returns Some(TTEntry) if present
+    pub fn tt_lookup(&self, key: u64) -> Option<TTEntry> {
+        self.transposition_table.probe(key)
     }
 
     /// Transposition table insert or update
-    pub fn tt_insert(&mut self, key: u64, entry: TTEntry) {
-        self.transposition_table.insert(key, entry);
+    pub fn tt_insert(&self, key: u64, entry: TTEntry) {
+        self.transposition_table.store(key, entry);
+    }
+
+    /// Marks the start of a new search so older transposition table entries are replaced first
+    pub fn tt_new_search(&self) {
+        self.transposition_table.new_search();
+    }
+
+    /// Permille of the transposition table filled by the current search
+    pub fn tt_hashfull(&self) -> u32 {
+        self.transposition_table.hashfull()
     }
 
-    /// Returns the current number of moves to go
+    /// Returns the number of moves remaining until the next time control, None for sudden death
     #[inline]
-    pub fn get_moves_to_go(&self) -> u32 {
+    pub fn get_moves_to_go(&self) -> Option<u32> {
         self.moves_to_go
     }
 
-    /// Decrements the number of moves to go (minimum 1)
+    /// Sets the number of moves remaining until the next time control (minimum 1)
     #[inline]
-    pub fn decrement_moves_to_go(&mut self) {
-        if self.moves_to_go > 1 {
-            self.moves_to_go -= 1;
-        }
+    pub fn set_moves_to_go(&mut self, moves_to_go: Option<u32>) {
+        self.moves_to_go = moves_to_go.map(|n| n.max(1));
     }
 
     #[inline]
-    pub fn get_clock_time(&self) -> std::time::Duration {
+    pub fn get_clock_time(&self) -> Duration {
         self.clock_time
     }
 
     #[inline]
-    pub fn decrement_clock_time(&mut self, decrement: u64) {
-        self.clock_time = self.clock_time.saturating_sub(std::time::Duration::from_secs(decrement));
+    pub fn set_clock_time(&mut self, clock_time: Duration) {
+        self.clock_time = clock_time;
+    }
+
+    #[inline]
+    pub fn decrement_clock_time(&mut self, decrement: Duration) {
+        self.clock_time = self.clock_time.saturating_sub(decrement);
+    }
+
+    /// Returns the time reserved per move for communication delays
+    #[inline]
+    pub fn get_move_overhead(&self) -> Duration {
+        self.move_overhead
+    }
+
+    /// Sets the time reserved per move for communication delays
+    #[inline]
+    pub fn set_move_overhead(&mut self, move_overhead: Duration) {
+        self.move_overhead = move_overhead;
+    }
+
+    /// Returns the contempt in centipawns
+    #[inline]
+    pub fn get_contempt(&self) -> i32 {
+        self.contempt
+    }
+
+    #[inline]
+    pub fn set_contempt(&mut self, contempt: i32) {
+        self.contempt = contempt;
+    }
+
+    #[inline]
+    pub fn get_eval_params(&self) -> &EvalParams {
+        self.board_state.get_eval_params()
+    }
+
+    /// Evaluates positions with `params` from now on. The moves played so far are replayed so that
+    /// they can still be undone, and the transposition and pawn hash tables are cleared since their scores used the old parameters.
+    pub fn set_eval_params(&mut self, params: EvalParams) {
+        let moves: Vec<Move> = self.get_moves().collect();
+        let (start_fen, forfeit) = (self.start_fen.clone(), self.forfeit);
+        self.board_state.set_eval_params(Arc::new(params));
+        self.set_position(start_fen.as_deref()).expect("the start position was valid before");
+        for m in moves {
+            self.make_move(m);
+        }
+        self.forfeit = forfeit;
+        self.clear_transposition_table();
+        self.clear_pawn_tables();
     }
 
     /// Makes a move on the board, recording history and zobrist hash for undo and repetition
     #[inline]
     pub fn make_move(&mut self, m: Move) {
-        // Save previous board for undo
-        self.history.push(self.board_state.clone());
-        // Apply move, updating board_state and internal hash
-        self.board_state.make_move(m, &self.zobrist);
+        #[cfg(debug_assertions)]
+        self.debug_history.push(self.board_state.clone());
+        // Apply move in place, keeping what is needed to undo it
+        let undo = self.board_state.make_move(m, &self.zobrist);
+        self.history.push((m, undo));
         // Record new zobrist hash for repetition detection
         let h = self.board_state.get_zobrist_hash();
         self.zobrist_history.push(h);
     }
 
-    /// Makes a null move (used for null move pruning). Does NOT affect repetition tracking.
+    /// Makes a null move (used for null move pruning). Its position is recorded for repetition detection,
+    /// but repetitions are not looked for across it.
     #[inline]
     pub fn make_null_move(&mut self) {
-        self.board_state.make_null_move(&self.zobrist);
-        // Note: do not push to history or zobrist_history
+        let undo = self.board_state.make_null_move(&self.zobrist);
+        self.zobrist_history.push(self.board_state.get_zobrist_hash());
+        self.null_move_history.push((undo, self.zobrist_history.len() - 1));
     }
 
-    /// Unmakes a null move. Does NOT affect repetition tracking.
+    /// Unmakes a null move
     #[inline]
     pub fn unmake_null_move(&mut self) {
-        self.board_state.unmake_null_move(&self.zobrist);
+        let (undo, _) = self
+            .null_move_history
+            .pop()
+            .expect("GameState::unmake_null_move called without a null move");
+        self.zobrist_history.pop();
+        self.board_state.unmake_null_move(&undo);
     }
 
     /// Unmakes the last move, restoring board state and repetition history
@@ -142,111 +319,176 @@ impl GameState {
             panic!("unmake_move: zobrist_history empty");
         }
         // Restore previous board state
-        if let Some(prev_state) = self.history.pop() {
-            self.board_state = prev_state;
-        } else {
+        let Some((m, undo)) = self.history.pop() else {
             panic!("GameState::unmake_move called with empty history");
+        };
+        self.board_state.unmake_move(m, &undo);
+
+        #[cfg(debug_assertions)]
+        {
+            let expected = self.debug_history.pop().expect("debug history out of sync with move history");
+            debug_assert!(self.board_state == expected, "unmake_move did not restore the position before {}", m);
         }
     }
 
+    /// Returns the number of moves that can be taken back with `unmake_move`
+    #[inline]
+    pub fn get_history_len(&self) -> usize {
+        self.history.len()
+    }
+
     /// Checks if the given side is in check
     #[inline]
     pub fn is_check(&self, side: Color) -> bool {
         self.board_state.is_check(side, &self.pregen_attacks)
     }
 
-    /// Checks if the current position is checkmate
-    #[inline]
-    pub fn is_checkmate(&self) -> bool {
-        let side = self.board_state.get_side();
-        // If not in check, can't be checkmate
-        if !self.is_check(side) {
-            return false;
+    /// Returns how many times the current position has occurred, including now
+    pub fn repetition_count(&self) -> usize {
+        let current = self.board_state.get_zobrist_hash();
+        self.zobrist_history.iter().filter(|&&h| h == current).count()
+    }
+
+    /// Returns the half-move clock for fifty-move rule, delegating to BoardState
+    pub fn half_move_clock(&self) -> u8 {
+        self.board_state.get_half_moves()
+    }
+
+    /// Checks if the current position, `ply` moves below the root of a search, should be scored as a draw:
+    /// insufficient material, the fifty-move rule, or a repetition. A position that already occurred inside the
+    /// search tree counts as a draw, since the side that repeated it can repeat it again; a position from before
+    /// the root must have occurred twice. Only positions since the last capture, pawn move or null move are compared.
+    pub fn is_search_draw(&self, ply: usize) -> bool {
+        if self.half_move_clock() >= 100 || self.board_state.is_insufficient_material() {
+            return true;
+        }
+
+        let last = self.zobrist_history.len() - 1;
+        let mut reversible = (self.half_move_clock() as usize).min(last);
+        if let Some(&(_, null_index)) = self.null_move_history.last() {
+            reversible = reversible.min(last - null_index);
         }
-        // Generate pseudo-legal moves
-        let mut moves = Vec::with_capacity(256);
-        pseudo_move_gen::get_pseudo_moves(&self.board_state, &self.pregen_attacks, &mut moves);
-        // Test each move: if any gets out of check, not checkmate
-        for &m in &moves {
-            let mut temp = self.board_state.clone();
-            temp.make_move(m, &self.zobrist);
-            if !temp.is_check(side, &self.pregen_attacks) {
-                return false;
+        let current = self.zobrist_history[last];
+        let mut before_root = 0;
+        // The same side is to move every other ply, and a position cannot repeat in fewer than 4 plies
+        for distance in (4..=reversible).step_by(2) {
+            if self.zobrist_history[last - distance] == current {
+                if distance < ply {
+                    return true;
+                }
+                before_root += 1;
+                if before_root == 2 {
+                    return true;
+                }
             }
         }
-        true
+        false
     }
 
-    /// Checks if the current position is stalemate
-    #[inline]
-    pub fn is_stalemate(&self) -> bool {
-        let side = self.board_state.get_side();
-        // If in check, not stalemate
-        if self.is_check(side) {
-            return false;
+    /// Checks if the current position is drawn by insufficient material, repetition or the move counting rules.
+    /// Checkmate and stalemate are left to the caller.
+    pub fn draw_by_rule(&self) -> Option<Termination> {
+        if self.board_state.is_insufficient_material() {
+            return Some(Termination::InsufficientMaterial);
         }
-        let mut moves = Vec::with_capacity(256);
-        pseudo_move_gen::get_pseudo_moves(&self.board_state, &self.pregen_attacks, &mut moves);
-        for &m in &moves {
-            let mut temp = self.board_state.clone();
-            temp.make_move(m, &self.zobrist);
-            if !temp.is_check(side, &self.pregen_attacks) {
-                return false;
-            }
+        let repetitions = self.repetition_count();
+        if repetitions >= 5 {
+            Some(Termination::FivefoldRepetition)
+        } else if self.half_move_clock() >= 150 {
+            Some(Termination::SeventyFiveMoveRule)
+        } else if repetitions >= 3 {
+            Some(Termination::ThreefoldRepetition)
+        } else if self.half_move_clock() >= 100 {
+            Some(Termination::FiftyMoveRule)
+        } else {
+            None
         }
-        true
     }
 
-    /// Checks for threefold repetition draw: current position hash appears at least 3 times
-    pub fn is_repetition_draw(&self) -> bool {
-        let current = self.board_state.get_zobrist_hash();
-        let count = self.zobrist_history.iter().filter(|&&h| h == current).count();
-        count >= 3
+    /// Records that `side` resigned
+    pub fn resign(&mut self, side: Color) {
+        self.forfeit = Some((side, Termination::Resignation));
     }
 
-    /// Returns the half-move clock for fifty-move rule, delegating to BoardState
-    pub fn half_move_clock(&self) -> u8 {
-        self.board_state.get_half_moves()
+    /// Records that `side` ran out of time
+    pub fn record_timeout(&mut self, side: Color) {
+        self.forfeit = Some((side, Termination::Timeout));
+    }
+
+    /// Returns the result of the game if it is over.
+    /// A resignation or timeout takes precedence, then checkmate, which also wins over the move counting rules.
+    /// Threefold repetition and the fifty-move rule are treated as claimed.
+    pub fn game_result(&self) -> Option<GameResult> {
+        if let Some((loser, termination)) = self.forfeit {
+            // A timeout is a draw if no series of legal moves lets the opponent mate
+            if termination == Termination::Timeout && !self.board_state.can_mate(loser.opposite()) {
+                return Some(GameResult::Draw(Termination::Timeout));
+            }
+            return Some(GameResult::Win {
+                winner: loser.opposite(),
+                termination,
+            });
+        }
+
+        if !legal_move_gen::has_legal_moves(&self.board_state, &self.pregen_attacks) {
+            let side = self.board_state.get_side();
+            return Some(match self.is_check(side) {
+                true => GameResult::Win {
+                    winner: side.opposite(),
+                    termination: Termination::Checkmate,
+                },
+                false => GameResult::Draw(Termination::Stalemate),
+            });
+        }
+
+        self.draw_by_rule().map(GameResult::Draw)
+    }
+
+    /// Prints the result if the game is over, and returns true if it should continue.
+    /// A draw by threefold repetition or the fifty-move rule only ends the game if `claim_draw` is set.
+    fn check_game_over(&self, claim_draw: bool) -> bool {
+        match self.game_result() {
+            Some(result) if !claim_draw && !result.get_termination().is_automatic() => {
+                println!("A draw by {} can be claimed with `draw`", result.get_termination());
+                true
+            }
+            Some(result) => {
+                println!("{}", result);
+                false
+            }
+            None => true,
+        }
     }
 
-    /// Makes the engine's move and returns true if the game should continue
-    fn make_engine_move(&mut self) -> bool {
+    /// Makes the engine's move, recording the search result in `comments`, and returns true if the game should continue
+    fn make_engine_move(&mut self, comments: &mut Vec<Option<String>>) -> bool {
         let now = std::time::Instant::now();
-        if let Some(engine_move) = move_eval::find_best_move(self, self.get_clock_time()) {
-            let time_elapsed = now.elapsed().as_secs();
+        let side = self.board_state.get_side();
+        let limits = SearchLimits::from_clock(side, self.get_clock_time(), self.get_moves_to_go());
+        let mut last_report = None;
+        let mut report = |r: &SearchReport| {
+            print_search_report(r);
+            last_report = Some(r.clone());
+        };
+        if let Some(engine_move) = move_eval::find_best_move(self, &limits, &SearchSignals::default(), &mut report) {
+            let time_elapsed = now.elapsed();
 
             self.decrement_clock_time(time_elapsed);
 
-            if self.get_clock_time().as_secs() <= 0 {
-                print!("Times Up");
-                return false;
+            if self.get_clock_time().is_zero() {
+                self.record_timeout(side);
+                return self.check_game_over(false);
             }
 
-            println!("Time: {}s", time_elapsed);
-            println!("Move Selected: {:?}{:?}", engine_move.get_from(), engine_move.get_to());
+            println!("Time: {:.2}s", time_elapsed.as_secs_f64());
+            println!("Move Selected: {}", engine_move.to_san(&self.board_state, &self.pregen_attacks));
 
             self.make_move(engine_move);
+            comments.push(last_report.map(|r| pgn::engine_comment(&r, side, Some(self.get_clock_time()))));
             self.board_state.display_info(&self.pregen_attacks);
-            self.decrement_moves_to_go();
 
-            // Check for end conditions
-            if self.is_checkmate() {
-                println!("Checkmate!");
-                return false;
-            }
-            if self.is_stalemate() {
-                println!("Stalemate!");
-                return false;
-            }
-            if self.is_repetition_draw() {
-                println!("Draw by repetition!");
-                return false;
-            }
-            if self.half_move_clock() >= 100 {
-                println!("Draw by fifty-move rule!");
-                return false;
-            }
-            true
+            // The player is to move and may claim a draw
+            self.check_game_over(false)
         } else {
             println!("Time: {}s", now.elapsed().as_secs());
             println!("No move found");
@@ -255,30 +497,24 @@ impl GameState {
     }
 
     /// Makes the player's move and returns true if the game should continue
-    fn make_player_move(&mut self) -> bool {
-        let user_move = user_input::get_user_move(self);
+    fn make_player_move(&mut self, comments: &mut Vec<Option<String>>) -> bool {
+        let user_move = loop {
+            match user_input::get_user_move(self) {
+                PlayerAction::Move(m) => break m,
+                PlayerAction::Resign => {
+                    self.resign(self.board_state.get_side());
+                    return self.check_game_over(false);
+                }
+                PlayerAction::ClaimDraw if self.draw_by_rule().is_some() => return self.check_game_over(true),
+                PlayerAction::ClaimDraw => println!("No draw to claim"),
+            }
+        };
         self.make_move(user_move);
+        comments.push(None);
         self.board_state.display_info(&self.pregen_attacks);
-        self.decrement_moves_to_go();
 
-        // Check for end conditions
-        if self.is_checkmate() {
-            println!("Checkmate!");
-            return false;
-        }
-        if self.is_stalemate() {
-            println!("Stalemate!");
-            return false;
-        }
-        if self.is_repetition_draw() {
-            println!("Draw by repetition!");
-            return false;
-        }
-        if self.half_move_clock() >= 100 {
-            println!("Draw by fifty-move rule!");
-            return false;
-        }
-        true
+        // The engine is to move and claims any draw it is entitled to
+        self.check_game_over(true)
     }
 
     /// Runs the main game loop
@@ -288,25 +524,140 @@ impl GameState {
         let mut input = String::new();
         std::io::stdin().read_line(&mut input).unwrap();
         let engine_first = input.trim() == "1";
+        let engine_side = match engine_first {
+            true => self.board_state.get_side(),
+            false => self.board_state.get_opposite_side(),
+        };
 
         self.board_state.display_info(&self.pregen_attacks);
 
+        // Comments for each move played, with the engine's evaluation and clock
+        let mut comments = Vec::new();
         loop {
             if engine_first {
-                if !self.make_engine_move() {
+                if !self.make_engine_move(&mut comments) {
                     break;
                 }
-                if !self.make_player_move() {
+                if !self.make_player_move(&mut comments) {
                     break;
                 }
             } else {
-                if !self.make_player_move() {
+                if !self.make_player_move(&mut comments) {
                     break;
                 }
-                if !self.make_engine_move() {
+                if !self.make_engine_move(&mut comments) {
                     break;
                 }
             }
         }
+
+        self.save_game(engine_side, comments);
+    }
+
+    /// Appends the finished console game to `PGN_FILE`
+    fn save_game(&self, engine_side: Color, comments: Vec<Option<String>>) {
+        let mut game = PgnGame::from_game_state(self);
+        game.set_tag("Event", "Console game");
+        let (white, black) = match engine_side {
+            Color::White => (ENGINE_NAME, "Player"),
+            Color::Black => ("Player", ENGINE_NAME),
+        };
+        game.set_tag("White", white);
+        game.set_tag("Black", black);
+        for (i, comment) in comments.into_iter().enumerate() {
+            game.set_move_comment(i, comment);
+        }
+
+        game.set_result(self.game_result().map_or("*", GameResult::to_pgn));
+
+        let saved = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(PGN_FILE)
+            .and_then(|mut file| writeln!(file, "{}", game));
+        match saved {
+            Ok(()) => println!("Game saved to {}", PGN_FILE),
+            Err(e) => println!("Could not save the game to {}: {}", PGN_FILE, e),
+        }
+    }
+}
+
+/// Prints the progress of an engine search to the console
+fn print_search_report(report: &SearchReport) {
+    let score = match report.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("{}", report.score),
+    };
+    let pv: Vec<String> = report.pv.iter().map(|m| m.to_string()).collect();
+    println!(
+        "Depth {:>2}/{:<2} Score: {:>8} Nodes: {:>9} Time: {:?} PV: {}",
+        report.depth,
+        report.selective_depth,
+        score,
+        report.nodes,
+        report.time,
+        pv.join(" ")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(game_state: &mut GameState, moves: &[&str]) {
+        for san in moves {
+            let m = Move::from_san(san, game_state.get_board_state(), game_state.get_pregen_attacks()).unwrap();
+            game_state.make_move(m);
+        }
+    }
+
+    #[test]
+    fn test_search_repetition() {
+        let mut game_state = GameState::new(None, Duration::ZERO);
+        play(&mut game_state, &["Nf3", "Nf6", "Ng1", "Ng8", "Nf3"]);
+        // One earlier occurrence is a draw only if it is inside the search tree
+        assert!(game_state.is_search_draw(5));
+        assert!(!game_state.is_search_draw(4));
+        assert!(!game_state.is_search_draw(0));
+        play(&mut game_state, &["Nf6", "Ng1", "Ng8", "Nf3"]);
+        assert!(game_state.is_search_draw(0));
+
+        // Positions before a null move are not compared
+        let mut game_state = GameState::new(None, Duration::ZERO);
+        play(&mut game_state, &["Nf3"]);
+        game_state.make_null_move();
+        play(&mut game_state, &["Ng1"]);
+        game_state.make_null_move();
+        assert_eq!(
+            game_state.get_board_state().get_zobrist_hash(),
+            GameState::new(None, Duration::ZERO).get_board_state().get_zobrist_hash()
+        );
+        assert!(!game_state.is_search_draw(4));
+        game_state.unmake_null_move();
+        game_state.unmake_move();
+        game_state.unmake_null_move();
+        play(&mut game_state, &["Nf6", "Ng1", "Ng8"]);
+        // Unmaking the null moves restores the history
+        assert!(game_state.is_search_draw(5));
+        assert!(!game_state.is_search_draw(4));
+    }
+
+    #[test]
+    fn test_pawn_tables_are_kept_between_searches() {
+        let mut game_state = GameState::new(Some("r1bqkb1r/pp3ppp/2np1n2/4p3/2B1P3/2N2N2/PP3PPP/R1BQK2R w KQkq - 0 7"), Duration::ZERO);
+        let search = |game_state: &mut GameState| {
+            game_state.clear_transposition_table();
+            let limits = SearchLimits {
+                depth: Some(5),
+                ..SearchLimits::default()
+            };
+            let mut hit_rate = 0;
+            move_eval::find_best_move(game_state, &limits, &SearchSignals::default(), &mut |r| hit_rate = r.pawn_hash_hit_rate);
+            hit_rate
+        };
+        let first = search(&mut game_state);
+        assert!(search(&mut game_state) > first);
+        game_state.clear_pawn_tables();
+        assert_eq!(search(&mut game_state), first);
     }
 }
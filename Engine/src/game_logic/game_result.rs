@@ -0,0 +1,204 @@
+use std::fmt;
+
+use crate::core::piece::Color;
+
+/// Why a game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+    /// The same position arose five times; the game is drawn without a claim
+    FivefoldRepetition,
+    /// 75 moves by each side without a pawn move or capture; the game is drawn without a claim
+    SeventyFiveMoveRule,
+    /// The same position arose three times
+    ThreefoldRepetition,
+    /// 50 moves by each side without a pawn move or capture
+    FiftyMoveRule,
+    Timeout,
+    Resignation,
+}
+
+impl Termination {
+    /// Whether the rules end the game by themselves; threefold repetition and the fifty-move rule only give a right to claim a draw
+    pub fn is_automatic(self) -> bool {
+        !matches!(self, Termination::ThreefoldRepetition | Termination::FiftyMoveRule)
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::FivefoldRepetition => "fivefold repetition",
+            Termination::SeventyFiveMoveRule => "seventy-five move rule",
+            Termination::ThreefoldRepetition => "threefold repetition",
+            Termination::FiftyMoveRule => "fifty move rule",
+            Termination::Timeout => "timeout",
+            Termination::Resignation => "resignation",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Outcome of a finished game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GameResult {
+    Win { winner: Color, termination: Termination },
+    Draw(Termination),
+}
+
+impl GameResult {
+    #[inline]
+    pub fn get_termination(self) -> Termination {
+        match self {
+            GameResult::Win { termination, .. } | GameResult::Draw(termination) => termination,
+        }
+    }
+
+    /// Returns the winner, or None for a draw
+    #[inline]
+    pub fn get_winner(self) -> Option<Color> {
+        match self {
+            GameResult::Win { winner, .. } => Some(winner),
+            GameResult::Draw(_) => None,
+        }
+    }
+
+    /// Returns the result as written in PGN: `1-0`, `0-1` or `1/2-1/2`
+    pub fn to_pgn(self) -> &'static str {
+        match self.get_winner() {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    /// Formats the result with a short explanation, e.g. `1-0 {White mates}`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {{", self.to_pgn())?;
+        match *self {
+            GameResult::Win { winner, termination } => match termination {
+                Termination::Checkmate => write!(f, "{:?} mates", winner)?,
+                Termination::Resignation => write!(f, "{:?} resigns", winner.opposite())?,
+                Termination::Timeout => write!(f, "{:?} wins on time", winner)?,
+                termination => write!(f, "{:?} wins by {}", winner, termination)?,
+            },
+            GameResult::Draw(Termination::Stalemate) => write!(f, "Stalemate")?,
+            GameResult::Draw(Termination::Timeout) => write!(f, "Draw by timeout with insufficient mating material")?,
+            GameResult::Draw(termination) => write!(f, "Draw by {}", termination)?,
+        }
+        write!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{game_logic::game::GameState, move_logic::move_encode::Move};
+
+    fn play(game_state: &mut GameState, moves: &[&str]) {
+        for san in moves {
+            let m = Move::from_san(san, game_state.get_board_state(), game_state.get_pregen_attacks()).unwrap();
+            game_state.make_move(m);
+        }
+    }
+
+    fn result_of(fen: &str) -> Option<GameResult> {
+        GameState::new(Some(fen), Duration::from_secs(60)).game_result()
+    }
+
+    #[test]
+    fn test_mate_and_stalemate() {
+        let mut game_state = GameState::new(None, Duration::from_secs(60));
+        assert_eq!(game_state.game_result(), None);
+        play(&mut game_state, &["f3", "e5", "g4", "Qh4#"]);
+        let result = game_state.game_result().unwrap();
+        assert_eq!(
+            result,
+            GameResult::Win {
+                winner: Color::Black,
+                termination: Termination::Checkmate
+            }
+        );
+        assert_eq!(result.to_string(), "0-1 {Black mates}");
+
+        let result = result_of("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(result, GameResult::Draw(Termination::Stalemate));
+        assert_eq!(result.to_string(), "1/2-1/2 {Stalemate}");
+
+        // Mate on the move that reaches the seventy-five move limit still counts
+        let mut game_state = GameState::new(Some("7k/8/6K1/8/8/8/8/R7 w - - 149 120"), Duration::from_secs(60));
+        play(&mut game_state, &["Ra8#"]);
+        assert_eq!(game_state.game_result().unwrap().get_termination(), Termination::Checkmate);
+    }
+
+    #[test]
+    fn test_draw_rules() {
+        assert_eq!(
+            result_of("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"),
+            Some(GameResult::Draw(Termination::InsufficientMaterial))
+        );
+        assert_eq!(result_of("4k3/8/8/8/8/8/8/R3K3 w - - 99 80"), None);
+        assert_eq!(
+            result_of("4k3/8/8/8/8/8/8/R3K3 w - - 100 80"),
+            Some(GameResult::Draw(Termination::FiftyMoveRule))
+        );
+        assert_eq!(
+            result_of("4k3/8/8/8/8/8/8/R3K3 w - - 150 80"),
+            Some(GameResult::Draw(Termination::SeventyFiveMoveRule))
+        );
+
+        let shuffle = ["Nf3", "Nf6", "Ng1", "Ng8"];
+        let mut game_state = GameState::new(None, Duration::from_secs(60));
+        play(&mut game_state, &shuffle);
+        assert_eq!(game_state.game_result(), None);
+        play(&mut game_state, &shuffle);
+        let result = game_state.game_result().unwrap();
+        assert_eq!(result, GameResult::Draw(Termination::ThreefoldRepetition));
+        assert!(!result.get_termination().is_automatic());
+        play(&mut game_state, &shuffle);
+        play(&mut game_state, &shuffle);
+        assert_eq!(game_state.game_result(), Some(GameResult::Draw(Termination::FivefoldRepetition)));
+    }
+
+    #[test]
+    fn test_forfeits() {
+        let mut game_state = GameState::new(None, Duration::from_secs(60));
+        game_state.resign(Color::White);
+        let result = game_state.game_result().unwrap();
+        assert_eq!(result.to_pgn(), "0-1");
+        assert_eq!(result.to_string(), "0-1 {White resigns}");
+
+        game_state.record_timeout(Color::Black);
+        assert_eq!(
+            game_state.game_result(),
+            Some(GameResult::Win {
+                winner: Color::White,
+                termination: Termination::Timeout
+            })
+        );
+        game_state.set_position(None).unwrap();
+        assert_eq!(game_state.game_result(), None);
+
+        // Running out of time against a lone king is a draw
+        let mut game_state = GameState::new(Some("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1"), Duration::from_secs(60));
+        game_state.record_timeout(Color::White);
+        assert_eq!(game_state.game_result(), Some(GameResult::Draw(Termination::Timeout)));
+        game_state.record_timeout(Color::Black);
+        assert_eq!(game_state.game_result().unwrap().get_winner(), Some(Color::White));
+        // A lone knight still wins on time when the loser's rook could block its own king
+        let mut game_state = GameState::new(Some("4k2r/8/8/8/8/8/8/1N2K3 w - - 0 1"), Duration::from_secs(60));
+        game_state.record_timeout(Color::Black);
+        assert_eq!(game_state.game_result().unwrap().get_winner(), Some(Color::White));
+        game_state.record_timeout(Color::White);
+        assert_eq!(game_state.game_result().unwrap().get_winner(), Some(Color::Black));
+    }
+}
@@ -1,2 +1,7 @@
+pub mod epd_runner;
 pub mod game;
+pub mod game_result;
+pub mod pgn;
+pub mod uci;
 pub mod user_input;
+pub mod xboard;
@@ -0,0 +1,621 @@
+use std::{
+    fmt,
+    io::{BufRead, Lines},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    core::{board_state::BoardState, piece::Color},
+    move_logic::{
+        move_encode::Move,
+        move_eval::{mate_in, SearchReport},
+    },
+};
+
+use super::game::GameState;
+
+/// Tags every exported game starts with, in this order
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+/// Movetext lines are wrapped before this many characters
+const MAX_LINE_LENGTH: usize = 80;
+/// Traditional move suffix annotations and the NAGs they stand for
+const SUFFIX_NAGS: [(&str, u8); 6] = [("!", 1), ("?", 2), ("!!", 3), ("??", 4), ("!?", 5), ("?!", 6)];
+
+/// A move of the main line with its annotations
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PgnMove {
+    /// The move in SAN, without annotation suffixes such as `!?`
+    pub san: String,
+    /// Numeric Annotation Glyphs, e.g. 1 for `!`
+    pub nags: Vec<u8>,
+    /// Comment following the move
+    pub comment: Option<String>,
+}
+
+/// A game in Portable Game Notation: tag pairs, the main line and the result.
+/// Variations are skipped when reading and are never written.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    tags: Vec<(String, String)>,
+    /// Comment before the first move
+    comment: Option<String>,
+    moves: Vec<PgnMove>,
+}
+
+impl PgnGame {
+    /// Creates a game with no tags and no moves
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the moves played in `game_state` from its start position
+    pub fn from_game_state(game_state: &GameState) -> Self {
+        let mut game = Self::new();
+        game.set_tag("Date", &today());
+        if let Some(fen) = game_state.get_start_fen() {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", fen);
+        }
+
+        let moves: Vec<Move> = game_state.get_moves().collect();
+        let mut replay = game_state.clone();
+        while replay.get_history_len() > 0 {
+            replay.unmake_move();
+        }
+        for m in moves {
+            let san = m.to_san(replay.get_board_state(), replay.get_pregen_attacks());
+            game.push_move(&san, None);
+            replay.make_move(m);
+        }
+        game
+    }
+
+    /// Returns the value of a tag, if present
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// Sets a tag, replacing any previous value
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Returns the result (`1-0`, `0-1`, `1/2-1/2` or `*` for an unfinished game)
+    pub fn get_result(&self) -> &str {
+        self.get_tag("Result").unwrap_or("*")
+    }
+
+    #[inline]
+    pub fn set_result(&mut self, result: &str) {
+        self.set_tag("Result", result);
+    }
+
+    #[inline]
+    pub fn get_moves(&self) -> &[PgnMove] {
+        &self.moves
+    }
+
+    /// Returns the comment before the first move, if any
+    #[cfg(test)]
+    #[inline]
+    pub fn get_comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Appends a move given in SAN to the main line
+    pub fn push_move(&mut self, san: &str, comment: Option<String>) {
+        self.moves.push(PgnMove {
+            san: san.to_string(),
+            nags: Vec::new(),
+            comment,
+        });
+    }
+
+    /// Sets the comment of the move at `index`
+    pub fn set_move_comment(&mut self, index: usize, comment: Option<String>) {
+        if let Some(m) = self.moves.get_mut(index) {
+            m.comment = comment;
+        }
+    }
+
+    /// Plays the game in `game_state` from its start position, returning the position before every move and the final one
+    pub fn replay(&self, game_state: &mut GameState) -> Result<Vec<BoardState>, String> {
+        game_state.set_position(self.get_tag("FEN"))?;
+        let (first_number, black_first) = self.first_move();
+        let mut positions = Vec::with_capacity(self.moves.len() + 1);
+        positions.push(game_state.get_board_state().clone());
+
+        for (i, pgn_move) in self.moves.iter().enumerate() {
+            let m = Move::from_san(&pgn_move.san, game_state.get_board_state(), game_state.get_pregen_attacks()).map_err(|e| {
+                let ply = i + black_first as usize;
+                let dots = if ply % 2 == 1 { "..." } else { "" };
+                format!("move {}{} ({}): {}", first_number as usize + ply / 2, dots, pgn_move.san, e)
+            })?;
+            game_state.make_move(m);
+            positions.push(game_state.get_board_state().clone());
+        }
+        Ok(positions)
+    }
+
+    /// Adds a comment to the last move, or before the first move if there is none
+    fn add_comment(&mut self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let comment = match self.moves.last_mut() {
+            Some(m) => &mut m.comment,
+            None => &mut self.comment,
+        };
+        match comment {
+            Some(existing) => {
+                existing.push(' ');
+                existing.push_str(text);
+            }
+            None => *comment = Some(text.to_string()),
+        }
+    }
+
+    /// Returns the number of the first move and whether black makes it, from the FEN tag
+    fn first_move(&self) -> (u32, bool) {
+        let Some(fen) = self.get_tag("FEN") else {
+            return (1, false);
+        };
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let black = fields.get(1) == Some(&"b");
+        let number = fields.get(5).and_then(|n| n.parse().ok()).unwrap_or(1);
+        (number, black)
+    }
+}
+
+impl fmt::Display for PgnGame {
+    /// Writes the game in PGN export format
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => self.get_result(),
+                "Date" => self.get_tag(name).unwrap_or("????.??.??"),
+                _ => self.get_tag(name).unwrap_or("?"),
+            };
+            writeln!(f, "[{} \"{}\"]", name, escape_tag_value(value))?;
+        }
+        for (name, value) in self.tags.iter().filter(|(n, _)| !SEVEN_TAG_ROSTER.contains(&n.as_str())) {
+            writeln!(f, "[{} \"{}\"]", name, escape_tag_value(value))?;
+        }
+        writeln!(f)?;
+
+        let mut tokens = Vec::with_capacity(self.moves.len() * 2 + 2);
+        if let Some(comment) = &self.comment {
+            tokens.push(format_comment(comment));
+        }
+        let (mut number, mut black) = self.first_move();
+        // Black's move needs its own number at the start of the game and after a comment
+        let mut number_black = true;
+        for m in &self.moves {
+            if !black {
+                tokens.push(format!("{}.", number));
+            } else if number_black {
+                tokens.push(format!("{}...", number));
+            }
+            tokens.push(m.san.clone());
+            tokens.extend(m.nags.iter().map(|nag| format!("${}", nag)));
+            number_black = m.comment.is_some();
+            if let Some(comment) = &m.comment {
+                tokens.push(format_comment(comment));
+            }
+            if black {
+                number += 1;
+            }
+            black = !black;
+        }
+        tokens.push(self.get_result().to_string());
+
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() >= MAX_LINE_LENGTH {
+                writeln!(f)?;
+                line_length = 0;
+            } else if line_length > 0 {
+                write!(f, " ")?;
+                line_length += 1;
+            }
+            write!(f, "{}", token)?;
+            line_length += token.len();
+        }
+        writeln!(f)
+    }
+}
+
+/// Reads games one at a time from PGN text, so files with many games never have to be loaded whole
+pub struct PgnReader<R: BufRead> {
+    lines: Lines<R>,
+    /// Tag line of the next game, read while looking for the end of the previous one
+    pending: Option<String>,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            pending: None,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut game = PgnGame::new();
+        let mut movetext = MovetextParser::default();
+        let mut started = false;
+
+        loop {
+            let line = match self.pending.take().map(Ok).or_else(|| self.lines.next()) {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => return Some(Err(e.to_string())),
+                None => break,
+            };
+
+            if !movetext.in_comment {
+                let trimmed = line.trim();
+                // Lines starting with `%` are escaped from PGN processing
+                if trimmed.is_empty() || line.starts_with('%') {
+                    continue;
+                }
+                if trimmed.starts_with('[') {
+                    if movetext.has_moves {
+                        // The previous game ended without a result
+                        self.pending = Some(line);
+                        break;
+                    }
+                    let (name, value) = match parse_tag(trimmed) {
+                        Ok(tag) => tag,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    game.set_tag(&name, &value);
+                    started = true;
+                    continue;
+                }
+            }
+
+            started = true;
+            if movetext.feed(&line, &mut game) {
+                break;
+            }
+        }
+        started.then_some(Ok(game))
+    }
+}
+
+/// Parses movetext line by line, keeping the state of comments and variations that span lines
+#[derive(Default)]
+struct MovetextParser {
+    in_comment: bool,
+    comment: String,
+    /// Nesting depth of the variation being skipped, 0 on the main line
+    variation_depth: u32,
+    has_moves: bool,
+}
+
+impl MovetextParser {
+    /// Parses one line of movetext into `game`, returning true once the game termination marker is read
+    fn feed(&mut self, line: &str, game: &mut PgnGame) -> bool {
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            if self.in_comment {
+                match rest.find('}') {
+                    Some(end) => {
+                        self.comment.push_str(&rest[..end]);
+                        if self.variation_depth == 0 {
+                            game.add_comment(&self.comment);
+                        }
+                        self.comment.clear();
+                        self.in_comment = false;
+                        rest = &rest[end + 1..];
+                    }
+                    None => {
+                        self.comment.push_str(rest);
+                        self.comment.push(' ');
+                        return false;
+                    }
+                }
+                continue;
+            }
+
+            match c {
+                '{' => {
+                    self.in_comment = true;
+                    rest = &rest[1..];
+                }
+                ';' => {
+                    if self.variation_depth == 0 {
+                        game.add_comment(&rest[1..]);
+                    }
+                    return false;
+                }
+                '(' => {
+                    self.variation_depth += 1;
+                    rest = &rest[1..];
+                }
+                ')' => {
+                    self.variation_depth = self.variation_depth.saturating_sub(1);
+                    rest = &rest[1..];
+                }
+                c if c.is_whitespace() => rest = &rest[c.len_utf8()..],
+                _ => {
+                    let end = rest.find(|c: char| c.is_whitespace() || "{};()".contains(c)).unwrap_or(rest.len());
+                    let token = &rest[..end];
+                    rest = &rest[end..];
+                    if self.variation_depth == 0 && self.read_token(token, game) {
+                        return true;
+                    }
+                }
+            }
+        }
+        if self.in_comment {
+            self.comment.push(' ');
+        }
+        false
+    }
+
+    /// Handles a main line token, returning true for a game termination marker
+    fn read_token(&mut self, token: &str, game: &mut PgnGame) -> bool {
+        if matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+            game.set_result(token);
+            return true;
+        }
+        if let Some(nag) = token.strip_prefix('$') {
+            if let (Some(m), Ok(nag)) = (game.moves.last_mut(), nag.parse()) {
+                m.nags.push(nag);
+            }
+            return false;
+        }
+
+        // Move numbers may be glued to the move, as in `1.e4` or `12...Nf6`
+        let san = token.trim_start_matches(|c: char| c.is_ascii_digit());
+        let san = if san.starts_with('.') { san.trim_start_matches('.') } else { token };
+        if san.is_empty() {
+            return false;
+        }
+
+        let annotation_start = san.find(['!', '?']).unwrap_or(san.len());
+        let (san, annotation) = san.split_at(annotation_start);
+        game.push_move(san, None);
+        if let Some((_, nag)) = SUFFIX_NAGS.iter().find(|(suffix, _)| *suffix == annotation) {
+            game.moves.last_mut().unwrap().nags.push(*nag);
+        }
+        self.has_moves = true;
+        false
+    }
+}
+
+/// Parses a tag pair line such as `[White "Carlsen, Magnus"]`
+fn parse_tag(line: &str) -> Result<(String, String), String> {
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|l| l.trim_end().strip_suffix(']'))
+        .ok_or_else(|| format!("Invalid tag pair: {}", line))?;
+    let (name, value) = inner
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| format!("Invalid tag pair: {}", line))?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| format!("Invalid tag value: {}", line))?;
+    Ok((name.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+fn escape_tag_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Wraps a comment in braces; PGN has no escape for `}` inside a comment, so it is dropped
+fn format_comment(comment: &str) -> String {
+    format!("{{{}}}", comment.replace('}', ""))
+}
+
+/// Builds the comment recorded after an engine move: the evaluation from white's point of view
+/// with the search depth, and the engine's remaining clock time
+pub fn engine_comment(report: &SearchReport, side: Color, clock: Option<Duration>) -> String {
+    let eval = match mate_in(report.score) {
+        Some(moves) => format!("#{}", moves * side.get_factor()),
+        None => format!("{:.2}", (report.score * side.get_factor()) as f64 / 100.0),
+    };
+    let mut comment = format!("[%eval {},{}]", eval, report.depth);
+    if let Some(clock) = clock {
+        let secs = clock.as_secs();
+        comment.push_str(&format!(" [%clk {}:{:02}:{:02}]", secs / 3600, secs / 60 % 60, secs % 60));
+    }
+    comment
+}
+
+/// Today's date in the PGN `YYYY.MM.DD` format
+fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() / 86400);
+    // Converts days since 1970-01-01 to a civil date
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as u64;
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAMES: &str = r#"[Event "Test match"]
+[Site "?"]
+[White "Alice \"The Rook\""]
+[Black "Bob"]
+[Result "1-0"]
+
+{Opening comment} 1. e4 e5 2.Nf3 $1 Nc6 (2... d6 {Philidor} 3. d4 (3. Bc4)) 3. Bb5!? a6
+; rest of line comment
+4. Ba4 {A comment that
+spans two lines} 4... Nf6 5. O-O Be7?! 1-0
+
+% escaped line
+[Event "Unfinished"]
+
+1. d4 d5
+[Event "No movetext"]
+[Result "*"]
+
+*
+"#;
+
+    fn read_all(text: &str) -> Vec<PgnGame> {
+        PgnReader::new(text.as_bytes()).collect::<Result<_, _>>().unwrap()
+    }
+
+    fn sans(game: &PgnGame) -> Vec<&str> {
+        game.get_moves().iter().map(|m| m.san.as_str()).collect()
+    }
+
+    #[test]
+    fn test_read_games() {
+        let games = read_all(GAMES);
+        assert_eq!(games.len(), 3);
+
+        let game = &games[0];
+        assert_eq!(game.get_tag("Event"), Some("Test match"));
+        assert_eq!(game.get_tag("White"), Some("Alice \"The Rook\""));
+        assert_eq!(game.get_result(), "1-0");
+        assert_eq!(game.get_comment(), Some("Opening comment"));
+        assert_eq!(sans(game), ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6", "O-O", "Be7"]);
+        assert_eq!(game.get_moves()[2].nags, [1]);
+        assert_eq!(game.get_moves()[4].nags, [5]);
+        assert_eq!(game.get_moves()[9].nags, [6]);
+        assert_eq!(game.get_moves()[5].comment.as_deref(), Some("rest of line comment"));
+        assert_eq!(game.get_moves()[6].comment.as_deref(), Some("A comment that spans two lines"));
+        assert_eq!(game.get_moves()[3].comment, None);
+
+        assert_eq!(games[1].get_tag("Event"), Some("Unfinished"));
+        assert_eq!(sans(&games[1]), ["d4", "d5"]);
+        assert_eq!(games[1].get_result(), "*");
+
+        assert_eq!(games[2].get_tag("Event"), Some("No movetext"));
+        assert!(games[2].get_moves().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_tag() {
+        let mut reader = PgnReader::new("[Event Test]\n\n1. e4 *\n".as_bytes());
+        assert!(reader.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_replay() {
+        let games = read_all(GAMES);
+        let mut game_state = GameState::new(None, Duration::ZERO);
+        let positions = games[0].replay(&mut game_state).unwrap();
+        assert_eq!(positions.len(), 11);
+        assert_eq!(positions[10].get_side(), Color::White);
+        assert_eq!(game_state.get_history_len(), 10);
+
+        let mut game = PgnGame::new();
+        game.push_move("e4", None);
+        game.push_move("e5", None);
+        game.push_move("Ke3", None);
+        assert_eq!(game.replay(&mut game_state), Err("move 2 (Ke3): Illegal move".to_string()));
+
+        // Move numbers count from the FEN's
+        let mut game = PgnGame::new();
+        game.set_tag("FEN", "4k3/8/8/8/8/8/8/R3K3 b - - 0 40");
+        game.push_move("Kd8", None);
+        game.push_move("Ra8", None);
+        game.push_move("Kc8", None);
+        assert_eq!(game.replay(&mut game_state), Err("move 41... (Kc8): Illegal move".to_string()));
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let mut game_state = GameState::new(Some("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3"), Duration::ZERO);
+        for san in ["Bb5", "a6", "Bxc6", "dxc6", "O-O", "f6"] {
+            let m = Move::from_san(san, game_state.get_board_state(), game_state.get_pregen_attacks()).unwrap();
+            game_state.make_move(m);
+        }
+
+        let mut game = PgnGame::from_game_state(&game_state);
+        game.set_tag("White", "RustyChessEngine");
+        game.set_move_comment(2, Some("[%eval 0.30,12] [%clk 0:04:59]".to_string()));
+        game.set_result("1/2-1/2");
+        let text = game.to_string();
+
+        assert!(text.starts_with("[Event \"?\"]\n[Site \"?\"]\n[Date \""));
+        assert!(text.contains("[White \"RustyChessEngine\"]\n[Black \"?\"]\n[Result \"1/2-1/2\"]\n[SetUp \"1\"]\n[FEN \"r1bqkbnr/"));
+        assert!(text.contains("\n\n3. Bb5 a6 4. Bxc6 {[%eval 0.30,12] [%clk 0:04:59]} 4... dxc6 5. O-O f6 1/2-1/2\n"));
+
+        let read_back = read_all(&text);
+        assert_eq!(read_back.len(), 1);
+        assert_eq!(read_back[0].get_moves(), game.get_moves());
+        assert_eq!(read_back[0].get_tag("FEN"), game.get_tag("FEN"));
+        assert_eq!(read_back[0].get_result(), "1/2-1/2");
+        assert_eq!(read_back[0].replay(&mut game_state).unwrap().len(), 7);
+    }
+
+    #[test]
+    fn test_line_wrapping_and_black_first() {
+        let mut game = PgnGame::new();
+        game.set_tag("FEN", "4k3/8/8/8/8/8/8/R3K3 b - - 0 40");
+        for _ in 0..20 {
+            game.push_move("Kd8", None);
+            game.push_move("Kd1", None);
+        }
+        let text = game.to_string();
+        assert!(text.contains("\n40... Kd8 41. Kd1 Kd8 42. Kd1"));
+        assert!(text.lines().all(|line| line.len() < MAX_LINE_LENGTH));
+        assert!(text.trim_end().ends_with('*'));
+    }
+
+    #[test]
+    fn test_comment_with_closing_brace() {
+        let mut game = PgnGame::new();
+        game.push_move("e4", Some("a {nested} comment".to_string()));
+        game.push_move("e5", None);
+        let text = game.to_string();
+        assert!(text.contains("1. e4 {a {nested comment} 1... e5 *"));
+
+        let read_back = read_all(&text);
+        assert_eq!(read_back[0].get_moves().len(), 2);
+        assert_eq!(read_back[0].get_moves()[0].comment.as_deref(), Some("a {nested comment"));
+    }
+
+    #[test]
+    fn test_engine_comment() {
+        let report = SearchReport {
+            depth: 12,
+            selective_depth: 20,
+            score: 35,
+            nodes: 0,
+            time: Duration::ZERO,
+            pv: Vec::new(),
+            pawn_hash_hit_rate: 0,
+            hashfull: 0,
+        };
+        assert_eq!(
+            engine_comment(&report, Color::White, Some(Duration::from_secs(299))),
+            "[%eval 0.35,12] [%clk 0:04:59]"
+        );
+        assert_eq!(engine_comment(&report, Color::Black, None), "[%eval -0.35,12]");
+    }
+
+    #[test]
+    fn test_today() {
+        let date = today();
+        assert_eq!(date.len(), 10);
+        assert!(date.as_str() >= "2024.01.01");
+    }
+}
@@ -0,0 +1,564 @@
+use std::io::{self, BufRead};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::core::eval_params::EvalParams;
+use crate::move_logic::{
+    move_encode::Move,
+    move_eval::{self, SearchLimits, SearchReport, SearchSignals, MAX_CONTEMPT},
+    time_management::{DEFAULT_MOVE_OVERHEAD_MS, MAX_MOVE_OVERHEAD_MS},
+    transposition_table::{DEFAULT_TT_SIZE_MB, MAX_TT_SIZE_MB},
+};
+
+use super::{
+    game::{GameState, ENGINE_NAME, MAX_THREADS},
+    user_input,
+};
+
+const ENGINE_AUTHOR: &str = "bwt86";
+
+/// Universal Chess Interface front-end driving a `GameState`
+pub struct Uci {
+    game_state: GameState,
+    /// Search started by `go`, running until it finishes or is stopped
+    search: Option<BackgroundSearch>,
+}
+
+/// A search running on its own thread, which prints `bestmove` when it ends
+struct BackgroundSearch {
+    signals: SearchSignals,
+    handle: JoinHandle<()>,
+}
+
+impl Uci {
+    pub fn new() -> Self {
+        Self {
+            game_state: GameState::new(None, Duration::ZERO),
+            search: None,
+        }
+    }
+
+    /// Reads commands from stdin until `quit` or end of input
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else { break };
+            if !self.handle_command(&line) {
+                break;
+            }
+        }
+        self.stop_search();
+    }
+
+    /// Handles a single command line. Returns false when the engine should quit.
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        match command {
+            "uci" => {
+                println!("id name {}", ENGINE_NAME);
+                println!("id author {}", ENGINE_AUTHOR);
+                println!("option name Hash type spin default {} min 1 max {}", DEFAULT_TT_SIZE_MB, MAX_TT_SIZE_MB);
+                println!("option name Clear Hash type button");
+                println!("option name Threads type spin default 1 min 1 max {}", MAX_THREADS);
+                println!("option name Ponder type check default false");
+                println!(
+                    "option name Move Overhead type spin default {} min 0 max {}",
+                    DEFAULT_MOVE_OVERHEAD_MS, MAX_MOVE_OVERHEAD_MS
+                );
+                println!("option name Contempt type spin default 0 min {} max {}", -MAX_CONTEMPT, MAX_CONTEMPT);
+                println!("option name EvalFile type string default <empty>");
+                println!("option name Save EvalFile type string default <empty>");
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "ucinewgame" => {
+                self.stop_search();
+                self.game_state.clear_transposition_table();
+                self.game_state.clear_pawn_tables();
+                if let Err(e) = self.game_state.set_position(None) {
+                    println!("info string {}", e);
+                }
+            }
+            "position" => {
+                self.stop_search();
+                if let Err(e) = self.handle_position(args) {
+                    println!("info string {}", e);
+                }
+            }
+            "go" => self.handle_go(args),
+            "setoption" => {
+                self.stop_search();
+                self.handle_setoption(args);
+            }
+            "stop" => self.stop_search(),
+            "ponderhit" => {
+                if let Some(search) = &self.search {
+                    search.signals.ponderhit();
+                }
+            }
+            // Not part of UCI: `eval [json]` prints the evaluation of the current position term by term
+            "eval" => {
+                let trace = self.game_state.get_board_state().trace_eval(self.game_state.get_pregen_attacks());
+                match args.first() {
+                    Some(&"json") => println!("{}", trace.to_json()),
+                    _ => println!("{}", trace),
+                }
+            }
+            "debug" | "register" => {}
+            "quit" => {
+                self.stop_search();
+                return false;
+            }
+            _ => println!("info string unknown command: {}", command),
+        }
+        true
+    }
+
+    /// `position [startpos | fen <fen>] [moves <m1> ... <mi>]`
+    fn handle_position(&mut self, args: &[&str]) -> Result<(), String> {
+        let moves_index = args.iter().position(|&t| t == "moves");
+        let (setup, moves) = match moves_index {
+            Some(i) => (&args[..i], &args[i + 1..]),
+            None => (args, &args[args.len()..]),
+        };
+
+        match setup.split_first() {
+            Some((&"startpos", _)) => self.game_state.set_position(None)?,
+            Some((&"fen", fen)) => self.game_state.set_position(Some(&fen.join(" ")))?,
+            _ => return Err("position: expected 'startpos' or 'fen'".into()),
+        }
+
+        for move_str in moves {
+            let m = parse_move(&self.game_state, move_str)?;
+            self.game_state.make_move(m);
+        }
+        Ok(())
+    }
+
+    /// `go [ponder] [wtime <x>] [btime <x>] [winc <x>] [binc <x>] [movestogo <x>] [depth <x>] [nodes <x>] [movetime <x>] [mate <x>] [infinite]`
+    ///
+    /// Starts the search on a background thread so `stop` and `ponderhit` can be handled while it runs.
+    fn handle_go(&mut self, args: &[&str]) {
+        self.stop_search();
+        let limits = parse_go(args);
+        let signals = SearchSignals::new(limits.ponder);
+        let search_signals = signals.clone();
+        // The copy shares the transposition and pawn hash tables with this game state
+        let mut game_state = self.game_state.clone();
+
+        let handle = std::thread::spawn(move || {
+            // The last report is the result of the thread whose move is played, not necessarily the main thread's
+            let mut result: Option<SearchReport> = None;
+            let best_move = move_eval::find_best_move(&mut game_state, &limits, &search_signals, &mut |report| {
+                print_info(report);
+                result = Some(report.clone());
+            });
+            let mate = result.as_ref().and_then(SearchReport::mate_in);
+            if let Some(moves) = limits.mate.filter(|&moves| !mate.is_some_and(|m| (1..=moves as i32).contains(&m))) {
+                println!("info string no mate in {} found", moves);
+            }
+            if let Some(rate) = result.as_ref().map(|r| r.pawn_hash_hit_rate) {
+                println!("info string pawn hash hit rate {}.{}%", rate / 10, rate % 10);
+            }
+            println!("{}", format_bestmove(best_move, result.as_ref().map_or(&[], |r| &r.pv)));
+        });
+        self.search = Some(BackgroundSearch { signals, handle });
+    }
+
+    /// Stops the running search, if any, and waits until it has printed its best move
+    fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.signals.stop();
+            search.handle.join().expect("search thread panicked");
+        }
+    }
+
+    /// `setoption name <id> [value <x>]`
+    fn handle_setoption(&mut self, args: &[&str]) {
+        let (name, value) = parse_setoption(args);
+        if let Err(e) = self.set_option(&name, value.as_deref()) {
+            println!("info string {}", e);
+        }
+    }
+
+    /// Applies an engine option; option names are case-insensitive
+    fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<(), String> {
+        match name.to_lowercase().as_str() {
+            "hash" => {
+                let size_mb = value
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .filter(|mb| (1..=MAX_TT_SIZE_MB).contains(mb))
+                    .ok_or_else(|| format!("invalid Hash value: {}", value.unwrap_or("")))?;
+                self.game_state.resize_transposition_table(size_mb);
+            }
+            "clear hash" => self.game_state.clear_transposition_table(),
+            // Pondering is requested per search with `go ponder`, so the option needs no state
+            "ponder" => match value.map(str::to_lowercase).as_deref() {
+                Some("true" | "false") => {}
+                _ => return Err(format!("invalid Ponder value: {}", value.unwrap_or(""))),
+            },
+            "threads" => {
+                let threads = value
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .filter(|n| (1..=MAX_THREADS).contains(n))
+                    .ok_or_else(|| format!("invalid Threads value: {}", value.unwrap_or("")))?;
+                self.game_state.set_threads(threads);
+            }
+            "move overhead" => {
+                let overhead_ms = value
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .filter(|ms| *ms <= MAX_MOVE_OVERHEAD_MS)
+                    .ok_or_else(|| format!("invalid Move Overhead value: {}", value.unwrap_or("")))?;
+                self.game_state.set_move_overhead(Duration::from_millis(overhead_ms));
+            }
+            "contempt" => {
+                let contempt = value
+                    .and_then(|v| v.parse::<i32>().ok())
+                    .filter(|cp| (-MAX_CONTEMPT..=MAX_CONTEMPT).contains(cp))
+                    .ok_or_else(|| format!("invalid Contempt value: {}", value.unwrap_or("")))?;
+                self.game_state.set_contempt(contempt);
+            }
+            // An empty value goes back to the parameters the engine started with
+            "evalfile" => match value.unwrap_or("") {
+                "" | "<empty>" => self.game_state.set_eval_params((*EvalParams::get_default()).clone()),
+                path => self.game_state.set_eval_params(EvalParams::load(path)?),
+            },
+            "save evalfile" => match value.unwrap_or("") {
+                "" | "<empty>" => return Err("Save EvalFile needs a file name".to_string()),
+                path => self.game_state.get_eval_params().save(path)?,
+            },
+            _ => return Err(format!("unknown option: {}", name)),
+        }
+        Ok(())
+    }
+}
+
+/// Parses the arguments of a `go` command into search limits
+pub fn parse_go(args: &[&str]) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    let mut iter = args.iter();
+
+    while let Some(&token) = iter.next() {
+        let mut next_number = || iter.next().and_then(|v| v.parse::<i64>().ok());
+        match token {
+            "wtime" => limits.wtime = next_number().map(millis),
+            "btime" => limits.btime = next_number().map(millis),
+            "winc" => limits.winc = next_number().map(millis),
+            "binc" => limits.binc = next_number().map(millis),
+            "movestogo" => limits.moves_to_go = next_number().map(|n| n.max(1) as u32),
+            "depth" => limits.depth = next_number().map(|n| n.clamp(1, move_eval::MAX_PLY as i64) as u8),
+            "nodes" => limits.nodes = next_number().map(|n| n.max(1) as u64),
+            "movetime" => limits.move_time = next_number().map(millis),
+            "mate" => limits.mate = next_number().map(|n| n.clamp(1, move_eval::MAX_PLY as i64 / 2) as u8),
+            "infinite" => limits.infinite = true,
+            "ponder" => limits.ponder = true,
+            _ => {}
+        }
+    }
+    limits
+}
+
+/// Splits `setoption` arguments into the option name and optional value
+pub fn parse_setoption(args: &[&str]) -> (String, Option<String>) {
+    let name_start = args.iter().position(|&t| t == "name").map_or(0, |i| i + 1);
+    let value_index = args.iter().position(|&t| t == "value");
+    let name_end = value_index.unwrap_or(args.len()).max(name_start);
+
+    let name = args[name_start..name_end].join(" ");
+    let value = value_index.map(|i| args[i + 1..].join(" "));
+    (name, value)
+}
+
+/// Parses a move in coordinate notation and checks that it is legal in the current position
+pub fn parse_move(game_state: &GameState, move_str: &str) -> Result<Move, String> {
+    let m = Move::move_from_algebraic(move_str, game_state.get_board_state()).map_err(|e| format!("{}: {}", move_str, e))?;
+    if !user_input::is_valid(m, game_state) {
+        return Err(format!("illegal move: {}", move_str));
+    }
+    Ok(m)
+}
+
+/// Formats a search report as a UCI `info` line
+pub fn format_info(report: &SearchReport) -> String {
+    let score = match report.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", report.score),
+    };
+    let mut info = format!(
+        "info depth {} seldepth {} score {} nodes {} nps {} hashfull {} time {}",
+        report.depth,
+        report.selective_depth,
+        score,
+        report.nodes,
+        report.nps(),
+        report.hashfull,
+        report.time.as_millis()
+    );
+    if !report.pv.is_empty() {
+        info.push_str(" pv");
+        for m in &report.pv {
+            info.push(' ');
+            info.push_str(&m.to_string());
+        }
+    }
+    info
+}
+
+/// Formats the `bestmove` line, suggesting the second move of the principal variation to ponder on
+pub fn format_bestmove(best_move: Option<Move>, pv: &[Move]) -> String {
+    let Some(best_move) = best_move else {
+        return "bestmove 0000".to_string();
+    };
+    match pv {
+        [first, reply, ..] if *first == best_move => format!("bestmove {} ponder {}", best_move, reply),
+        _ => format!("bestmove {}", best_move),
+    }
+}
+
+fn print_info(report: &SearchReport) {
+    println!("{}", format_info(report));
+}
+
+fn millis(ms: i64) -> Duration {
+    Duration::from_millis(ms.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{score::Score, square::Square};
+
+    #[test]
+    fn test_parse_go() {
+        let limits = parse_go(&["wtime", "60000", "btime", "55000", "winc", "1000", "binc", "500", "movestogo", "20"]);
+        assert_eq!(limits.wtime, Some(Duration::from_secs(60)));
+        assert_eq!(limits.btime, Some(Duration::from_secs(55)));
+        assert_eq!(limits.winc, Some(Duration::from_secs(1)));
+        assert_eq!(limits.binc, Some(Duration::from_millis(500)));
+        assert_eq!(limits.moves_to_go, Some(20));
+        assert!(!limits.infinite);
+
+        let limits = parse_go(&["depth", "6", "nodes", "10000", "movetime", "250", "infinite"]);
+        assert_eq!(limits.depth, Some(6));
+        assert_eq!(limits.nodes, Some(10000));
+        assert_eq!(limits.move_time, Some(Duration::from_millis(250)));
+        assert!(limits.infinite);
+
+        assert_eq!(parse_go(&["mate", "3"]).mate, Some(3));
+        assert_eq!(parse_go(&["mate", "0"]).mate, Some(1));
+    }
+
+    #[test]
+    fn test_parse_setoption() {
+        let (name, value) = parse_setoption(&["name", "Move", "Overhead", "value", "30"]);
+        assert_eq!(name, "Move Overhead");
+        assert_eq!(value.as_deref(), Some("30"));
+
+        let (name, value) = parse_setoption(&["name", "Clear", "Hash"]);
+        assert_eq!(name, "Clear Hash");
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn test_set_option() {
+        let mut uci = Uci::new();
+        assert!(uci.set_option("Hash", Some("8")).is_ok());
+        assert!(uci.set_option("hash", Some("0")).is_err());
+        assert!(uci.set_option("Hash", None).is_err());
+        assert!(uci.set_option("Clear Hash", None).is_ok());
+        assert!(uci.set_option("Threads", Some("4")).is_ok());
+        assert_eq!(uci.game_state.get_threads(), 4);
+        assert!(uci.set_option("threads", Some("0")).is_err());
+        assert!(uci.set_option("Move Overhead", Some("30")).is_ok());
+        assert_eq!(uci.game_state.get_move_overhead(), Duration::from_millis(30));
+        assert!(uci.set_option("move overhead", Some("-1")).is_err());
+        assert!(uci.set_option("Move Overhead", Some("5001")).is_err());
+        assert!(uci.set_option("Contempt", Some("-20")).is_ok());
+        assert_eq!(uci.game_state.get_contempt(), -20);
+        assert!(uci.set_option("contempt", Some("201")).is_err());
+        assert!(uci.set_option("Nonexistent", Some("1")).is_err());
+    }
+
+    #[test]
+    fn test_eval_file_options() {
+        let path = std::env::temp_dir().join(format!("eval_params_{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut uci = Uci::new();
+        uci.handle_command("position startpos moves e2e4 e7e5");
+        let default_eval = uci.game_state.get_board_state().evaluate(uci.game_state.get_pregen_attacks());
+
+        std::fs::write(path, "pawn_value 200 200\n").unwrap();
+        assert!(uci.set_option("EvalFile", Some(path)).is_ok());
+        assert_eq!(uci.game_state.get_eval_params().piece_values[0], Score::new(200, 200));
+        assert_eq!(uci.game_state.get_moves().count(), 2);
+        assert!(uci.set_option("Save EvalFile", Some(path)).is_ok());
+        assert_eq!(EvalParams::load(path).as_ref(), Ok(uci.game_state.get_eval_params()));
+
+        assert!(uci.set_option("EvalFile", Some("<empty>")).is_ok());
+        assert_eq!(uci.game_state.get_eval_params(), &EvalParams::default());
+        assert_eq!(
+            uci.game_state.get_board_state().evaluate(uci.game_state.get_pregen_attacks()),
+            default_eval
+        );
+        std::fs::remove_file(path).unwrap();
+
+        assert!(uci.set_option("EvalFile", Some(path)).is_err());
+        assert!(uci.set_option("Save EvalFile", None).is_err());
+    }
+
+    #[test]
+    fn test_position_moves() {
+        let mut uci = Uci::new();
+        uci.handle_command("position startpos moves e2e4 e7e5 g1f3");
+        let board = uci.game_state.get_board_state();
+        assert!(board.get_piece_on_square(Square::E4).is_some());
+        assert!(board.get_piece_on_square(Square::E5).is_some());
+        assert!(board.get_piece_on_square(Square::F3).is_some());
+
+        uci.handle_command("position fen 4k3/P7/8/8/8/8/8/4K3 w - - 0 1 moves a7a8q");
+        let board = uci.game_state.get_board_state();
+        assert!(board.get_piece_on_square(Square::A8).is_some_and(|p| p.is_queen()));
+    }
+
+    #[test]
+    fn test_position_rejects_illegal_move() {
+        let game_state = GameState::new(None, Duration::ZERO);
+        assert!(parse_move(&game_state, "e2e5").is_err());
+        assert!(parse_move(&game_state, "e2e4").is_ok());
+    }
+
+    #[test]
+    fn test_go_depth_finds_mate() {
+        let mut game_state = GameState::new(Some("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"), Duration::ZERO);
+        let limits = parse_go(&["depth", "3"]);
+        let mut last_report = None;
+        let best_move = move_eval::find_best_move(&mut game_state, &limits, &SearchSignals::default(), &mut |r| {
+            last_report = Some(r.clone())
+        });
+        assert_eq!(best_move.map(|m| m.to_string()), Some("a1a8".to_string()));
+        assert_eq!(last_report.and_then(|r| r.mate_in()), Some(1));
+    }
+
+    #[test]
+    fn test_go_mate() {
+        // Rd8+ Rxd8 Rxd8#
+        let fen = "r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1";
+        let search = |moves: &str| {
+            let mut game_state = GameState::new(Some(fen), Duration::ZERO);
+            let limits = parse_go(&["mate", moves]);
+            let mut reports = Vec::new();
+            let best_move = move_eval::find_best_move(&mut game_state, &limits, &SearchSignals::default(), &mut |r| reports.push(r.clone()));
+            (best_move.map(|m| m.to_string()), reports)
+        };
+
+        let (best_move, reports) = search("2");
+        assert_eq!(best_move, Some("d2d8".to_string()));
+        assert_eq!(reports.last().and_then(|r| r.mate_in()), Some(2));
+
+        // There is no mate in one, which a one ply search refutes; checks in the quiescence search may still see the mate in two
+        let (_, reports) = search("1");
+        assert_eq!(reports.len(), 1);
+        assert_ne!(reports[0].mate_in(), Some(1));
+    }
+
+    #[test]
+    fn test_go_mate_with_threads() {
+        // A helper thread may prove the mate and stop the search before the main thread completes an iteration
+        for _ in 0..10 {
+            let mut game_state = GameState::new(Some("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"), Duration::ZERO);
+            game_state.set_threads(4);
+            let mut last_report = None;
+            let best_move = move_eval::find_best_move(&mut game_state, &parse_go(&["mate", "1"]), &SearchSignals::default(), &mut |r| {
+                last_report = Some(r.clone())
+            });
+            assert_eq!(best_move.map(|m| m.to_string()), Some("a1a8".to_string()));
+            assert_eq!(last_report.and_then(|r| r.mate_in()), Some(1));
+        }
+    }
+
+    #[test]
+    fn test_go_finds_perpetual_check() {
+        // White is a queen and rook down but draws by checking on h5 and e8
+        let fen = "8/6pk/8/8/1r6/q7/4QPPP/7K w - - 0 1";
+        let mut uci = Uci::new();
+        for contempt in [0, 30] {
+            uci.handle_command("ucinewgame");
+            uci.handle_command(&format!("setoption name Contempt value {}", contempt));
+            uci.handle_command(&format!("position fen {}", fen));
+            let limits = parse_go(&["depth", "7"]);
+            let mut last_report = None;
+            let best_move = move_eval::find_best_move(&mut uci.game_state, &limits, &SearchSignals::default(), &mut |r| {
+                last_report = Some(r.clone())
+            });
+            assert_eq!(best_move.map(|m| m.to_string()), Some("e2h5".to_string()));
+            // The engine plays White, so contempt makes the draw worth less to it
+            assert_eq!(last_report.map(|r| r.score), Some(-contempt));
+        }
+    }
+
+    #[test]
+    fn test_go_with_threads() {
+        let mut uci = Uci::new();
+        uci.handle_command("setoption name Threads value 4");
+        uci.handle_command("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let limits = parse_go(&["depth", "4"]);
+        let best_move = move_eval::find_best_move(&mut uci.game_state, &limits, &SearchSignals::default(), &mut |_| {});
+        assert_eq!(best_move.map(|m| m.to_string()), Some("a1a8".to_string()));
+        // Helper threads search copies, so the position is unchanged
+        assert_eq!(uci.game_state.get_history_len(), 0);
+    }
+
+    fn wait_for_search(uci: &Uci) {
+        let search = uci.search.as_ref().expect("no search running");
+        let start = std::time::Instant::now();
+        while !search.handle.is_finished() {
+            assert!(start.elapsed() < Duration::from_secs(10), "search did not finish");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_go_infinite_until_stop() {
+        let mut uci = Uci::new();
+        uci.handle_command("position startpos");
+        // Even after reaching its depth limit, an infinite search waits for `stop`
+        uci.handle_command("go infinite depth 1");
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!uci.search.as_ref().unwrap().handle.is_finished());
+
+        uci.handle_command("stop");
+        assert!(uci.search.is_none());
+    }
+
+    #[test]
+    fn test_ponderhit_starts_clock() {
+        let mut uci = Uci::new();
+        uci.handle_command("position startpos moves e2e4");
+        uci.handle_command("go ponder movetime 50");
+        // Time limits do not apply while pondering
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!uci.search.as_ref().unwrap().handle.is_finished());
+
+        uci.handle_command("ponderhit");
+        wait_for_search(&uci);
+        uci.handle_command("stop");
+        assert!(uci.search.is_none());
+    }
+
+    #[test]
+    fn test_format_bestmove() {
+        let game_state = GameState::new(None, Duration::ZERO);
+        let e2e4 = parse_move(&game_state, "e2e4").unwrap();
+        let d2d4 = parse_move(&game_state, "d2d4").unwrap();
+        let e7e5 = Move::new(Square::E7, Square::E5, crate::core::piece::Piece::BPawn, None, None, true, false, false);
+
+        assert_eq!(format_bestmove(Some(e2e4), &[e2e4, e7e5]), "bestmove e2e4 ponder e7e5");
+        assert_eq!(format_bestmove(Some(d2d4), &[e2e4, e7e5]), "bestmove d2d4");
+        assert_eq!(format_bestmove(Some(e2e4), &[e2e4]), "bestmove e2e4");
+        assert_eq!(format_bestmove(None, &[]), "bestmove 0000");
+    }
+}
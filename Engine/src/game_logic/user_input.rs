@@ -1,31 +1,37 @@
 use std::io::{self, Write};
 
-use crate::move_logic::{move_encode::Move, pseudo_move_gen};
+use crate::move_logic::{legal_move_gen, move_encode::Move};
 
 use super::game::GameState;
 
-pub fn get_user_move(game_state: &mut GameState) -> Move {
+/// What the player entered at the move prompt
+pub enum PlayerAction {
+    Move(Move),
+    Resign,
+    /// Claims a draw by threefold repetition or the fifty-move rule
+    ClaimDraw,
+}
+
+/// Reads the player's move, resignation or draw claim
+pub fn get_user_move(game_state: &mut GameState) -> PlayerAction {
     let mut input = String::new();
     print!("Enter move: ");
 
     io::stdout().flush().unwrap();
     io::stdin().read_line(&mut input).expect("Failed to read line");
 
-    if input.trim() == "quit" {
-        std::process::exit(0);
+    match input.trim() {
+        "quit" => std::process::exit(0),
+        "resign" => return PlayerAction::Resign,
+        "draw" => return PlayerAction::ClaimDraw,
+        _ => {}
     }
 
-    let m = Move::move_from_algebraic(input.trim(), game_state.get_board_state());
+    // Accepts SAN (`Nf3`) as well as coordinate notation (`g1f3`)
+    let m = Move::from_san(input.trim(), game_state.get_board_state(), game_state.get_pregen_attacks());
 
     match m {
-        Ok(m) => {
-            if is_valid(m, game_state) {
-                return m;
-            } else {
-                println!("Invalid move");
-                get_user_move(game_state)
-            }
-        }
+        Ok(m) => PlayerAction::Move(m),
         Err(e) => {
             println!("Error: {:?}", e);
             get_user_move(game_state)
@@ -33,20 +39,8 @@ pub fn get_user_move(game_state: &mut GameState) -> Move {
     }
 }
 
-fn is_valid(c_move: Move, game_state: &mut GameState) -> bool {
-    let mut pseudo_moves: Vec<Move> = Vec::new();
-    pseudo_move_gen::get_pseudo_moves(game_state.get_board_state(), game_state.get_pregen_attacks(), &mut pseudo_moves);
-
-    if !pseudo_moves.contains(&c_move) {
-        return false;
-    }
-
-    game_state.make_move(c_move);
-    if game_state.is_check(game_state.get_board_state().get_opposite_side()) {
-        game_state.unmake_move();
-        return false;
-    }
-    game_state.unmake_move();
-
-    true
+pub fn is_valid(c_move: Move, game_state: &GameState) -> bool {
+    let mut legal_moves: Vec<Move> = Vec::new();
+    legal_move_gen::get_legal_moves(game_state.get_board_state(), game_state.get_pregen_attacks(), &mut legal_moves);
+    legal_moves.contains(&c_move)
 }
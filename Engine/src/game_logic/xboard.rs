@@ -0,0 +1,594 @@
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{
+    core::piece::Color,
+    move_logic::{
+        move_encode::Move,
+        move_eval::{self, SearchLimits, SearchReport, SearchSignals},
+    },
+};
+
+use super::{
+    game::{GameState, ENGINE_NAME},
+    uci,
+};
+
+/// Chess Engine Communication Protocol (xboard/winboard) front-end driving a `GameState`
+pub struct Xboard {
+    game_state: GameState,
+    /// Side the engine plays, or None in force mode
+    engine_side: Option<Color>,
+    /// Moves per time control from `level` (0 means the whole game)
+    moves_per_control: u32,
+    /// Increment per move from `level`
+    increment: Duration,
+    /// Opponent's remaining time from `otim`
+    opponent_time: Duration,
+    /// Fixed time per move from `st`
+    move_time: Option<Duration>,
+    /// Depth limit from `sd`
+    max_depth: Option<u8>,
+    /// Whether thinking output is shown (`post`/`nopost`)
+    post: bool,
+    /// Whether the engine thinks on the opponent's time (`hard`/`easy`)
+    ponder: bool,
+    /// Version from `protover`. GUIs that never send it speak version 1.
+    protocol_version: u32,
+    search: Option<BackgroundSearch>,
+    /// Id of the next search, so the end of a search that was already handled is ignored
+    next_search_id: u64,
+    sender: Sender<Event>,
+    receiver: Receiver<Event>,
+}
+
+/// Input of the main loop: a command line from the GUI or the end of a search
+enum Event {
+    Command(String),
+    SearchDone(u64),
+}
+
+/// A search running on its own thread, which returns its best move and principal variation
+struct BackgroundSearch {
+    id: u64,
+    signals: SearchSignals,
+    handle: JoinHandle<(Option<Move>, Vec<Move>)>,
+    /// The opponent's expected move while pondering, searched as if it had been played
+    ponder_move: Option<Move>,
+}
+
+impl Xboard {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            game_state: GameState::new(None, Duration::from_secs(300)),
+            engine_side: Some(Color::Black),
+            moves_per_control: 0,
+            increment: Duration::ZERO,
+            opponent_time: Duration::ZERO,
+            move_time: None,
+            max_depth: None,
+            post: false,
+            ponder: false,
+            protocol_version: 1,
+            search: None,
+            next_search_id: 0,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Reads commands from stdin until `quit` or end of input.
+    ///
+    /// Unlike UCI, the engine plays its own moves, so stdin is read on another thread and both the commands
+    /// and the ends of searches are handled in order here.
+    pub fn run(&mut self) {
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(Event::Command(line)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(Event::Command("quit".to_string()));
+        });
+
+        while let Ok(event) = self.receiver.recv() {
+            match event {
+                Event::Command(line) => {
+                    if !self.handle_command(&line) {
+                        break;
+                    }
+                }
+                Event::SearchDone(id) => self.finish_search(id),
+            }
+        }
+        self.abort_search();
+    }
+
+    /// Handles a single command line. Returns false when the engine should quit.
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        match command {
+            "xboard" => {}
+            "protover" => {
+                self.protocol_version = args.first().and_then(|s| s.parse::<u32>().ok()).unwrap_or(2);
+                println!(
+                    "feature myname=\"{}\" usermove=1 setboard=1 ping=1 playother=1 memory=1 smp=1 colors=0 sigint=0 sigterm=0 done=1",
+                    ENGINE_NAME
+                );
+            }
+            "new" => {
+                self.abort_search();
+                self.game_state.clear_transposition_table();
+                self.game_state.clear_pawn_tables();
+                if let Err(e) = self.game_state.set_position(None) {
+                    println!("Error (new): {}", e);
+                }
+                self.engine_side = Some(Color::Black);
+                self.move_time = None;
+                self.max_depth = None;
+            }
+            "force" => {
+                self.abort_search();
+                self.engine_side = None;
+            }
+            "go" => {
+                self.abort_search();
+                self.engine_side = Some(self.game_state.get_board_state().get_side());
+                self.think();
+            }
+            "playother" => {
+                self.abort_search();
+                self.engine_side = Some(self.game_state.get_board_state().get_opposite_side());
+            }
+            "usermove" => match args.first() {
+                Some(move_str) => self.handle_usermove(move_str),
+                None => println!("Error (missing move): usermove"),
+            },
+            "level" => {
+                if let Err(e) = self.handle_level(args) {
+                    println!("Error ({}): {}", e, line);
+                }
+            }
+            "st" => match args.first().and_then(|s| s.parse::<f64>().ok()).map(Duration::try_from_secs_f64) {
+                Some(Ok(time)) => self.move_time = Some(time),
+                _ => println!("Error (invalid time): {}", line),
+            },
+            "sd" => self.max_depth = args.first().and_then(|s| s.parse::<u8>().ok()),
+            "memory" => match args.first().and_then(|s| s.parse::<usize>().ok()) {
+                Some(size_mb) => self.game_state.resize_transposition_table(size_mb),
+                None => println!("Error (invalid size): {}", line),
+            },
+            "cores" => match args.first().and_then(|s| s.parse::<usize>().ok()) {
+                Some(cores) => self.game_state.set_threads(cores),
+                None => println!("Error (invalid count): {}", line),
+            },
+            "time" => {
+                if let Some(time) = parse_centiseconds(args) {
+                    self.game_state.set_clock_time(time);
+                }
+            }
+            "otim" => {
+                if let Some(time) = parse_centiseconds(args) {
+                    self.opponent_time = time;
+                }
+            }
+            "undo" => self.take_back(1),
+            "remove" => self.take_back(2),
+            "setboard" => {
+                self.abort_search();
+                if let Err(e) = self.game_state.set_position(Some(&args.join(" "))) {
+                    println!("tellusererror Illegal position: {}", e);
+                }
+            }
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "hard" => self.ponder = true,
+            "easy" => {
+                self.ponder = false;
+                if self.is_pondering() {
+                    self.abort_search();
+                }
+            }
+            "?" => self.move_now(),
+            "result" => {
+                self.abort_search();
+                self.engine_side = None;
+            }
+            "ping" => println!("pong {}", args.first().unwrap_or(&"")),
+            "quit" => {
+                self.abort_search();
+                return false;
+            }
+            "accepted" | "rejected" | "random" | "computer" | "name" | "rating" | "ics" | "." | "draw" => {}
+            // Protocol version 1 GUIs send bare moves
+            _ if self.protocol_version == 1 => self.handle_usermove(command),
+            _ => println!("Error (unknown command): {}", command),
+        }
+        true
+    }
+
+    /// Applies the opponent's move and replies if it is the engine's turn
+    fn handle_usermove(&mut self, move_str: &str) {
+        let Ok(m) = uci::parse_move(&self.game_state, move_str) else {
+            println!("Illegal move: {}", move_str);
+            return;
+        };
+        let ponder_hit = self.search.as_ref().is_some_and(|search| search.ponder_move == Some(m));
+        if !ponder_hit {
+            self.abort_search();
+        }
+        self.game_state.make_move(m);
+
+        if self.report_game_over() {
+            self.abort_search();
+            self.engine_side = None;
+        } else if ponder_hit {
+            // The ponder search already searches this position and goes on as a normal timed search
+            if let Some(search) = self.search.as_mut() {
+                search.signals.ponderhit();
+                search.ponder_move = None;
+            }
+        } else if self.engine_side == Some(self.game_state.get_board_state().get_side()) {
+            self.think();
+        }
+    }
+
+    /// `level MPS BASE INC`, where BASE is minutes or `minutes:seconds` and INC is seconds
+    fn handle_level(&mut self, args: &[&str]) -> Result<(), String> {
+        let [mps, base, inc] = args else {
+            return Err("expected 'level MPS BASE INC'".into());
+        };
+        let mps = mps.parse::<u32>().map_err(|_| format!("invalid moves per session: {}", mps))?;
+        let base = match base.split_once(':') {
+            Some((min, sec)) => {
+                let min = min.parse::<u64>().map_err(|_| format!("invalid base time: {}", base))?;
+                let sec = sec.parse::<u64>().map_err(|_| format!("invalid base time: {}", base))?;
+                Duration::from_secs(min * 60 + sec)
+            }
+            None => base
+                .parse::<f64>()
+                .ok()
+                .and_then(|min| Duration::try_from_secs_f64(min * 60.0).ok())
+                .ok_or_else(|| format!("invalid base time: {}", base))?,
+        };
+        let inc = inc
+            .parse::<f64>()
+            .ok()
+            .and_then(|sec| Duration::try_from_secs_f64(sec).ok())
+            .ok_or_else(|| format!("invalid increment: {}", inc))?;
+
+        self.moves_per_control = mps;
+        self.increment = inc;
+        self.game_state.set_clock_time(base);
+        Ok(())
+    }
+
+    /// Takes back up to `count` moves
+    fn take_back(&mut self, count: usize) {
+        self.abort_search();
+        for _ in 0..count.min(self.game_state.get_history_len()) {
+            self.game_state.unmake_move();
+        }
+    }
+
+    /// Starts searching for the engine's move, which is played when the search ends
+    fn think(&mut self) {
+        if self.report_game_over() {
+            self.engine_side = None;
+            return;
+        }
+        self.start_search(None);
+    }
+
+    /// Starts a search on a background thread, which sends `SearchDone` when it ends.
+    /// With a `ponder_move`, the position after it is searched until the opponent plays it.
+    fn start_search(&mut self, ponder_move: Option<Move>) {
+        // The copy shares the transposition and pawn hash tables with this game state
+        let mut game_state = self.game_state.clone();
+        if let Some(m) = ponder_move {
+            game_state.make_move(m);
+        }
+        let limits = self.search_limits(&mut game_state);
+        let signals = SearchSignals::new(ponder_move.is_some());
+        let search_signals = signals.clone();
+        let sender = self.sender.clone();
+        let post = self.post;
+        let id = self.next_search_id;
+        self.next_search_id += 1;
+
+        let handle = thread::spawn(move || {
+            let mut pv = Vec::new();
+            let best_move = move_eval::find_best_move(&mut game_state, &limits, &search_signals, &mut |report| {
+                if post {
+                    println!("{}", format_thinking(report));
+                }
+                pv = report.pv.clone();
+            });
+            // The receiver is only gone once the engine quits
+            let _ = sender.send(Event::SearchDone(id));
+            (best_move, pv)
+        });
+        self.search = Some(BackgroundSearch {
+            id,
+            signals,
+            handle,
+            ponder_move,
+        });
+    }
+
+    /// Plays the move of search `id` if it is the running search, then ponders on the expected reply
+    fn finish_search(&mut self, id: u64) {
+        let Some(search) = self.search.take_if(|search| search.id == id && search.ponder_move.is_none()) else {
+            return;
+        };
+        let (best_move, pv) = search.handle.join().expect("search thread panicked");
+        let Some(best_move) = best_move else { return };
+        self.game_state.make_move(best_move);
+        println!("move {}", best_move);
+
+        if self.report_game_over() {
+            self.engine_side = None;
+        } else if let (true, [first, reply, ..]) = (self.ponder, pv.as_slice()) {
+            if *first == best_move {
+                self.start_search(Some(*reply));
+            }
+        }
+    }
+
+    /// `?`: stops thinking and plays the best move found so far
+    fn move_now(&mut self) {
+        let Some(search) = self.search.as_ref().filter(|search| search.ponder_move.is_none()) else {
+            return;
+        };
+        search.signals.stop();
+        self.finish_search(search.id);
+    }
+
+    /// Stops the running search, if any, without playing its move
+    fn abort_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.signals.stop();
+            search.handle.join().expect("search thread panicked");
+        }
+    }
+
+    fn is_pondering(&self) -> bool {
+        self.search.as_ref().is_some_and(|search| search.ponder_move.is_some())
+    }
+
+    /// Builds search limits for `game_state` from the clock fields set by `level`, `time`, `st` and `sd`
+    fn search_limits(&self, game_state: &mut GameState) -> SearchLimits {
+        let board = game_state.get_board_state();
+        let side = board.get_side();
+
+        // Sudden death unless `level` set a number of moves per time control
+        let moves_to_go = (self.moves_per_control > 0).then(|| {
+            let moves_played = board.get_full_moves().saturating_sub(1);
+            self.moves_per_control - moves_played % self.moves_per_control
+        });
+        game_state.set_moves_to_go(moves_to_go);
+
+        let mut limits = SearchLimits::from_clock(side, game_state.get_clock_time(), game_state.get_moves_to_go());
+        match side {
+            Color::White => {
+                limits.winc = Some(self.increment);
+                limits.btime = Some(self.opponent_time);
+            }
+            Color::Black => {
+                limits.binc = Some(self.increment);
+                limits.wtime = Some(self.opponent_time);
+            }
+        }
+        limits.move_time = self.move_time;
+        limits.depth = self.max_depth;
+        limits
+    }
+
+    /// Prints the result if the game is over. Returns true if it is.
+    fn report_game_over(&self) -> bool {
+        let Some(result) = game_over_result(&self.game_state) else {
+            return false;
+        };
+        println!("{}", result);
+        true
+    }
+}
+
+/// Returns the CECP result string if the game has ended
+pub fn game_over_result(game_state: &GameState) -> Option<String> {
+    game_state.game_result().map(|result| result.to_string())
+}
+
+/// Formats a search report as CECP thinking output: `ply score time nodes pv`
+pub fn format_thinking(report: &SearchReport) -> String {
+    let score = match report.mate_in() {
+        Some(moves) if moves > 0 => 100_000 + moves,
+        Some(moves) => -100_000 + moves,
+        None => report.score,
+    };
+    let pv: Vec<String> = report.pv.iter().map(|m| m.to_string()).collect();
+    format!(
+        "{} {} {} {} {}",
+        report.depth,
+        score,
+        report.time.as_millis() / 10,
+        report.nodes,
+        pv.join(" ")
+    )
+}
+
+/// Parses the centisecond argument of `time` and `otim`
+fn parse_centiseconds(args: &[&str]) -> Option<Duration> {
+    let centis = args.first()?.parse::<i64>().ok()?;
+    Some(Duration::from_millis(centis.max(0) as u64 * 10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::square::Square;
+
+    #[test]
+    fn test_level_and_time() {
+        let mut xboard = Xboard::new();
+        xboard.handle_command("level 40 5 2");
+        assert_eq!(xboard.moves_per_control, 40);
+        assert_eq!(xboard.increment, Duration::from_secs(2));
+        assert_eq!(xboard.game_state.get_clock_time(), Duration::from_secs(300));
+
+        xboard.handle_command("level 0 2:30 0");
+        assert_eq!(xboard.game_state.get_clock_time(), Duration::from_secs(150));
+
+        xboard.handle_command("time 1234");
+        xboard.handle_command("otim 500");
+        assert_eq!(xboard.game_state.get_clock_time(), Duration::from_millis(12340));
+        assert_eq!(xboard.opponent_time, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_invalid_times_are_rejected() {
+        let mut xboard = Xboard::new();
+        xboard.handle_command("st 5");
+        for command in [
+            "st -1",
+            "st NaN",
+            "st inf",
+            "level 40 -5 0",
+            "level 40 inf 0",
+            "level 40 5 -2",
+            "level 40 5 NaN",
+        ] {
+            xboard.handle_command(command);
+        }
+        assert_eq!(xboard.move_time, Some(Duration::from_secs(5)));
+        assert_eq!(xboard.moves_per_control, 0);
+        assert_eq!(xboard.increment, Duration::ZERO);
+        assert_eq!(xboard.game_state.get_clock_time(), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_force_usermove_and_undo() {
+        let mut xboard = Xboard::new();
+        xboard.handle_command("new");
+        xboard.handle_command("force");
+        xboard.handle_command("usermove e2e4");
+        xboard.handle_command("usermove e7e5");
+        assert_eq!(xboard.game_state.get_history_len(), 2);
+        assert!(xboard.game_state.get_board_state().get_piece_on_square(Square::E5).is_some());
+
+        xboard.handle_command("undo");
+        assert_eq!(xboard.game_state.get_history_len(), 1);
+        assert!(xboard.game_state.get_board_state().get_piece_on_square(Square::E5).is_none());
+
+        xboard.handle_command("remove");
+        assert_eq!(xboard.game_state.get_history_len(), 0);
+    }
+
+    /// Handles the ends of searches like `run` does until the engine has moved
+    fn wait_for_search(xboard: &mut Xboard) {
+        while xboard.search.is_some() && !xboard.is_pondering() {
+            match xboard.receiver.recv_timeout(Duration::from_secs(10)) {
+                Ok(Event::SearchDone(id)) => xboard.finish_search(id),
+                _ => panic!("search did not finish"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_engine_replies_to_usermove() {
+        let mut xboard = Xboard::new();
+        xboard.handle_command("new");
+        xboard.handle_command("sd 2");
+        xboard.handle_command("usermove e2e4");
+        // The engine plays Black and answers once its search ends
+        wait_for_search(&mut xboard);
+        assert_eq!(xboard.game_state.get_history_len(), 2);
+        assert_eq!(xboard.game_state.get_board_state().get_side(), Color::White);
+    }
+
+    #[test]
+    fn test_move_now_and_force() {
+        let mut xboard = Xboard::new();
+        xboard.handle_command("new");
+        xboard.handle_command("st 1000");
+        xboard.handle_command("usermove e2e4");
+        std::thread::sleep(Duration::from_millis(50));
+        // `?` plays the best move found so far
+        xboard.handle_command("?");
+        assert!(xboard.search.is_none());
+        assert_eq!(xboard.game_state.get_history_len(), 2);
+
+        // `force` stops thinking without moving
+        xboard.handle_command("usermove d2d4");
+        assert!(xboard.search.is_some());
+        xboard.handle_command("force");
+        assert!(xboard.search.is_none());
+        assert_eq!(xboard.game_state.get_history_len(), 3);
+        // The end of the aborted search is ignored
+        while let Ok(Event::SearchDone(id)) = xboard.receiver.try_recv() {
+            xboard.finish_search(id);
+        }
+        assert_eq!(xboard.game_state.get_history_len(), 3);
+    }
+
+    #[test]
+    fn test_hard_ponders_on_expected_reply() {
+        let mut xboard = Xboard::new();
+        xboard.handle_command("new");
+        xboard.handle_command("hard");
+        xboard.handle_command("sd 3");
+        xboard.handle_command("usermove e2e4");
+        wait_for_search(&mut xboard);
+        assert_eq!(xboard.game_state.get_history_len(), 2);
+        let expected = xboard
+            .search
+            .as_ref()
+            .and_then(|search| search.ponder_move)
+            .expect("engine is not pondering");
+
+        // Playing the expected move turns the ponder search into the engine's search
+        xboard.handle_command(&format!("usermove {}", expected));
+        assert!(xboard.search.is_some() && !xboard.is_pondering());
+        wait_for_search(&mut xboard);
+        assert_eq!(xboard.game_state.get_history_len(), 4);
+
+        // `easy` stops pondering
+        assert!(xboard.is_pondering());
+        xboard.handle_command("easy");
+        assert!(xboard.search.is_none());
+    }
+
+    #[test]
+    fn test_bare_moves_only_for_protocol_version_1() {
+        let mut xboard = Xboard::new();
+        xboard.handle_command("force");
+        xboard.handle_command("e2e4");
+        assert_eq!(xboard.game_state.get_history_len(), 1);
+
+        xboard.handle_command("protover 2");
+        xboard.handle_command("e7e5");
+        xboard.handle_command("bogus");
+        assert_eq!(xboard.game_state.get_history_len(), 1);
+        xboard.handle_command("usermove e7e5");
+        assert_eq!(xboard.game_state.get_history_len(), 2);
+    }
+
+    #[test]
+    fn test_setboard_and_game_over() {
+        let mut xboard = Xboard::new();
+        xboard.handle_command("force");
+        xboard.handle_command("setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(game_over_result(&xboard.game_state), None);
+        xboard.handle_command("usermove a1a8");
+        assert_eq!(game_over_result(&xboard.game_state).as_deref(), Some("1-0 {White mates}"));
+    }
+}
@@ -1,11 +1,11 @@
-use std::time::{self, Duration};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::time::Duration;
 
 use crate::{
-    game_logic::game,
-    move_logic::{
-        move_encode::Move,
-        pseudo_move_gen::{self, get_pseudo_moves},
-    },
+    core::eval_params::EvalParams,
+    game_logic::{epd_runner, game::GameState, pgn::PgnReader, uci, uci::Uci, xboard::Xboard},
+    move_logic::{bench, move_eval, perft},
 };
 
 mod core;
@@ -13,7 +13,213 @@ mod game_logic;
 mod move_logic;
 
 fn main() {
-    let mut game_state = game_logic::game::GameState::new(None, Duration::from_secs(300));
+    let mut args: Vec<String> = std::env::args().collect();
+    // `--eval-file <file>` before any command evaluates with the parameters in the file instead of the compiled ones
+    if args.get(1).is_some_and(|arg| arg == "--eval-file") {
+        let loaded = args
+            .get(2)
+            .ok_or_else(|| "usage: --eval-file <file>".to_string())
+            .and_then(|path| EvalParams::load(path));
+        if let Err(e) = loaded.and_then(EvalParams::set_default) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        args.drain(1..3);
+    }
+    match args.get(1).map(String::as_str) {
+        // Interactive console game against the engine
+        Some("play") => GameState::new(None, Duration::from_secs(300)).run(),
+        // `perft <depth> [fen]` / `divide <depth> [fen]`
+        Some(command @ ("perft" | "divide")) => run_perft(command, &args[2..]),
+        // `epd <file> [depth N | movetime MS | nodes N] [csv FILE | json FILE]`
+        Some("epd") => run_epd(&args[2..]),
+        // `pgn <file>`: replays every game of a PGN file, reporting the games whose moves are illegal
+        Some("pgn") => run_pgn(&args[2..]),
+        // `bench [depth]`: fixed-depth search of a set of positions, to measure speed and check that the search is unchanged
+        Some("bench") => run_bench(&args[2..]),
+        // `eval [json] [fen]`: static evaluation term by term, as a table or JSON
+        Some("eval") => run_eval(&args[2..]),
+        // `eval-params [file]`: writes the evaluation parameters in use, to start a parameter file from
+        Some("eval-params") => run_eval_params(&args[2..]),
+        _ => run_protocol(),
+    }
+}
+
+/// Counts move-tree nodes from the start position or a FEN, per root move for `divide`
+fn run_perft(command: &str, args: &[String]) {
+    let Some(depth) = args.first().and_then(|d| d.parse::<u8>().ok()) else {
+        eprintln!("usage: {} <depth> [fen]", command);
+        std::process::exit(1);
+    };
+    let fen = args[1..].join(" ");
+    let fen = (!fen.is_empty()).then_some(fen.as_str());
+
+    let mut game_state = GameState::new(None, Duration::ZERO);
+    if let Err(e) = game_state.set_position(fen) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    if command == "divide" {
+        perft::print_divide(&mut game_state, depth);
+    } else {
+        let start = std::time::Instant::now();
+        let nodes = perft::perft(&mut game_state, depth);
+        println!("Nodes: {}", nodes);
+        println!("Time: {:?}", start.elapsed());
+    }
+}
+
+/// Runs an EPD test suite and prints the result of each position and the totals
+fn run_epd(args: &[String]) {
+    let Some(path) = args.first() else {
+        eprintln!("usage: epd <file> [depth N | movetime MS | nodes N] [csv FILE | json FILE]");
+        std::process::exit(1);
+    };
+    let file = File::open(path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        std::process::exit(1);
+    });
+
+    let args: Vec<&str> = args[1..].iter().map(String::as_str).collect();
+    let mut limits = uci::parse_go(&args);
+    if limits.depth.is_none() && limits.move_time.is_none() && limits.nodes.is_none() {
+        limits.move_time = Some(Duration::from_secs(1));
+    }
+    let output = args.windows(2).find(|w| matches!(w[0], "csv" | "json")).map(|w| (w[0], w[1]));
+
+    let mut game_state = GameState::new(None, Duration::ZERO);
+    let results = epd_runner::run_suite(BufReader::new(file), &mut game_state, &limits, &mut |result| {
+        println!("{}", epd_runner::format_result(result))
+    })
+    .unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        std::process::exit(1);
+    });
+
+    let summary = epd_runner::EpdSummary::new(&results);
+    println!(
+        "Solved {}/{} Nodes: {} Time: {:?}",
+        summary.solved, summary.total, summary.nodes, summary.time
+    );
+
+    if let Some((format, out_path)) = output {
+        let written = File::create(out_path).and_then(|mut out| match format {
+            "csv" => epd_runner::write_csv(&results, &mut out),
+            _ => epd_runner::write_json(&results, &mut out),
+        });
+        if let Err(e) = written {
+            eprintln!("{}: {}", out_path, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Reads the games of a PGN file one at a time and checks that each one replays from its start position
+fn run_pgn(args: &[String]) {
+    let Some(path) = args.first() else {
+        eprintln!("usage: pgn <file>");
+        std::process::exit(1);
+    };
+    let file = File::open(path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        std::process::exit(1);
+    });
+
+    let mut game_state = GameState::new(None, Duration::ZERO);
+    let (mut total, mut failed) = (0, 0);
+    for (i, game) in PgnReader::new(BufReader::new(file)).enumerate() {
+        total += 1;
+        let replayed = game.and_then(|game| {
+            game.replay(&mut game_state)?;
+            Ok(game)
+        });
+        match replayed {
+            Ok(game) => println!(
+                "Game {}: {} - {} {} ({} moves)",
+                i + 1,
+                game.get_tag("White").unwrap_or("?"),
+                game.get_tag("Black").unwrap_or("?"),
+                game.get_result(),
+                game.get_moves().len()
+            ),
+            Err(e) => {
+                failed += 1;
+                println!("Game {}: {}", i + 1, e);
+            }
+        }
+    }
+    println!("Games: {} Errors: {}", total, failed);
+}
+
+/// Searches the bench positions and prints the node counts and speed
+fn run_bench(args: &[String]) {
+    let depth = match args.first() {
+        Some(depth) => depth
+            .parse::<u8>()
+            .ok()
+            .filter(|d| (1..=move_eval::MAX_PLY).contains(d))
+            .unwrap_or_else(|| {
+                eprintln!("usage: bench [depth]");
+                std::process::exit(1);
+            }),
+        None => bench::DEFAULT_BENCH_DEPTH,
+    };
+    bench::print_bench(&mut GameState::new(None, Duration::ZERO), depth);
+}
+
+/// Prints the evaluation breakdown of the start position or a FEN
+fn run_eval(args: &[String]) {
+    let json = args.first().is_some_and(|arg| arg == "json");
+    let fen = args[json as usize..].join(" ");
+    let fen = (!fen.is_empty()).then_some(fen.as_str());
+
+    let mut game_state = GameState::new(None, Duration::ZERO);
+    if let Err(e) = game_state.set_position(fen) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    let trace = game_state.get_board_state().trace_eval(game_state.get_pregen_attacks());
+    if json {
+        println!("{}", trace.to_json());
+    } else {
+        println!("{}", trace);
+    }
+}
+
+fn run_eval_params(args: &[String]) {
+    let params = EvalParams::get_default();
+    match args.first() {
+        Some(path) => {
+            if let Err(e) = params.save(path) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        None => print!("{}", params),
+    }
+}
+
+/// Picks the protocol (UCI or CECP) from the first command the GUI sends
+fn run_protocol() {
+    let mut first_line = String::new();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { return };
+        if !line.trim().is_empty() {
+            first_line = line;
+            break;
+        }
+    }
 
-    game_state.run();
+    if first_line.trim() == "xboard" {
+        let mut xboard = Xboard::new();
+        if xboard.handle_command(&first_line) {
+            xboard.run();
+        }
+    } else {
+        let mut uci = Uci::new();
+        if uci.handle_command(&first_line) {
+            uci.run();
+        }
+    }
 }
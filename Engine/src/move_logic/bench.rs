@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+use crate::game_logic::game::GameState;
+
+use super::move_eval::{self, SearchLimits, SearchSignals};
+
+/// Depth searched by `bench` when none is given
+pub const DEFAULT_BENCH_DEPTH: u8 = 8;
+
+/// Openings, middlegames and endgames searched by `bench`
+const BENCH_POSITIONS: [&str; 12] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1",
+    "r1bq1rk1/pp2ppbp/2np1np1/8/3NP3/2N1BP2/PPPQ2PP/R3KB1R w KQ - 3 9",
+    "3r1k2/4npp1/1ppr3p/p6P/P2PPPP1/1NR5/5K2/2R5 w - - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/3N4 b - - 0 1",
+    "8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1",
+    "6k1/8/8/4PK2/8/8/r7/7R w - - 0 1",
+];
+
+/// Result of searching one bench position
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BenchResult {
+    pub fen: &'static str,
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+/// Searches every bench position to `depth` with an empty transposition table, so the node count only changes with the search.
+/// `on_result` is called after each position.
+pub fn bench(game_state: &mut GameState, depth: u8, on_result: &mut dyn FnMut(&BenchResult)) -> Vec<BenchResult> {
+    let limits = SearchLimits {
+        depth: Some(depth),
+        ..SearchLimits::default()
+    };
+
+    let mut results = Vec::with_capacity(BENCH_POSITIONS.len());
+    for fen in BENCH_POSITIONS {
+        game_state.clear_transposition_table();
+        game_state.set_position(Some(fen)).expect("invalid bench position");
+
+        let start = Instant::now();
+        let mut nodes = 0;
+        move_eval::find_best_move(game_state, &limits, &SearchSignals::default(), &mut |report| nodes = report.nodes);
+        let result = BenchResult {
+            fen,
+            nodes,
+            time: start.elapsed(),
+        };
+        on_result(&result);
+        results.push(result);
+    }
+    results
+}
+
+/// Runs `bench` and prints the nodes of every position followed by the total, time and speed
+pub fn print_bench(game_state: &mut GameState, depth: u8) -> u64 {
+    let results = bench(game_state, depth, &mut |result| println!("{:>10} {}", result.nodes, result.fen));
+    let nodes: u64 = results.iter().map(|r| r.nodes).sum();
+    let time: Duration = results.iter().map(|r| r.time).sum();
+    println!();
+    println!("Nodes: {}", nodes);
+    println!("Time: {:?}", time);
+    println!("NPS: {}", (nodes as u128 * 1000 / time.as_millis().max(1)) as u64);
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_is_deterministic() {
+        let mut game_state = GameState::new(None, Duration::ZERO);
+        let first: Vec<u64> = bench(&mut game_state, 4, &mut |_| {}).iter().map(|r| r.nodes).collect();
+        let second: Vec<u64> = bench(&mut game_state, 4, &mut |_| {}).iter().map(|r| r.nodes).collect();
+        assert_eq!(first.len(), BENCH_POSITIONS.len());
+        assert!(first.iter().all(|&nodes| nodes > 0));
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::core::{attack_pregen::PregenAttacks, bitboard::Bitboard, board_state::BoardState, piece::*, square::Square};
+
+use super::{move_encode::Move, pseudo_move_gen};
+
+/// Generates all legal moves for the current position
+///
+/// Pseudo-legal moves are filtered using the checkers and pinned pieces of the side to move,
+/// so no move has to be made and unmade to test it.
+///
+/// # Arguments
+/// * `board_state` - The current board state
+/// * `pregen_attacks` - Pre-generated attack tables
+/// * `moves` - Vector to store the generated moves
+#[inline]
+pub fn get_legal_moves(board_state: &BoardState, pregen_attacks: &PregenAttacks, moves: &mut Vec<Move>) {
+    pseudo_move_gen::get_pseudo_moves(board_state, pregen_attacks, moves);
+
+    let side = board_state.get_side();
+    // Toy positions used in tests may have no king, in which case every move is legal
+    let Some(&king_sq) = board_state.get_piece_squares(Piece::new(side, PieceType::King)).first() else {
+        return;
+    };
+
+    let legality = Legality::new(board_state, pregen_attacks, king_sq);
+    moves.retain(|&m| legality.is_legal(m));
+}
+
+/// Checks if the side to move has at least one legal move
+#[inline]
+pub fn has_legal_moves(board_state: &BoardState, pregen_attacks: &PregenAttacks) -> bool {
+    let mut moves = Vec::with_capacity(256);
+    get_legal_moves(board_state, pregen_attacks, &mut moves);
+    !moves.is_empty()
+}
+
+/// Check and pin information for the side to move
+struct Legality<'a> {
+    board_state: &'a BoardState,
+    pregen_attacks: &'a PregenAttacks,
+    king_sq: Square,
+    /// Enemy pieces giving check
+    checkers: Bitboard,
+    /// Squares a non-king move must land on: the checker and the squares between it and the king
+    check_mask: Bitboard,
+    /// Friendly pieces that may only move along the line to their king
+    pinned: Bitboard,
+}
+
+impl<'a> Legality<'a> {
+    fn new(board_state: &'a BoardState, pregen_attacks: &'a PregenAttacks, king_sq: Square) -> Self {
+        let side = board_state.get_side();
+        let enemy = side.opposite();
+        let occupancy = board_state.get_combined_bb();
+
+        let checkers = board_state.get_attackers(king_sq, enemy, occupancy, pregen_attacks);
+        let check_mask = match checkers.count_squares() {
+            0 => Bitboard::new_full(),
+            1 => checkers.combine(pregen_attacks.get_between(king_sq, checkers.get_ls_square())),
+            _ => Bitboard::new_empty(),
+        };
+
+        // Enemy sliders that would attack the king on an empty board pin a lone friendly piece in between
+        let queens = board_state.get_piece_bb(Piece::new(enemy, PieceType::Queen));
+        let snipers = pregen_attacks
+            .get_rook_attacks(king_sq, &Bitboard::new_empty())
+            .intersect(board_state.get_piece_bb(Piece::new(enemy, PieceType::Rook)).combine(queens))
+            .combine(
+                pregen_attacks
+                    .get_bishop_attacks(king_sq, &Bitboard::new_empty())
+                    .intersect(board_state.get_piece_bb(Piece::new(enemy, PieceType::Bishop)).combine(queens)),
+            );
+        let mut pinned = Bitboard::new_empty();
+        for sniper_sq in snipers.get_occupied_squares() {
+            let blockers = pregen_attacks.get_between(king_sq, sniper_sq).intersect(occupancy);
+            if blockers.count_squares() == 1 {
+                pinned = pinned.combine(blockers.intersect(board_state.get_position_bb(side)));
+            }
+        }
+
+        Self {
+            board_state,
+            pregen_attacks,
+            king_sq,
+            checkers,
+            check_mask,
+            pinned,
+        }
+    }
+
+    fn is_legal(&self, m: Move) -> bool {
+        let from = m.get_from();
+        let to = m.get_to();
+        let enemy = self.board_state.get_opposite_side();
+
+        if from == self.king_sq {
+            if m.is_castling() {
+                // Move generation already checks the king's square and the square it passes
+                return !self.board_state.is_square_attacked(to, enemy, self.pregen_attacks);
+            }
+            // Lift the king off the board so sliders checking along its line still attack the square behind it
+            let occupancy = self.board_state.get_combined_bb().diff(from.to_bitboard());
+            return self.board_state.get_attackers(to, enemy, occupancy, self.pregen_attacks).is_empty();
+        }
+
+        if m.is_en_passant() {
+            return self.is_legal_en_passant(from, to);
+        }
+        if !self.check_mask.is_occupied(to) {
+            return false;
+        }
+        !self.pinned.is_occupied(from) || self.pregen_attacks.get_line(self.king_sq, from).is_occupied(to)
+    }
+
+    /// En passant removes two pieces from one rank, so replay the capture on the occupancy and look for any attacker
+    fn is_legal_en_passant(&self, from: Square, to: Square) -> bool {
+        if self.checkers.count_squares() > 1 {
+            return false;
+        }
+        let captured_sq = match self.board_state.get_side() {
+            Color::White => to.move_down(1),
+            Color::Black => to.move_up(1),
+        };
+        let occupancy = self
+            .board_state
+            .get_combined_bb()
+            .diff(from.to_bitboard())
+            .diff(captured_sq.to_bitboard())
+            .combine(to.to_bitboard());
+        let enemy = self.board_state.get_opposite_side();
+        self.board_state
+            .get_attackers(self.king_sq, enemy, occupancy, self.pregen_attacks)
+            .is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::zobrist::ZobristHasher;
+
+    fn legal_moves(fen: &str) -> Vec<Move> {
+        let zobrist = ZobristHasher::new();
+        let board_state = BoardState::new(Some(fen), &zobrist).unwrap();
+        let pregen_attacks = PregenAttacks::init();
+        let mut moves = Vec::new();
+        get_legal_moves(&board_state, &pregen_attacks, &mut moves);
+        moves
+    }
+
+    fn move_strings(moves: &[Move]) -> Vec<String> {
+        let mut strings: Vec<String> = moves.iter().map(|m| m.to_string()).collect();
+        strings.sort();
+        strings
+    }
+
+    #[test]
+    fn test_initial_position() {
+        assert_eq!(legal_moves("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").len(), 20);
+    }
+
+    #[test]
+    fn test_pinned_piece_moves_along_pin() {
+        // The rook on e2 is pinned by the rook on e8 and may only move along the e-file
+        let moves = legal_moves("4r1k1/8/8/8/8/8/4R3/4K3 w - - 0 1");
+        let rook_moves: Vec<Move> = moves.into_iter().filter(|m| m.get_from() == Square::E2).collect();
+        assert_eq!(
+            move_strings(&rook_moves),
+            ["e2e3", "e2e4", "e2e5", "e2e6", "e2e7", "e2e8"].map(String::from)
+        );
+
+        // A pinned knight cannot move at all
+        let moves = legal_moves("4k3/8/8/8/1b6/8/3N4/4K3 w - - 0 1");
+        assert!(moves.iter().all(|m| m.get_from() != Square::D2));
+    }
+
+    #[test]
+    fn test_check_evasions() {
+        // Single check by a rook: block or step aside
+        let moves = legal_moves("4k3/4r3/8/8/8/8/R7/4K3 w - - 0 1");
+        assert!(moves.iter().any(|m| m.to_string() == "a2e2"));
+        assert!(moves.iter().all(|m| m.get_piece().is_king() || m.get_to() == Square::E2));
+
+        // Double check: only king moves
+        let moves = legal_moves("4k3/8/8/8/7b/3n4/8/R3K3 w Q - 0 1");
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|m| m.get_piece().is_king()));
+
+        // The king may not retreat along the checking line
+        let moves = legal_moves("4k3/4r3/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(move_strings(&moves), ["e1d1", "e1d2", "e1f1", "e1f2"].map(String::from));
+    }
+
+    #[test]
+    fn test_castling_through_check() {
+        // The bishop on c4 covers f1, so only queen-side castling is allowed
+        let moves = legal_moves("4k3/8/8/8/2b5/8/8/R3K2R w KQ - 0 1");
+        let castles: Vec<Move> = moves.into_iter().filter(|m| m.is_castling()).collect();
+        assert_eq!(move_strings(&castles), ["e1c1"]);
+
+        // Castling into check on g1 is illegal
+        let moves = legal_moves("4k3/8/8/8/8/8/6r1/4K2R w K - 0 1");
+        assert!(moves.iter().all(|m| !m.is_castling()));
+    }
+
+    #[test]
+    fn test_en_passant_discovered_check() {
+        // Capturing en passant would expose the king on a5 to the rook on h5
+        let moves = legal_moves("8/8/8/KPp4r/8/8/8/7k w - c6 0 1");
+        assert!(moves.iter().all(|m| !m.is_en_passant()));
+
+        // Without the rook the capture is legal
+        let moves = legal_moves("8/8/8/KPp5/8/8/8/7k w - c6 0 1");
+        assert!(moves.iter().any(|m| m.is_en_passant()));
+
+        // En passant capture of a checking pawn
+        let moves = legal_moves("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1");
+        assert!(moves.iter().any(|m| m.is_en_passant()));
+    }
+
+    #[test]
+    fn test_checkmate_and_stalemate_have_no_moves() {
+        assert!(legal_moves("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").is_empty());
+        assert!(legal_moves("7k/5Q2/8/8/8/8/8/6K1 b - - 0 1").is_empty());
+    }
+}
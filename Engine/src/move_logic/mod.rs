@@ -1,3 +1,12 @@
+pub mod bench;
+pub mod legal_move_gen;
 pub mod move_encode;
 pub mod move_eval;
+pub mod move_ordering;
+pub mod perft;
 pub mod pseudo_move_gen;
+pub mod san;
+#[cfg(test)]
+pub mod test_position;
+pub mod time_management;
+pub mod transposition_table;
@@ -1,8 +1,6 @@
 use crate::core::{
-    bitboard::*,
     board_state::BoardState,
-    piece::{Color, Piece, PieceType, PIECES},
-    piece_square_table::{Phase, PieceSquareTable},
+    piece::{Piece, PieceType, PIECES},
     square::*,
 };
 
@@ -27,6 +25,7 @@ const CASTLING_FLAG: u32 = 1 << 26;
 pub struct Move(u32);
 
 impl Move {
+    #[allow(clippy::too_many_arguments)]
     #[inline(always)]
     pub fn new(
         from: Square,
@@ -72,7 +71,9 @@ impl Move {
         let mut castling = false;
 
         if algebraic.len() == 5 {
-            promotion = Some(Piece::from_char(algebraic.chars().nth(4).unwrap())?);
+            // Promotion piece takes the mover's color regardless of the letter's case (UCI sends `e7e8q`)
+            let promo_type = Piece::from_char(algebraic.chars().nth(4).unwrap().to_ascii_uppercase())?.get_type();
+            promotion = Some(Piece::new(piece.get_color(), promo_type));
         }
 
         if piece.get_type() == PieceType::Pawn {
@@ -159,193 +160,30 @@ impl Move {
 
     #[inline(always)]
     pub const fn is_quiet(&self) -> bool {
-        self.is_capture() || self.is_promotion()
+        !(self.is_capture() || self.is_promotion())
     }
 
+    /// Returns the 27-bit encoding of the move, which is never zero
     #[inline(always)]
-    pub fn get_score(&self) -> i32 {
-        let mut score = 0;
-        let piece = self.get_piece();
-        let color = piece.get_color();
-        let from = self.get_from();
-        let to = self.get_to();
-        let rank = to.get_rank() as i32;
-        let file = to.get_file() as i32;
-
-        // MVV-LVA (Most Valuable Victim - Least Valuable Attacker)
-        if let Some(captured_piece) = self.get_capture() {
-            // Prioritize captures by victim value (10x) and penalize by attacker value
-            score += captured_piece.get_value() * 10 - piece.get_value();
-        }
-
-        // Special moves scoring
-        if self.is_castling() {
-            score += 500; // High priority for castling
-        }
-
-        if let Some(promotion_piece) = self.get_promotion() {
-            score += promotion_piece.get_value() * 10; // Prioritize promotions
-        }
-
-        if self.is_en_passant() {
-            score += 200; // Good bonus for en passant
-        }
-
-        if self.is_double_pawn_push() {
-            score += 30; // Bonus for double pawn push
-        }
-
-        // Position-based scoring using piece-square tables
-        let pst = PieceSquareTable::new();
-
-        // Penalize moving from a good square
-        score -= pst.get_value(Phase::Opening, piece, from);
-
-        // Reward moving to a good square
-        score += pst.get_value(Phase::Opening, piece, to);
-
-        // Piece-specific bonuses
-        match piece.get_type() {
-            PieceType::Pawn => {
-                // Encourage pawn advancement and structure
-                if color == Color::White {
-                    // Encourage pawn advancement
-                    score += rank * 10;
-
-                    // Bonus for central pawns
-                    if file >= 2 && file <= 5 {
-                        score += 10;
-                    }
-                } else {
-                    // Encourage pawn advancement
-                    score += (7 - rank) * 10;
-
-                    // Bonus for central pawns
-                    if file >= 2 && file <= 5 {
-                        score += 10;
-                    }
-                }
-            }
-            PieceType::Knight => {
-                // Encourage development and central control
-                if (color == Color::White && rank > 0) || (color == Color::Black && rank < 7) {
-                    score += 20;
-                }
-
-                // Strong bonus for central knights
-                if file >= 2 && file <= 5 && rank >= 2 && rank <= 5 {
-                    score += 30;
-                }
-
-                // Penalty for knights on the edge
-                if file == 0 || file == 7 {
-                    score -= 10;
-                }
-            }
-            PieceType::Bishop => {
-                // Encourage development
-                if (color == Color::White && rank > 0) || (color == Color::Black && rank < 7) {
-                    score += 20;
-                }
-
-                // Bonus for central bishops
-                if file >= 2 && file <= 5 && rank >= 2 && rank <= 5 {
-                    score += 20;
-                }
-            }
-            PieceType::Rook => {
-                // Encourage rooks on open/semi-open files
-                if (color == Color::White && rank > 0) || (color == Color::Black && rank < 7) {
-                    score += 15;
-                }
-
-                // Bonus for rooks on 7th rank
-                if (color == Color::White && rank == 6) || (color == Color::Black && rank == 1) {
-                    score += 30;
-                }
-            }
-            PieceType::Queen => {
-                // Keep queen safe in opening
-                if (color == Color::White && rank > 0) || (color == Color::Black && rank < 7) {
-                    score -= 10;
-                }
-
-                // Bonus for central queen in endgame
-                if file >= 2 && file <= 5 && rank >= 2 && rank <= 5 {
-                    score += 10;
-                }
-
-                // Penalty for early queen development
-                if (color == Color::White && rank > 1) || (color == Color::Black && rank < 6) {
-                    score -= 20;
-                }
-            }
-            PieceType::King => {
-                // Encourage king safety
-                if (color == Color::White && rank > 0) || (color == Color::Black && rank < 7) {
-                    score -= 20;
-                }
-
-                // Bonus for castled king
-                if self.is_castling() {
-                    score += 50;
-                }
-            }
-            _ => {}
-        }
-
-        // Opening/development penalty
-        if piece.get_type() != PieceType::Pawn {
-            score -= 10;
-        }
-
-        score
+    pub const fn get_bits(&self) -> u32 {
+        self.0
     }
 
+    /// Rebuilds a move from the encoding returned by `get_bits`
     #[inline(always)]
-    pub fn to_string(&self) -> String {
-        let mut s = String::with_capacity(5);
-        s.push_str(&self.get_from().to_string());
-        s.push_str(&self.get_to().to_string());
-        if let Some(p) = self.get_promotion() {
-            s.push(p.to_char());
-        }
-        s
+    pub const fn from_bits(bits: u32) -> Self {
+        Move(bits)
     }
+}
 
-    #[inline(always)]
-    pub fn print_move(&self) {
-        println!(
-            "from:{}\nto: {}\npiece: {}\ncap: {}\npromo: {}\ndouble: {}\ncastling: {}\nenpassant: {}\nscore: {}",
-            self.get_from().to_string(),
-            self.get_to().to_string(),
-            self.get_piece().to_char(),
-            self.is_capture(),
-            self.get_promotion().map_or(' ', |piece| piece.to_char()),
-            self.is_double_pawn_push(),
-            self.is_castling(),
-            self.is_en_passant(),
-            self.get_score(),
-        );
-        println!();
-        for rank in RANKS.iter().rev() {
-            for file in FILES.iter() {
-                print!(
-                    "| {} |",
-                    Bitboard::new_from_square(self.get_from()).is_occupied(Square::from_file_rank(*file, *rank)) as u8
-                )
-            }
-            print!("--{:?}--", self.get_piece());
-
-            for file in FILES.iter() {
-                print!(
-                    "| {} |",
-                    Bitboard::new_from_square(self.get_to()).is_occupied(Square::from_file_rank(*file, *rank)) as u8
-                )
-            }
-            println!();
+impl std::fmt::Display for Move {
+    /// Formats the move in coordinate notation (e.g. `e2e4`, `e7e8q`)
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}{}", self.get_from(), self.get_to())?;
+        if let Some(p) = self.get_promotion() {
+            write!(f, "{}", p.to_char().to_ascii_lowercase())?;
         }
-        println!();
+        Ok(())
     }
 }
 
@@ -359,28 +197,28 @@ mod tests {
         assert_eq!(m.get_from(), Square::E2);
         assert_eq!(m.get_to(), Square::E4);
         assert_eq!(m.get_piece(), Piece::WPawn);
-        assert_eq!(m.is_double_pawn_push(), true);
+        assert!(m.is_double_pawn_push());
     }
 
     #[test]
     fn test_move_flags() {
         let m = Move::new(Square::E1, Square::G1, Piece::WKing, None, None, false, false, true);
-        assert_eq!(m.is_castling(), true);
-        assert_eq!(m.is_quiet(), true);
+        assert!(m.is_castling());
+        assert!(m.is_quiet());
     }
 
     #[test]
     fn test_move_capture() {
         let m = Move::new(Square::E4, Square::D5, Piece::WPawn, Some(Piece::BPawn), None, false, true, false);
-        assert_eq!(m.is_capture(), true);
-        assert_eq!(m.is_en_passant(), true);
+        assert!(m.is_capture());
+        assert!(m.is_en_passant());
         assert_eq!(m.get_capture(), Some(Piece::BPawn));
     }
 
     #[test]
     fn test_move_promotion() {
         let m = Move::new(Square::E7, Square::E8, Piece::WPawn, None, Some(Piece::WQueen), false, false, false);
-        assert_eq!(m.is_promotion(), true);
+        assert!(m.is_promotion());
         assert_eq!(m.get_promotion(), Some(Piece::WQueen));
     }
 }
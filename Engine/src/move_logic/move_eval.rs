@@ -1,7 +1,27 @@
-use crate::game_logic::game::{GameState, TTEntry};
-use std::time::{Duration, Instant};
-
-use super::{move_encode::Move, pseudo_move_gen};
+use crate::{
+    core::{
+        pawn_table::PawnTable,
+        piece::{Color, PieceType},
+        square::Rank,
+    },
+    game_logic::game::GameState,
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{
+    legal_move_gen,
+    move_encode::Move,
+    move_ordering::{MovePicker, OrderingTables},
+    time_management::TimeManager,
+    transposition_table::{EntryType, TTEntry},
+};
 
 // Search constants
 const NULL_MOVE_DEPTH: u8 = 3;
@@ -10,111 +30,245 @@ const NULL_MOVE_PIECE_COUNT: u8 = 8;
 const ASPIRATION_WINDOW: i32 = 50;
 const FUTILITY_MARGIN: i32 = 150;
 const LMR_MIN_DEPTH: u8 = 3;
-const LMR_MIN_MOVE: usize = 4;
-const QUIESCENCE_DEPTH: u8 = 6;
-const MATE_SCORE: i32 = 100_000;
-const DRAW_SCORE: i32 = 0;
-const MAX_PLY: u8 = 64;
+const LMR_MIN_MOVE: usize = 3;
+/// Late move reduction is `LMR_BASE + ln(depth) * ln(move number) / LMR_DIVISOR` plies
+const LMR_BASE: f64 = 0.75;
+const LMR_DIVISOR: f64 = 2.25;
+/// Minimum depth for singular extensions, and how far the TT entry may be shallower than the node
+const SINGULAR_MIN_DEPTH: u8 = 8;
+const SINGULAR_TT_DEPTH_MARGIN: u8 = 3;
+/// Margin per ply of depth below the TT score that other moves must stay under for the TT move to be singular
+const SINGULAR_MARGIN: i32 = 2;
+/// Plies of quiescence search below the main search
+const QUIESCENCE_DEPTH: u8 = 8;
+/// Plies at the start of quiescence search that also try quiet checks
+const QUIESCENCE_CHECK_PLIES: u8 = 1;
+pub const MATE_SCORE: i32 = 100_000;
+pub const MAX_PLY: u8 = 64;
+/// Largest contempt accepted by the protocols, in centipawns
+pub const MAX_CONTEMPT: i32 = 200;
 const RAZOR_MARGIN: i32 = 300;
-const DELTA_MARGIN: i32 = 975;
-
-// Transposition table entry types
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub enum EntryType {
-    Exact,
-    LowerBound,
-    UpperBound,
-}
-
-#[derive(Clone, Debug)]
-struct SearchInfo {
-    start_time: Instant,
-    nodes: u64,
-    best_move: Option<Move>,
-    best_score: i32,
-    depth: u8,
-    pv: Vec<Move>, // principal variation from root
-    selective_depth: u8,
-    time_management: TimeManagement,
-    ply: u8,
-    stop: bool,
+/// Nodes searched between two checks of the clock and node limit by the main thread
+const LIMIT_CHECK_INTERVAL: u64 = 1024;
+
+/// Limits for a single search, mirroring the parameters of the UCI `go` command
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub moves_to_go: Option<u32>,
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub move_time: Option<Duration>,
+    pub infinite: bool,
+    /// Look for a forced mate in at most this many moves, turning off pruning that could hide one.
+    /// The search ends as soon as such a mate is found, or once it is refuted by a full search to the matching depth.
+    pub mate: Option<u8>,
+    /// Search the position after the expected reply on the opponent's time, see `SearchSignals::ponder`
+    pub ponder: bool,
 }
 
-#[derive(Clone, Debug)]
-struct TimeManagement {
-    max_time: Duration,
-    optimal_time: Duration,
-    min_time: Duration,
-    max_nodes: u64,
+/// Flags to control a running search from another thread. Use a new set of signals for every search.
+#[derive(Clone, Debug, Default)]
+pub struct SearchSignals {
+    /// Ends the search as soon as possible; the best move found so far is returned
+    pub stop: Arc<AtomicBool>,
+    /// While set, time limits are not applied. Clearing it (a ponder hit) starts the clock for a normal timed search.
+    pub ponder: Arc<AtomicBool>,
 }
 
-impl TimeManagement {
-    fn new(time_left: Duration, increment: Duration, moves_to_go: u32) -> Self {
-        let moves_to_go = moves_to_go.max(1);
-
-        // Calculate optimal time based on game phase
-        let optimal_time = if moves_to_go <= 5 {
-            // Endgame: use more time
-            time_left / moves_to_go
-        } else {
-            // Opening/middlegame: use less time
-            time_left / (moves_to_go * 2)
-        };
+impl SearchSignals {
+    pub fn new(ponder: bool) -> Self {
+        Self {
+            stop: Arc::new(AtomicBool::new(false)),
+            ponder: Arc::new(AtomicBool::new(ponder)),
+        }
+    }
 
-        // Add increment if available
-        let optimal_time = optimal_time + increment;
+    /// Asks the search to end
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
 
-        // Set minimum time to avoid instant moves
-        let min_time = Duration::from_millis(100);
+    /// Converts a pondering search into a normal timed search
+    pub fn ponderhit(&self) {
+        self.ponder.store(false, Ordering::Relaxed);
+    }
 
-        // Set maximum time to avoid time trouble
-        let max_time = optimal_time * 2;
+    fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
 
-        // Estimate max nodes based on time
-        let max_nodes = 1_000_000; // Conservative estimate
+    fn is_pondering(&self) -> bool {
+        self.ponder.load(Ordering::Relaxed)
+    }
+}
 
-        Self {
-            max_time,
-            optimal_time,
-            min_time,
-            max_nodes,
+impl SearchLimits {
+    /// Limits for a clock-based search where `time_left` is the side to move's remaining time
+    pub fn from_clock(side: Color, time_left: Duration, moves_to_go: Option<u32>) -> Self {
+        let mut limits = SearchLimits {
+            moves_to_go,
+            ..Default::default()
+        };
+        match side {
+            Color::White => limits.wtime = Some(time_left),
+            Color::Black => limits.btime = Some(time_left),
         }
+        limits
     }
 
-    fn should_stop(&self, elapsed: Duration, nodes: u64) -> bool {
-        // Stop if we've exceeded max time
-        if elapsed >= self.max_time {
-            return true;
+    /// Returns the remaining time and increment for the given side, if any
+    pub fn clock_for(&self, side: Color) -> (Option<Duration>, Duration) {
+        match side {
+            Color::White => (self.wtime, self.winc.unwrap_or_default()),
+            Color::Black => (self.btime, self.binc.unwrap_or_default()),
         }
+    }
+}
 
-        // Stop if we've searched enough nodes
-        if nodes >= self.max_nodes {
-            return true;
-        }
+/// Progress of the search, reported after every completed iteration
+#[derive(Clone, Debug)]
+pub struct SearchReport {
+    pub depth: u8,
+    pub selective_depth: u8,
+    pub score: i32,
+    pub nodes: u64,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+    /// Permille of pawn hash table probes by the reporting thread that were answered from the table
+    pub pawn_hash_hit_rate: u32,
+    /// Permille of the transposition table filled by the current search
+    pub hashfull: u32,
+}
 
-        // Don't stop if we haven't searched minimum time
-        if elapsed < self.min_time {
-            return false;
+impl SearchReport {
+    /// Nodes per second over the elapsed search time
+    pub fn nps(&self) -> u64 {
+        let millis = self.time.as_millis().max(1) as u64;
+        self.nodes * 1000 / millis
+    }
+
+    /// Returns the number of moves to mate (negative if the engine is getting mated), if the score is a mate score
+    pub fn mate_in(&self) -> Option<i32> {
+        mate_in(self.score)
+    }
+}
+
+/// Converts a mate score into moves to mate (negative when being mated)
+pub fn mate_in(score: i32) -> Option<i32> {
+    let plies = MATE_SCORE - score.abs();
+    if !(0..=MAX_PLY as i32).contains(&plies) {
+        return None;
+    }
+    let moves = (plies + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+/// Node counter of one search thread, padded to its own cache line so threads do not slow each other down
+#[derive(Debug, Default)]
+#[repr(align(64))]
+struct NodeCounter(AtomicU64);
+
+/// State shared by all threads of one search
+#[derive(Debug)]
+struct SharedSearch {
+    /// The caller's signals; the stop flag is also set by the main thread when a limit is reached or it is done
+    signals: SearchSignals,
+    nodes: Vec<NodeCounter>,
+}
+
+impl SharedSearch {
+    fn new(threads: usize, signals: SearchSignals) -> Self {
+        Self {
+            signals,
+            nodes: (0..threads).map(|_| NodeCounter::default()).collect(),
         }
+    }
 
-        // Stop if we've used more than optimal time
-        elapsed > self.optimal_time
+    /// Nodes searched by all threads
+    fn total_nodes(&self) -> u64 {
+        self.nodes.iter().map(|counter| counter.0.load(Ordering::Relaxed)).sum()
     }
 }
 
+/// Best move and report of the deepest iteration a thread completed
+struct ThreadResult {
+    /// Index of the thread, see `SearchInfo::thread_id`
+    thread_id: usize,
+    best_move: Move,
+    report: SearchReport,
+}
+
+#[derive(Clone, Debug)]
+struct SearchInfo {
+    start_time: Instant,
+    /// Time from which the time limits count: the start of the search, or the ponder hit
+    limits_start: Instant,
+    /// Whether the search was pondering when the limits were last checked
+    pondering: bool,
+    nodes: u64,
+    best_move: Option<Move>,
+    best_score: i32,
+    depth: u8,
+    pv: Vec<Move>, // principal variation from root
+    selective_depth: u8,
+    time_manager: TimeManager,
+    ply: u8,
+    stop: bool,
+    ordering: OrderingTables,
+    /// Move played at each ply of the current line, None for null moves
+    move_stack: Vec<Option<Move>>,
+    /// Move skipped at each ply by the verification search of a singular extension
+    excluded_moves: Vec<Option<Move>>,
+    /// Index of this thread; thread 0 is the main thread, which enforces the search limits
+    thread_id: usize,
+    shared: Arc<SharedSearch>,
+    /// Side to move at the root, the side the engine plays
+    root_side: Color,
+    /// See `GameState::get_contempt`
+    contempt: i32,
+    /// Searching for a forced mate: pruning that could hide one is turned off, see `SearchLimits::mate`
+    mate_search: bool,
+    /// Pawn structure terms of the positions this thread evaluated, kept by `GameState` between searches
+    pawn_table: PawnTable,
+}
+
 impl SearchInfo {
-    fn new(time_limit: Duration, moves_to_go: u32) -> Self {
+    fn new(time_manager: TimeManager, thread_id: usize, shared: Arc<SharedSearch>, root_side: Color, contempt: i32, pawn_table: PawnTable) -> Self {
         Self {
             start_time: Instant::now(),
+            limits_start: Instant::now(),
+            pondering: shared.signals.is_pondering(),
             nodes: 0,
             best_move: None,
             best_score: i32::MIN + 1,
             depth: 0,
             pv: Vec::with_capacity(MAX_PLY as usize),
             selective_depth: 0,
-            time_management: TimeManagement::new(time_limit, Duration::from_secs(0), moves_to_go),
+            time_manager,
             ply: 0,
             stop: false,
+            ordering: OrderingTables::new(),
+            move_stack: vec![None; MAX_PLY as usize + 1],
+            excluded_moves: vec![None; MAX_PLY as usize + 1],
+            thread_id,
+            shared,
+            root_side,
+            contempt,
+            mate_search: false,
+            pawn_table,
+        }
+    }
+
+    /// Score of a draw for `side`: below zero for the engine and above zero for its opponent with a positive contempt
+    fn draw_score(&self, side: Color) -> i32 {
+        if side == self.root_side {
+            -self.contempt
+        } else {
+            self.contempt
         }
     }
 
@@ -122,26 +276,173 @@ impl SearchInfo {
         self.start_time.elapsed()
     }
 
-    fn should_stop(&self) -> bool {
-        self.time_management.should_stop(self.time_elapsed(), self.nodes)
+    fn should_stop(&mut self) -> bool {
+        if self.shared.signals.is_stopped() {
+            return true;
+        }
+        // Reading the clock and the other threads' counters is slow, so the main thread only does it every few nodes
+        if self.thread_id != 0 || !self.nodes.is_multiple_of(LIMIT_CHECK_INTERVAL) {
+            return false;
+        }
+        let Some(elapsed) = self.limits_elapsed() else {
+            return false;
+        };
+        let stop = self.time_manager.should_stop(elapsed, self.shared.total_nodes());
+        if stop {
+            self.shared.signals.stop();
+        }
+        stop
+    }
+
+    /// Returns the time counted against the search limits, or None while pondering
+    fn limits_elapsed(&mut self) -> Option<Duration> {
+        if self.shared.signals.is_pondering() {
+            return None;
+        }
+        if self.pondering {
+            // Ponder hit: the time for this move starts now
+            self.pondering = false;
+            self.limits_start = Instant::now();
+        }
+        Some(self.limits_start.elapsed())
+    }
+
+    fn add_node(&mut self) {
+        self.nodes += 1;
+        self.shared.nodes[self.thread_id].0.store(self.nodes, Ordering::Relaxed);
     }
 
     fn update_selective_depth(&mut self, depth: u8) {
         self.selective_depth = self.selective_depth.max(depth);
     }
+
+    /// Records the move about to be searched at the current ply
+    fn push_move(&mut self, m: Option<Move>) {
+        if let Some(slot) = self.move_stack.get_mut(self.ply as usize) {
+            *slot = m;
+        }
+        self.ply += 1;
+    }
+
+    /// Returns the move that led to the current node
+    fn get_previous_move(&self) -> Option<Move> {
+        let ply = self.ply.checked_sub(1)?;
+        self.move_stack.get(ply as usize).copied().flatten()
+    }
+}
+
+/// Find the best move within the given search limits, using `GameState::get_threads` threads.
+/// `report` is called with the search progress after every iteration completed by the main thread.
+/// Its last call always carries the result of the thread whose move is returned, which may be a helper thread.
+/// The search can be stopped or switched from pondering to a timed search through `signals`;
+/// an infinite or pondering search does not return before it is stopped or hit, even if it reaches its depth limit.
+///
+/// Helper threads search their own copy of the position and share results through the transposition table (Lazy SMP).
+/// The best move is chosen by a vote weighted by the score and depth each thread completed.
+pub fn find_best_move(
+    game_state: &mut GameState,
+    limits: &SearchLimits,
+    signals: &SearchSignals,
+    report: &mut dyn FnMut(&SearchReport),
+) -> Option<Move> {
+    let side = game_state.get_board_state().get_side();
+    let mut root_moves = Vec::with_capacity(256);
+    legal_move_gen::get_legal_moves(game_state.get_board_state(), game_state.get_pregen_attacks(), &mut root_moves);
+    let time_manager = TimeManager::new(limits, side, game_state.get_move_overhead(), root_moves.len());
+    let threads = game_state.get_threads();
+    let contempt = game_state.get_contempt();
+    let shared = Arc::new(SharedSearch::new(threads, signals.clone()));
+    game_state.tt_new_search();
+
+    let results: Vec<ThreadResult> = std::thread::scope(|s| {
+        let mut pawn_tables = game_state.take_pawn_tables(threads).into_iter();
+        let main_pawn_table = pawn_tables.next().expect("there is at least one search thread");
+        let helpers: Vec<_> = (1..threads)
+            .zip(pawn_tables)
+            .map(|(thread_id, pawn_table)| {
+                let mut worker = game_state.clone();
+                let mut search_info = SearchInfo::new(time_manager.clone(), thread_id, shared.clone(), side, contempt, pawn_table);
+                s.spawn(move || {
+                    let result = iterative_deepening(&mut worker, limits, &mut search_info, &mut |_| {});
+                    (result, search_info.pawn_table)
+                })
+            })
+            .collect();
+
+        let mut search_info = SearchInfo::new(time_manager, 0, shared.clone(), side, contempt, main_pawn_table);
+        let main_result = iterative_deepening(game_state, limits, &mut search_info, report);
+        // The best move may only be sent once an infinite search is stopped or a pondering search is hit
+        while (limits.infinite || signals.is_pondering()) && !signals.is_stopped() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        signals.stop();
+
+        let mut results = vec![main_result];
+        let mut pawn_tables = vec![search_info.pawn_table];
+        for helper in helpers {
+            let (result, pawn_table) = helper.join().expect("search thread panicked");
+            results.push(result);
+            pawn_tables.push(pawn_table);
+        }
+        game_state.return_pawn_tables(pawn_tables);
+        results.into_iter().flatten().collect()
+    });
+
+    let best = select_best_thread(&results)?;
+    // The main thread already reported its own result
+    if best.thread_id != 0 {
+        report(&SearchReport {
+            nodes: shared.total_nodes(),
+            ..best.report.clone()
+        });
+    }
+    Some(best.best_move)
+}
+
+/// Picks the thread whose move got the most votes, each thread voting for its move with its score and depth.
+/// A mate found by any thread is preferred, the shortest one if several threads found a mate.
+fn select_best_thread(results: &[ThreadResult]) -> Option<&ThreadResult> {
+    let min_score = results.iter().map(|r| r.report.score).min()?;
+    let mut votes: HashMap<Move, i64> = HashMap::new();
+    for r in results {
+        *votes.entry(r.best_move).or_default() += (r.report.score - min_score + 20) as i64 * r.report.depth as i64;
+    }
+
+    let is_mate = |r: &ThreadResult| r.report.score >= MATE_SCORE - MAX_PLY as i32;
+    let mut best = &results[0];
+    for r in &results[1..] {
+        let better = if is_mate(best) || is_mate(r) {
+            r.report.score > best.report.score
+        } else {
+            votes[&r.best_move] > votes[&best.best_move]
+        };
+        if better {
+            best = r;
+        }
+    }
+    Some(best)
 }
 
-/// Find the best move within the given time limit. keeps prints for info.
-pub fn find_best_move(game_state: &mut GameState, time_limit: Duration) -> Option<Move> {
-    let moves_to_go = game_state.get_moves_to_go().max(1);
-    let mut search_info = SearchInfo::new(time_limit, moves_to_go);
+/// Searches with increasing depth until the depth limit is reached or the search is stopped.
+/// Returns the best move of the deepest completed iteration.
+fn iterative_deepening(
+    game_state: &mut GameState,
+    limits: &SearchLimits,
+    search_info: &mut SearchInfo,
+    report: &mut dyn FnMut(&SearchReport),
+) -> Option<ThreadResult> {
+    let mut max_depth = limits.depth.unwrap_or(MAX_PLY).clamp(1, MAX_PLY);
+    if let Some(moves) = limits.mate {
+        // A mate in N moves is found by a search of 2N - 1 plies
+        max_depth = max_depth.min((moves.saturating_mul(2).saturating_sub(1)).clamp(1, MAX_PLY));
+        search_info.mate_search = true;
+    }
     let mut window_size = ASPIRATION_WINDOW;
-    let mut depth = 1;
-    let mut last_full_best_move = None;
-    let mut last_full_pv: Vec<Move> = Vec::new();
-    let mut last_full_score = 0;
+    // Half of the helper threads start one iteration deeper, so threads do not all search the same depth
+    let mut depth = 1 + (search_info.thread_id % 2) as u8;
+    let mut result: Option<ThreadResult> = None;
 
-    while depth <= MAX_PLY {
+    while depth <= max_depth {
         search_info.depth = depth;
         search_info.pv.clear();
         search_info.stop = false;
@@ -149,20 +450,20 @@ pub fn find_best_move(game_state: &mut GameState, time_limit: Duration) -> Optio
         let mut alpha = i32::MIN + 1;
         let mut beta = i32::MAX - 1;
         // Aspiration window
-        if depth > 4 && last_full_best_move.is_some() {
-            alpha = last_full_score - window_size;
-            beta = last_full_score + window_size;
+        if let Some(last) = result.as_ref().filter(|_| depth > 4) {
+            alpha = last.report.score - window_size;
+            beta = last.report.score + window_size;
         }
 
         // First search
-        let (mut score, pv) = negamax(game_state, depth, alpha, beta, false, &mut search_info);
-        // If time ran out during search, break without updating last full
+        let (mut score, pv) = negamax(game_state, depth, alpha, beta, false, search_info);
+        // If time ran out during search, break without updating the result
         if search_info.stop {
             break;
         }
         // If fail-low or fail-high, re-search with full window
         if score <= alpha || score >= beta {
-            let (rescore, full_pv) = negamax(game_state, depth, i32::MIN + 1, i32::MAX - 1, false, &mut search_info);
+            let (rescore, full_pv) = negamax(game_state, depth, i32::MIN + 1, i32::MAX - 1, false, search_info);
             if search_info.stop {
                 break;
             }
@@ -175,71 +476,104 @@ pub fn find_best_move(game_state: &mut GameState, time_limit: Duration) -> Optio
         }
 
         // Completed this depth fully
-        last_full_best_move = search_info.best_move;
-        last_full_pv = search_info.pv.clone();
-        last_full_score = score;
+        let iteration = SearchReport {
+            depth,
+            selective_depth: search_info.selective_depth,
+            score,
+            nodes: search_info.shared.total_nodes(),
+            time: search_info.time_elapsed(),
+            pv: search_info.pv.clone(),
+            pawn_hash_hit_rate: search_info.pawn_table.hit_rate(),
+            hashfull: game_state.tt_hashfull(),
+        };
+        report(&iteration);
+        if let Some(best_move) = search_info.best_move {
+            result = Some(ThreadResult {
+                thread_id: search_info.thread_id,
+                best_move,
+                report: iteration,
+            });
+            // The mate the search was looking for is proven
+            if limits
+                .mate
+                .is_some_and(|moves| mate_in(score).is_some_and(|m| (1..=moves as i32).contains(&m)))
+            {
+                search_info.shared.signals.stop();
+                break;
+            }
+            // Only the main thread decides when the search ends
+            if search_info.thread_id == 0 {
+                search_info.time_manager.update(best_move, score);
+                if search_info
+                    .limits_elapsed()
+                    .is_some_and(|elapsed| search_info.time_manager.should_stop_iteration(elapsed))
+                {
+                    search_info.shared.signals.stop();
+                    break;
+                }
+            }
+        }
 
         // Adjust window for next iteration
-        if score <= alpha {
-            window_size = window_size * 2;
-        } else if score >= beta {
-            window_size = window_size * 2;
+        if score <= alpha || score >= beta {
+            window_size *= 2;
         } else {
             window_size = (window_size * 3) / 2;
         }
 
         depth += 1;
     }
-
-    // Print search information for this depth
-    println!("Search completed at depth {}:", depth);
-    println!("  Depth: {}", search_info.depth);
-    println!("  Selective depth: {}", search_info.selective_depth);
-    println!("  Nodes searched: {}", search_info.nodes);
-    println!("  Time elapsed: {:?}", search_info.time_elapsed());
-    println!("  Best score: {}", last_full_score);
-    println!("  Moves to go: {}", game_state.get_moves_to_go());
-    println!("  Principal Variation:");
-    for (i, mv) in last_full_pv.iter().enumerate() {
-        println!("    {}. {:?}", i + 1, mv);
-    }
-    if let Some(best_move) = last_full_best_move {
-        println!("  Best move: {:?}", best_move);
-    }
-
-    // Update moves_to_go in game state after finding the best move
-    game_state.decrement_moves_to_go();
-    last_full_best_move
+    result
 }
 
-/// Negamax search returning (score, PV moves from this node)
+/// Principal variation search returning (score, PV moves from this node)
 fn negamax(game_state: &mut GameState, depth: u8, mut alpha: i32, mut beta: i32, null_move: bool, search_info: &mut SearchInfo) -> (i32, Vec<Move>) {
     // Time check
     if search_info.should_stop() {
         search_info.stop = true;
         return (0, Vec::new());
     }
-    search_info.nodes += 1;
-    let current_ply = search_info.depth.saturating_sub(depth);
-    search_info.update_selective_depth(current_ply);
+    search_info.add_node();
+    let ply = search_info.ply;
+    search_info.update_selective_depth(ply);
+    if ply >= MAX_PLY {
+        return (static_eval(game_state, search_info), Vec::new());
+    }
 
     // Terminal checks
-    if game_state.is_checkmate() {
-        return (-MATE_SCORE + current_ply as i32, Vec::new());
-    }
-    if game_state.is_stalemate() {
-        return (DRAW_SCORE, Vec::new());
+    let mut legal_moves: Vec<Move> = Vec::with_capacity(256);
+    legal_move_gen::get_legal_moves(game_state.get_board_state(), game_state.get_pregen_attacks(), &mut legal_moves);
+    let side = game_state.get_board_state().get_side();
+    let in_check = game_state.is_check(side);
+    if legal_moves.is_empty() {
+        let score = if in_check {
+            -MATE_SCORE + ply as i32
+        } else {
+            search_info.draw_score(side)
+        };
+        return (score, Vec::new());
     }
-    if game_state.is_repetition_draw() {
-        return (DRAW_SCORE, Vec::new());
+    // The root is always searched so that a move is found
+    if ply > 0 && game_state.is_search_draw(ply as usize) {
+        return (search_info.draw_score(side), Vec::new());
     }
-    if game_state.half_move_clock() >= 100 {
-        return (DRAW_SCORE, Vec::new());
+
+    // Mate distance pruning: no line from here beats mating at the next move or being mated now,
+    // so stop if a shorter mate was already found closer to the root
+    if ply > 0 {
+        alpha = alpha.max(-MATE_SCORE + ply as i32);
+        beta = beta.min(MATE_SCORE - ply as i32 - 1);
+        if alpha >= beta {
+            return (alpha, Vec::new());
+        }
     }
+    let prune = !search_info.mate_search;
+    // Set while verifying a singular extension: this node is searched without that move
+    let excluded = search_info.excluded_moves[ply as usize];
 
     // Razor pruning
-    if depth == 1 && !game_state.is_check(game_state.get_board_state().get_side()) {
-        let stand_pat = game_state.get_board_state().evaluate(game_state.get_pregen_attacks());
+    if prune && depth == 1 && !in_check {
+        let stand_pat = static_eval(game_state, search_info);
         if stand_pat + RAZOR_MARGIN < alpha {
             return (alpha, Vec::new());
         }
@@ -249,10 +583,13 @@ fn negamax(game_state: &mut GameState, depth: u8, mut alpha: i32, mut beta: i32,
     let zobrist_key = game_state.get_board_state().get_zobrist_hash();
     let orig_alpha = alpha;
     let orig_beta = beta;
-    if let Some(entry) = game_state.tt_lookup(zobrist_key) {
-        let tt_score = entry.score;
-        let tt_depth = entry.depth;
-        let tt_entry_type = entry.entry_type;
+    let tt_entry = game_state.tt_lookup(zobrist_key);
+    let tt_move = tt_entry.and_then(|entry| entry.get_best_move());
+    // Never cut off at the root: the caller needs a best move. The entry is for the search with all moves.
+    if let Some(entry) = tt_entry.filter(|_| ply > 0 && excluded.is_none()) {
+        let tt_score = entry.get_score(ply);
+        let tt_depth = entry.get_depth();
+        let tt_entry_type = entry.get_entry_type();
         if tt_depth >= depth {
             match tt_entry_type {
                 EntryType::Exact => return (tt_score, Vec::new()),
@@ -270,13 +607,15 @@ fn negamax(game_state: &mut GameState, depth: u8, mut alpha: i32, mut beta: i32,
     }
 
     // Null move pruning
-    if !null_move
+    if prune
+        && !null_move
+        && excluded.is_none()
         && depth >= NULL_MOVE_DEPTH
         && game_state.get_board_state().get_num_pieces() > NULL_MOVE_PIECE_COUNT
-        && !game_state.is_check(game_state.get_board_state().get_side())
+        && !in_check
     {
         game_state.make_null_move();
-        search_info.ply += 1;
+        search_info.push_move(None);
         let (score, _) = negamax(game_state, depth - 1 - NULL_MOVE_REDUCTION, -beta, -alpha, true, search_info);
         search_info.ply -= 1;
         game_state.unmake_null_move();
@@ -290,81 +629,108 @@ fn negamax(game_state: &mut GameState, depth: u8, mut alpha: i32, mut beta: i32,
 
     // Leaf node: quiescence
     if depth == 0 {
-        let score = quiescence_search(game_state, alpha, beta, search_info);
+        let score = quiescence_search(game_state, alpha, beta, 0, search_info);
         return (score, Vec::new());
     }
 
-    // Generate moves
-    let mut pseudo_moves: Vec<Move> = Vec::with_capacity(256);
-    pseudo_move_gen::get_pseudo_moves(game_state.get_board_state(), game_state.get_pregen_attacks(), &mut pseudo_moves);
-    order_moves(&mut pseudo_moves);
+    // The TT move may be singular if its score is a reliable lower bound from a search nearly as deep as this one
+    let singular_candidate = tt_entry
+        .filter(|entry| {
+            ply > 0
+                && excluded.is_none()
+                && depth >= SINGULAR_MIN_DEPTH
+                && entry.get_entry_type() != EntryType::UpperBound
+                && entry.get_depth() + SINGULAR_TT_DEPTH_MARGIN >= depth
+                && entry.get_score(ply).abs() < MATE_SCORE - MAX_PLY as i32
+        })
+        .and_then(|entry| Some((entry.get_best_move()?, entry.get_score(ply))));
+    // Extensions are limited to twice the root depth so that lines of checks cannot grow without bound
+    let can_extend = ply < search_info.depth.saturating_mul(2);
+    let pv_node = alpha + 1 < beta;
+
+    let prev_move = search_info.get_previous_move();
+    let mut picker = MovePicker::new(legal_moves, tt_move, ply, prev_move, &search_info.ordering);
 
     let mut best_score = i32::MIN + 1;
+    let mut best_move = None;
     let mut best_pv: Vec<Move> = Vec::new();
     let mut moves_searched = 0;
-    let mut pv_found = false;
+    let mut quiets_tried: Vec<Move> = Vec::new();
 
-    for m in pseudo_moves {
+    while let Some(m) = picker.next(game_state.get_board_state(), game_state.get_pregen_attacks(), &search_info.ordering) {
         if search_info.should_stop() {
             search_info.stop = true;
             break;
         }
+        if Some(m) == excluded {
+            continue;
+        }
 
-        // Delta pruning
-        if depth == 1 && !pv_found {
-            let captured_value = m.get_capture().map_or(0, |p| p.get_value());
-            if captured_value + DELTA_MARGIN < alpha {
-                continue;
+        // Singular extension: search the other moves with a reduced depth and a window below the TT score.
+        // If they all fail low, the TT move is the only good one and is extended; if they still beat beta,
+        // several moves refute the opponent's last move and the node is cut (multi-cut).
+        let mut extension = 0;
+        if let Some((singular_move, tt_score)) = singular_candidate.filter(|&(tt_move, _)| tt_move == m) {
+            let singular_beta = tt_score - SINGULAR_MARGIN * depth as i32;
+            search_info.excluded_moves[ply as usize] = Some(singular_move);
+            let (score, _) = negamax(game_state, (depth - 1) / 2, singular_beta - 1, singular_beta, null_move, search_info);
+            search_info.excluded_moves[ply as usize] = None;
+            if search_info.stop {
+                break;
+            }
+            if score < singular_beta {
+                extension = u8::from(can_extend);
+            } else if singular_beta >= beta {
+                return (singular_beta, Vec::new());
             }
         }
 
+        // Decided before the move is made, so that the exchange is only evaluated for checking moves
+        let gives_check = game_state.get_board_state().gives_check(m, game_state.get_pregen_attacks());
+        let safe_check = gives_check && can_extend && extension == 0 && game_state.get_board_state().see(m, 0, game_state.get_pregen_attacks());
         game_state.make_move(m);
-        search_info.ply += 1;
-
-        // Skip illegal moves
-        if game_state.is_check(game_state.get_board_state().get_opposite_side()) {
-            search_info.ply -= 1;
-            game_state.unmake_move();
-            continue;
+        search_info.push_move(Some(m));
+
+        // Extend checks that do not lose material, recaptures, and pawn pushes to the seventh rank, where the pawn is always passed
+        if extension == 0 && can_extend {
+            let recapture = m.is_capture() && prev_move.is_some_and(|prev| prev.is_capture() && prev.get_to() == m.get_to());
+            let seventh_rank = if side == Color::White { Rank::R7 } else { Rank::R2 };
+            let pawn_push = m.get_piece().is_pawn() && m.get_to().get_rank() == seventh_rank;
+            extension = u8::from(safe_check || recapture || pawn_push);
         }
+        let new_depth = depth - 1 + extension;
 
-        let (score, child_pv) = if !pv_found {
-            // full window first
-            let (s, pv) = negamax(game_state, depth - 1, -beta, -alpha, false, search_info);
-            (s, pv)
+        let (score, child_pv) = if moves_searched == 0 {
+            // The first move is expected to be the best and gets the full window
+            let (s, pv) = negamax(game_state, new_depth, -beta, -alpha, false, search_info);
+            (-s, pv)
         } else {
-            // Late Move Reduction with improved conditions
-            let reduced_depth = if depth >= LMR_MIN_DEPTH
-                && moves_searched >= LMR_MIN_MOVE
-                && !m.is_capture()
-                && !game_state.is_check(game_state.get_board_state().get_side())
-            {
-                depth.saturating_sub(2)
+            // Late move reduction for quiet moves that are unlikely to be good, less in PV nodes
+            let reduction = if prune && depth >= LMR_MIN_DEPTH && moves_searched >= LMR_MIN_MOVE && m.is_quiet() && !in_check && !gives_check {
+                let reduction = lmr_reduction(depth, moves_searched).saturating_sub(u8::from(pv_node));
+                reduction.min(new_depth - 1)
             } else {
-                depth - 1
+                0
             };
-            let (s_reduced, pv_reduced) = negamax(game_state, reduced_depth, -(alpha + 1), -alpha, false, search_info);
-            if search_info.stop {
-                search_info.ply -= 1;
-                game_state.unmake_move();
-                break;
+
+            // Later moves only need to be proven worse than alpha, which a null window search does cheaply
+            let (s, mut pv) = negamax(game_state, new_depth - reduction, -(alpha + 1), -alpha, false, search_info);
+            let mut score = -s;
+            // A reduced move that beats alpha is searched again at full depth
+            if !search_info.stop && score > alpha && reduction > 0 {
+                let (s, full_pv) = negamax(game_state, new_depth, -(alpha + 1), -alpha, false, search_info);
+                score = -s;
+                pv = full_pv;
             }
-            let mut s = s_reduced;
-            let mut pv = pv_reduced;
-            if s > alpha && reduced_depth < depth - 1 {
-                let (s_full, pv_full) = negamax(game_state, depth - 1, -beta, -alpha, false, search_info);
-                if search_info.stop {
-                    search_info.ply -= 1;
-                    game_state.unmake_move();
-                    break;
-                }
-                s = s_full;
-                pv = pv_full;
+            // A move that lands inside the window gets the full window for its exact score
+            if !search_info.stop && score > alpha && score < beta {
+                let (s, full_pv) = negamax(game_state, new_depth, -beta, -alpha, false, search_info);
+                score = -s;
+                pv = full_pv;
             }
-            (s, pv)
+            (score, pv)
         };
 
-        let score = -score;
         search_info.ply -= 1;
         game_state.unmake_move();
         if search_info.stop {
@@ -373,32 +739,35 @@ fn negamax(game_state: &mut GameState, depth: u8, mut alpha: i32, mut beta: i32,
 
         if score > best_score {
             best_score = score;
+            best_move = Some(m);
             // Update alpha and PV
             if score > alpha {
                 alpha = score;
-                pv_found = true;
                 // Build PV: current move + child PV
                 best_pv.clear();
                 best_pv.push(m);
-                // child_pv contains moves from child node; append
-                for &mv in &child_pv {
-                    best_pv.push(mv);
-                }
+                best_pv.extend_from_slice(&child_pv);
                 // If at root, update global best_move
-                if current_ply == 0 {
+                if ply == 0 {
                     search_info.best_move = Some(m);
                     search_info.best_score = score;
                 }
             }
         }
         if alpha >= beta {
+            if m.is_quiet() {
+                search_info.ordering.update_quiet_stats(ply, depth, prev_move, m, &quiets_tried);
+            }
             break;
         }
+        if m.is_quiet() {
+            quiets_tried.push(m);
+        }
         moves_searched += 1;
     }
 
-    // Store in TT using new interface
-    if !search_info.stop {
+    // Store in TT, unless every move was pruned or a move was left out
+    if !search_info.stop && best_move.is_some() && excluded.is_none() {
         let entry_type = if best_score <= orig_alpha {
             EntryType::UpperBound
         } else if best_score >= orig_beta {
@@ -406,79 +775,162 @@ fn negamax(game_state: &mut GameState, depth: u8, mut alpha: i32, mut beta: i32,
         } else {
             EntryType::Exact
         };
-        let entry = TTEntry {
-            score: best_score,
-            depth,
-            entry_type,
-        };
-        game_state.tt_insert(zobrist_key, entry);
+        // No move raised alpha at an upper bound node, so there is no best move worth keeping
+        let hash_move = if entry_type == EntryType::UpperBound { None } else { best_move };
+        game_state.tt_insert(zobrist_key, TTEntry::new(hash_move, best_score, depth, entry_type, ply));
     }
 
     (alpha, best_pv)
 }
 
-/// Quiescence search returning a static score. PV not tracked here.
-fn quiescence_search(game_state: &mut GameState, mut alpha: i32, beta: i32, search_info: &mut SearchInfo) -> i32 {
+/// Late move reduction in plies for the `moves_searched`-th move at `depth`
+fn lmr_reduction(depth: u8, moves_searched: usize) -> u8 {
+    static TABLE: OnceLock<[[u8; 64]; 64]> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        let mut table = [[0; 64]; 64];
+        for (depth, row) in table.iter_mut().enumerate().skip(1) {
+            for (moves, reduction) in row.iter_mut().enumerate().skip(1) {
+                *reduction = (LMR_BASE + (depth as f64).ln() * (moves as f64).ln() / LMR_DIVISOR) as u8;
+            }
+        }
+        table
+    });
+    table[(depth as usize).min(63)][moves_searched.min(63)]
+}
+
+/// Quiescence search, `qs_ply` plies below the main search, returning a score once the position is quiet.
+/// Searches captures and queen promotions that do not lose material, and quiet checks at its first plies.
+/// In check every evasion is searched, as standing pat is not an option. PV not tracked here.
+fn quiescence_search(game_state: &mut GameState, mut alpha: i32, beta: i32, qs_ply: u8, search_info: &mut SearchInfo) -> i32 {
     if search_info.should_stop() {
         search_info.stop = true;
         return alpha;
     }
-    search_info.nodes += 1;
-    // Optionally update selective depth deeper
-    search_info.update_selective_depth(search_info.depth);
-
-    let stand_pat = game_state.get_board_state().evaluate(game_state.get_pregen_attacks());
-    if stand_pat >= beta {
-        return beta;
+    search_info.add_node();
+    let ply = search_info.ply;
+    search_info.update_selective_depth(ply);
+    if ply >= MAX_PLY || qs_ply >= QUIESCENCE_DEPTH {
+        return static_eval(game_state, search_info).clamp(alpha, beta);
     }
-    if stand_pat > alpha {
-        alpha = stand_pat;
+
+    let mut legal_moves: Vec<Move> = Vec::with_capacity(256);
+    legal_move_gen::get_legal_moves(game_state.get_board_state(), game_state.get_pregen_attacks(), &mut legal_moves);
+    let side = game_state.get_board_state().get_side();
+    let in_check = game_state.is_check(side);
+    if legal_moves.is_empty() {
+        let score = if in_check {
+            -MATE_SCORE + ply as i32
+        } else {
+            search_info.draw_score(side)
+        };
+        return score.clamp(alpha, beta);
     }
 
-    // Add depth check
-    if search_info.ply >= QUIESCENCE_DEPTH {
-        return alpha;
+    // Any entry is at least as deep as quiescence search
+    let zobrist_key = game_state.get_board_state().get_zobrist_hash();
+    let tt_entry = game_state.tt_lookup(zobrist_key);
+    if let Some(entry) = tt_entry {
+        let tt_score = entry.get_score(ply);
+        match entry.get_entry_type() {
+            EntryType::Exact => return tt_score.clamp(alpha, beta),
+            EntryType::LowerBound if tt_score >= beta => return beta,
+            EntryType::UpperBound if tt_score <= alpha => return alpha,
+            _ => {}
+        }
     }
+    let orig_alpha = alpha;
 
-    let mut pseudo_moves: Vec<Move> = Vec::with_capacity(256);
-    pseudo_move_gen::get_pseudo_moves(game_state.get_board_state(), game_state.get_pregen_attacks(), &mut pseudo_moves);
-    // Only captures
-    pseudo_moves.retain(|m| m.is_capture());
-    order_moves(&mut pseudo_moves);
+    let mut stand_pat = None;
+    let mut quiet_checks = Vec::new();
+    let mut picker = if in_check {
+        let prev_move = search_info.get_previous_move();
+        MovePicker::new(
+            legal_moves,
+            tt_entry.and_then(|entry| entry.get_best_move()),
+            ply,
+            prev_move,
+            &search_info.ordering,
+        )
+    } else {
+        let eval = static_eval(game_state, search_info);
+        if eval >= beta {
+            return beta;
+        }
+        alpha = alpha.max(eval);
+        stand_pat = Some(eval);
 
-    for m in pseudo_moves {
+        let (noisy, quiets): (Vec<Move>, Vec<Move>) = legal_moves.into_iter().partition(|m| !m.is_quiet());
+        if qs_ply < QUIESCENCE_CHECK_PLIES {
+            quiet_checks = quiets;
+        }
+        // Under-promotions are left to the main search; the picker drops captures that lose material
+        MovePicker::new_noisy(noisy.into_iter().filter(|m| m.get_promotion().is_none_or(|p| p.is_queen())).collect())
+    };
+    let mut quiet_checks = quiet_checks.into_iter();
+
+    let mut best_move = None;
+    while let Some(m) = picker
+        .next(game_state.get_board_state(), game_state.get_pregen_attacks(), &search_info.ordering)
+        .or_else(|| quiet_checks.next())
+    {
         if search_info.should_stop() {
             search_info.stop = true;
             break;
         }
-        let captured_value = m.get_capture().map_or(0, |p| p.get_value());
-        if stand_pat + captured_value + FUTILITY_MARGIN < alpha {
-            continue;
+        if let Some(stand_pat) = stand_pat {
+            if m.is_quiet() {
+                // Quiet checks that lose material are unlikely to lead anywhere
+                if !game_state.get_board_state().gives_check(m, game_state.get_pregen_attacks())
+                    || !game_state.get_board_state().see(m, 0, game_state.get_pregen_attacks())
+                {
+                    continue;
+                }
+            } else {
+                // Delta pruning: even winning the captured piece and promoting cannot raise alpha
+                let board_state = game_state.get_board_state();
+                let captured_value = m.get_capture().map_or(0, |p| board_state.get_piece_value(p.get_type()));
+                let promotion_value = m.get_promotion().map_or(0, |p| {
+                    board_state.get_piece_value(p.get_type()) - board_state.get_piece_value(PieceType::Pawn)
+                });
+                if stand_pat + captured_value + promotion_value + FUTILITY_MARGIN < alpha {
+                    continue;
+                }
+            }
         }
+
         game_state.make_move(m);
-        search_info.ply += 1;
-        if game_state.is_check(game_state.get_board_state().get_opposite_side()) {
-            search_info.ply -= 1;
-            game_state.unmake_move();
-            continue;
-        }
-        let score = -quiescence_search(game_state, -beta, -alpha, search_info);
+        search_info.push_move(Some(m));
+        let score = -quiescence_search(game_state, -beta, -alpha, qs_ply + 1, search_info);
         search_info.ply -= 1;
         game_state.unmake_move();
         if search_info.stop {
             break;
         }
         if score >= beta {
+            game_state.tt_insert(zobrist_key, TTEntry::new(Some(m), score, 0, EntryType::LowerBound, ply));
             return beta;
         }
         if score > alpha {
             alpha = score;
+            best_move = Some(m);
         }
     }
+
+    if !search_info.stop {
+        // Not every quiet move was searched, so a score inside the window (possibly the stand pat) is only a lower bound.
+        // Never storing exact scores keeps them from ending the main search at nodes of depth 0.
+        let entry = if alpha > orig_alpha {
+            TTEntry::new(best_move, alpha, 0, EntryType::LowerBound, ply)
+        } else {
+            TTEntry::new(None, alpha, 0, EntryType::UpperBound, ply)
+        };
+        game_state.tt_insert(zobrist_key, entry);
+    }
     alpha
 }
 
-/// Simple move ordering by stored move scores; can be extended with history, killer heuristics, etc.
-pub fn order_moves(moves: &mut Vec<Move>) {
-    moves.sort_by(|a, b| b.get_score().cmp(&a.get_score()));
+/// Static evaluation from the side to move's point of view (`evaluate` is from White's)
+fn static_eval(game_state: &GameState, search_info: &mut SearchInfo) -> i32 {
+    let board_state = game_state.get_board_state();
+    board_state.get_side().get_factor() * board_state.evaluate_cached(game_state.get_pregen_attacks(), &mut search_info.pawn_table)
 }
@@ -0,0 +1,306 @@
+use crate::core::{attack_pregen::PregenAttacks, board_state::BoardState, piece::Piece};
+
+use super::{move_encode::Move, move_eval::MAX_PLY};
+
+/// History scores are kept within +-MAX_HISTORY so recent results outweigh old ones
+const MAX_HISTORY: i32 = 16_384;
+/// Largest history bonus for a single cutoff
+const MAX_HISTORY_BONUS: i32 = 1_200;
+/// Captures and promotions are scored above every quiet move
+const NOISY_MOVE_BONUS: i32 = 1_000_000;
+
+/// Move ordering statistics collected during one search
+#[derive(Clone, Debug)]
+pub struct OrderingTables {
+    /// Two quiet moves per ply that caused a beta cutoff
+    killers: [[Option<Move>; 2]; MAX_PLY as usize + 1],
+    /// Butterfly history indexed by moving piece and destination
+    history: [[i32; 64]; 12],
+    /// Quiet reply that refuted each previous move, indexed by its piece and destination
+    countermoves: [[Option<Move>; 64]; 12],
+    /// History of quiet moves as replies to the previous move: [previous piece][previous to][piece][to]
+    continuation_history: Box<[[[[i32; 64]; 12]; 64]; 12]>,
+}
+
+impl Default for OrderingTables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderingTables {
+    pub fn new() -> Self {
+        Self {
+            killers: [[None; 2]; MAX_PLY as usize + 1],
+            history: [[0; 64]; 12],
+            countermoves: [[None; 64]; 12],
+            continuation_history: vec![[[[0; 64]; 12]; 64]; 12].into_boxed_slice().try_into().unwrap(),
+        }
+    }
+
+    /// Returns the killer moves stored for `ply`
+    #[inline(always)]
+    pub fn get_killers(&self, ply: u8) -> [Option<Move>; 2] {
+        self.killers.get(ply as usize).copied().unwrap_or([None; 2])
+    }
+
+    /// Returns the stored refutation of `prev_move`
+    #[inline(always)]
+    pub fn get_countermove(&self, prev_move: Option<Move>) -> Option<Move> {
+        prev_move.and_then(|prev| self.countermoves[prev.get_piece()][prev.get_to()])
+    }
+
+    /// History score of a quiet move played in reply to `prev_move`
+    #[inline(always)]
+    pub fn quiet_score(&self, m: Move, prev_move: Option<Move>) -> i32 {
+        let (piece, to) = (m.get_piece(), m.get_to());
+        let continuation = prev_move.map_or(0, |prev| self.continuation_history[prev.get_piece()][prev.get_to()][piece][to]);
+        self.history[piece][to] + continuation
+    }
+
+    /// Rewards the quiet move `best` that caused a beta cutoff and penalizes the quiet moves searched before it
+    pub fn update_quiet_stats(&mut self, ply: u8, depth: u8, prev_move: Option<Move>, best: Move, quiets_tried: &[Move]) {
+        if let Some(killers) = self.killers.get_mut(ply as usize) {
+            if killers[0] != Some(best) {
+                killers[1] = killers[0];
+                killers[0] = Some(best);
+            }
+        }
+        if let Some(prev) = prev_move {
+            self.countermoves[prev.get_piece()][prev.get_to()] = Some(best);
+        }
+
+        let bonus = (depth as i32 * depth as i32).min(MAX_HISTORY_BONUS);
+        self.update_history(prev_move, best, bonus);
+        for &m in quiets_tried.iter().filter(|&&m| m != best) {
+            self.update_history(prev_move, m, -bonus);
+        }
+    }
+
+    fn update_history(&mut self, prev_move: Option<Move>, m: Move, bonus: i32) {
+        let (piece, to) = (m.get_piece(), m.get_to());
+        apply_bonus(&mut self.history[piece][to], bonus);
+        if let Some(prev) = prev_move {
+            apply_bonus(&mut self.continuation_history[prev.get_piece()][prev.get_to()][piece][to], bonus);
+        }
+    }
+}
+
+/// Moves the entry towards the bonus, shrinking it as it approaches MAX_HISTORY
+#[inline(always)]
+fn apply_bonus(entry: &mut i32, bonus: i32) {
+    *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+}
+
+/// Most valuable victim, least valuable attacker score for captures, plus the value of any promotion
+#[inline(always)]
+pub fn mvv_lva(m: Move, board_state: &BoardState) -> i32 {
+    let value = |p: Piece| board_state.get_piece_value(p.get_type());
+    let victim = m.get_capture().map_or(0, |p| value(p) * 10 - value(m.get_piece()));
+    victim + m.get_promotion().map_or(0, value)
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Stage {
+    HashMove,
+    ScoreNoisy,
+    GoodNoisy,
+    Killers,
+    ScoreQuiets,
+    Quiets,
+    BadNoisy,
+}
+
+/// Hands out moves in stages so that a cutoff found early saves scoring and sorting the rest:
+/// the hash move, winning captures and promotions by MVV-LVA, killers and the countermove, quiets by history,
+/// and finally the captures that lose material by static exchange evaluation.
+pub struct MovePicker {
+    stage: Stage,
+    hash_move: Option<Move>,
+    /// Killer moves followed by the countermove
+    refutations: [Option<Move>; 3],
+    refutation_index: usize,
+    noisy: Vec<(Move, i32)>,
+    /// Captures that lose material, in the order they were found
+    bad_noisy: Vec<Move>,
+    /// Losing captures are dropped rather than searched last
+    skip_bad_noisy: bool,
+    quiets: Vec<(Move, i32)>,
+    prev_move: Option<Move>,
+}
+
+impl MovePicker {
+    /// Creates a picker over `moves`, which must all be legal in the current position
+    pub fn new(moves: Vec<Move>, hash_move: Option<Move>, ply: u8, prev_move: Option<Move>, tables: &OrderingTables) -> Self {
+        let hash_move = hash_move.filter(|hm| moves.contains(hm));
+        let (noisy, quiets): (Vec<Move>, Vec<Move>) = moves.into_iter().filter(|&m| Some(m) != hash_move).partition(|m| !m.is_quiet());
+        let [killer_1, killer_2] = tables.get_killers(ply);
+
+        Self {
+            stage: Stage::HashMove,
+            hash_move,
+            refutations: [killer_1, killer_2, tables.get_countermove(prev_move)],
+            refutation_index: 0,
+            noisy: noisy.into_iter().map(|m| (m, 0)).collect(),
+            bad_noisy: Vec::new(),
+            skip_bad_noisy: false,
+            quiets: quiets.into_iter().map(|m| (m, 0)).collect(),
+            prev_move,
+        }
+    }
+
+    /// Creates a picker that returns only the given captures and promotions that do not lose material
+    pub fn new_noisy(moves: Vec<Move>) -> Self {
+        Self {
+            stage: Stage::ScoreNoisy,
+            hash_move: None,
+            refutations: [None; 3],
+            refutation_index: 0,
+            noisy: moves.into_iter().filter(|m| !m.is_quiet()).map(|m| (m, 0)).collect(),
+            bad_noisy: Vec::new(),
+            skip_bad_noisy: true,
+            quiets: Vec::new(),
+            prev_move: None,
+        }
+    }
+
+    /// Returns the next move to search, or None when all moves have been returned
+    pub fn next(&mut self, board_state: &BoardState, pregen_attacks: &PregenAttacks, tables: &OrderingTables) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::ScoreNoisy;
+                    if self.hash_move.is_some() {
+                        return self.hash_move;
+                    }
+                }
+                Stage::ScoreNoisy => {
+                    for entry in self.noisy.iter_mut() {
+                        entry.1 = NOISY_MOVE_BONUS + mvv_lva(entry.0, board_state);
+                    }
+                    self.stage = Stage::GoodNoisy;
+                }
+                Stage::GoodNoisy => match pick_best(&mut self.noisy) {
+                    Some(m) if board_state.see(m, 0, pregen_attacks) => return Some(m),
+                    Some(m) => {
+                        if !self.skip_bad_noisy {
+                            self.bad_noisy.push(m);
+                        }
+                    }
+                    None => self.stage = Stage::Killers,
+                },
+                Stage::Killers => {
+                    while self.refutation_index < self.refutations.len() {
+                        let refutation = self.refutations[self.refutation_index];
+                        self.refutation_index += 1;
+                        // Killers come from other positions at this ply, so they are only used if still available here
+                        if let Some(index) = refutation.and_then(|r| self.quiets.iter().position(|&(m, _)| m == r)) {
+                            return Some(self.quiets.swap_remove(index).0);
+                        }
+                    }
+                    self.stage = Stage::ScoreQuiets;
+                }
+                Stage::ScoreQuiets => {
+                    for entry in self.quiets.iter_mut() {
+                        entry.1 = tables.quiet_score(entry.0, self.prev_move);
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => match pick_best(&mut self.quiets) {
+                    Some(m) => return Some(m),
+                    None => {
+                        self.bad_noisy.reverse();
+                        self.stage = Stage::BadNoisy;
+                    }
+                },
+                Stage::BadNoisy => return self.bad_noisy.pop(),
+            }
+        }
+    }
+}
+
+/// Removes and returns the highest scored move, keeping the order of the others irrelevant
+#[inline(always)]
+fn pick_best(moves: &mut Vec<(Move, i32)>) -> Option<Move> {
+    let (index, _) = moves.iter().enumerate().max_by_key(|(_, &(_, score))| score)?;
+    Some(moves.swap_remove(index).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::square::Square;
+    use crate::move_logic::test_position::Position;
+
+    impl Position {
+        fn picked(&self, mut picker: MovePicker, tables: &OrderingTables) -> Vec<String> {
+            std::iter::from_fn(|| picker.next(&self.board_state, &self.pregen_attacks, tables))
+                .map(|m| m.to_string())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_stage_order() {
+        // White can capture the queen with the pawn or the rook
+        let position = Position::new("4k3/8/8/3q4/4P3/8/8/3RK3 w - - 0 1");
+        let mut tables = OrderingTables::new();
+        let killer = position.find("e1f2");
+        let hash_move = position.find("e4e5");
+        tables.update_quiet_stats(2, 4, None, killer, &[]);
+
+        let order = position.picked(MovePicker::new(position.moves.clone(), Some(hash_move), 2, None, &tables), &tables);
+        assert_eq!(order.len(), position.moves.len());
+        assert_eq!(&order[..4], ["e4e5", "e4d5", "d1d5", "e1f2"]);
+    }
+
+    #[test]
+    fn test_losing_captures_last() {
+        // The pawn on d5 is defended, so taking it with the queen loses material
+        let position = Position::new("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1");
+        let tables = OrderingTables::new();
+
+        let order = position.picked(MovePicker::new(position.moves.clone(), None, 0, None, &tables), &tables);
+        assert_eq!(order.len(), position.moves.len());
+        assert_eq!(order.last().map(String::as_str), Some("d1d5"));
+
+        let captures = position.picked(MovePicker::new_noisy(position.moves.clone()), &tables);
+        assert!(captures.is_empty());
+    }
+
+    #[test]
+    fn test_history_orders_quiets() {
+        let position = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let mut tables = OrderingTables::new();
+        let good = position.find("g1f3");
+        let bad = position.find("a2a3");
+        // Killers are stored for ply 0, so search from ply 1 to see the history order alone
+        tables.update_quiet_stats(0, 6, None, good, &[bad]);
+
+        let order = position.picked(MovePicker::new(position.moves.clone(), None, 1, None, &tables), &tables);
+        assert_eq!(order.first().map(String::as_str), Some("g1f3"));
+        assert_eq!(order.last().map(String::as_str), Some("a2a3"));
+    }
+
+    #[test]
+    fn test_countermove() {
+        let position = Position::new("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        let prev = Move::new(Square::E2, Square::E4, Piece::WPawn, None, None, true, false, false);
+        let mut tables = OrderingTables::new();
+        let reply = position.find("c7c5");
+        tables.update_quiet_stats(0, 1, Some(prev), reply, &[]);
+        assert_eq!(tables.get_countermove(Some(prev)), Some(reply));
+
+        let order = position.picked(MovePicker::new(position.moves.clone(), None, 1, Some(prev), &tables), &tables);
+        assert_eq!(order.first().map(String::as_str), Some("c7c5"));
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut entry = 0;
+        for _ in 0..1000 {
+            apply_bonus(&mut entry, MAX_HISTORY_BONUS);
+        }
+        assert!(entry <= MAX_HISTORY);
+    }
+}
@@ -0,0 +1,150 @@
+use std::time::{Duration, Instant};
+
+use crate::game_logic::game::GameState;
+
+use super::{legal_move_gen, move_encode::Move};
+
+/// Counts the leaf nodes of the legal move tree of the given depth
+pub fn perft(game_state: &mut GameState, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = legal_moves(game_state);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for m in moves {
+        game_state.make_move(m);
+        nodes += perft(game_state, depth - 1);
+        game_state.unmake_move();
+    }
+    nodes
+}
+
+/// Runs perft for each legal move of the position, returning the node count below every root move
+pub fn divide(game_state: &mut GameState, depth: u8) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    let mut results = Vec::new();
+    for m in legal_moves(game_state) {
+        game_state.make_move(m);
+        results.push((m, perft(game_state, depth - 1)));
+        game_state.unmake_move();
+    }
+    results
+}
+
+/// Runs divide and prints the per-move counts followed by the total, node count and speed
+pub fn print_divide(game_state: &mut GameState, depth: u8) -> u64 {
+    let start = Instant::now();
+    let results = divide(game_state, depth);
+    let elapsed = start.elapsed();
+
+    for (m, nodes) in &results {
+        println!("{}: {}", m, nodes);
+    }
+    let total: u64 = results.iter().map(|(_, nodes)| nodes).sum();
+    println!();
+    println!("Moves: {}", results.len());
+    println!("Nodes: {}", total);
+    println!("Time: {:?} ({} nps)", elapsed, nodes_per_second(total, elapsed));
+    total
+}
+
+fn legal_moves(game_state: &GameState) -> Vec<Move> {
+    let mut moves = Vec::with_capacity(256);
+    legal_move_gen::get_legal_moves(game_state.get_board_state(), game_state.get_pregen_attacks(), &mut moves);
+    moves
+}
+
+fn nodes_per_second(nodes: u64, elapsed: Duration) -> u64 {
+    (nodes as u128 * 1000 / elapsed.as_millis().max(1)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Node budget for the suite run by `cargo test`; deeper counts run with `cargo test --release -- --ignored`
+    const QUICK_NODE_LIMIT: u64 = 100_000;
+
+    /// Reference positions with known node counts for depths 1, 2, 3, ...
+    #[rustfmt::skip]
+    const PERFT_SUITE: [(&str, &[u64]); 20] = [
+        // Standard positions
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902, 197281, 4865609]),
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862, 4085603]),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238, 674624]),
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467, 422333]),
+        ("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", &[6, 264, 9467, 422333]),
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379, 2103487]),
+        ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890, 3894594]),
+        // En passant: illegal because of a discovered check, or giving check
+        ("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", &[18, 92, 1670, 10138, 185429, 1134888]),
+        ("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", &[13, 102, 1266, 10276, 135655, 1015133]),
+        ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", &[15, 126, 1928, 13931, 206379, 1440467]),
+        // Castling: giving check, losing rights, and prevented by attacked squares
+        ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", &[15, 66, 1198, 6399, 120330, 661072]),
+        ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", &[16, 71, 1286, 7418, 141077, 803711]),
+        ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", &[26, 1141, 27826, 1274206]),
+        ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", &[44, 1494, 50509, 1720476]),
+        // Promotions: out of check, giving check and under-promotion
+        ("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", &[11, 133, 1442, 19174, 266199, 3821001]),
+        ("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", &[9, 40, 472, 2661, 38983, 217342]),
+        ("8/P1k5/K7/8/8/8/8/8 w - - 0 1", &[6, 27, 273, 1329, 18135, 92683]),
+        // Discovered check, self-stalemate, and stalemate/checkmate
+        ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", &[29, 165, 5160, 31961, 1004658]),
+        ("K1k5/8/P7/8/8/8/8/8 w - - 0 1", &[2, 6, 13, 63, 382, 2217]),
+        ("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", &[37, 183, 6559, 23527]),
+    ];
+
+    fn run_suite(node_limit: u64) {
+        let mut game_state = GameState::new(None, Duration::ZERO);
+        for (fen, counts) in PERFT_SUITE {
+            game_state.set_position(Some(fen)).unwrap();
+            for (depth, &expected) in counts.iter().enumerate().filter(|(_, &n)| n <= node_limit) {
+                let depth = depth as u8 + 1;
+                assert_eq!(perft(&mut game_state, depth), expected, "perft({}) of {}", depth, fen);
+            }
+        }
+    }
+
+    #[test]
+    fn test_perft_suite() {
+        run_suite(QUICK_NODE_LIMIT);
+    }
+
+    #[test]
+    #[ignore = "slow; run with `cargo test --release -- --ignored`"]
+    fn test_perft_suite_full() {
+        run_suite(u64::MAX);
+    }
+
+    #[test]
+    fn test_perft_leaves_position_unchanged() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut game_state = GameState::new(Some(fen), Duration::ZERO);
+        let before = game_state.get_board_state().clone();
+        perft(&mut game_state, 2);
+        assert_eq!(game_state.get_board_state(), &before);
+        assert_eq!(game_state.get_history_len(), 0);
+    }
+
+    #[test]
+    fn test_divide_sums_to_perft() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut game_state = GameState::new(Some(fen), Duration::ZERO);
+        let results = divide(&mut game_state, 2);
+        assert_eq!(results.len(), 48);
+        assert_eq!(results.iter().map(|(_, n)| n).sum::<u64>(), 2039);
+
+        let castles: Vec<String> = results.iter().filter(|(m, _)| m.is_castling()).map(|(m, _)| m.to_string()).collect();
+        assert_eq!(castles.len(), 2);
+        assert!(castles.contains(&"e1g1".to_string()) && castles.contains(&"e1c1".to_string()));
+    }
+}
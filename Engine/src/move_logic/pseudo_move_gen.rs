@@ -281,25 +281,34 @@ fn gen_king_moves(board_state: &BoardState, pregen_attacks: &PregenAttacks, move
     // Handle castling
     let castle_rights = board_state.get_castling_rights();
     let castle_shift = 2 * side as u8;
+    let can_castle_ks = castle_rights & ((CastlePerms::WKC as u8) << castle_shift) != 0;
+    let can_castle_qs = castle_rights & ((CastlePerms::WQC as u8) << castle_shift) != 0;
+    if !can_castle_ks && !can_castle_qs {
+        return;
+    }
 
-    // King-side castling
-    if castle_rights & ((CastlePerms::WKC as u8) << castle_shift) != 0 {
-        let king_ray = pregen_attacks.get_rook_attacks(king_sq, &board_state.get_combined_bb());
-        let rook_ks = board_state.get_piece_bb(Piece::new(side, PieceType::Rook)).intersect(FILE_H_BB);
+    // The king may not castle out of or through check; the destination square is left to the legality check
+    let enemy = side.opposite();
+    if board_state.is_square_attacked(king_sq, enemy, pregen_attacks) {
+        return;
+    }
+    let king_ray = pregen_attacks.get_rook_attacks(king_sq, &board_state.get_combined_bb());
+    let rooks = board_state.get_piece_bb(Piece::new(side, PieceType::Rook));
 
-        if !king_ray.intersect(rook_ks).is_empty() && !board_state.is_check(side, pregen_attacks) {
-            moves.push(Move::new(king_sq, king_sq.move_right(2), piece, None, None, false, false, true));
-        }
+    // King-side castling
+    if can_castle_ks
+        && !king_ray.intersect(rooks.intersect(FILE_H_BB)).is_empty()
+        && !board_state.is_square_attacked(king_sq.move_right(1), enemy, pregen_attacks)
+    {
+        moves.push(Move::new(king_sq, king_sq.move_right(2), piece, None, None, false, false, true));
     }
 
     // Queen-side castling
-    if castle_rights & ((CastlePerms::WQC as u8) << castle_shift) != 0 {
-        let king_ray = pregen_attacks.get_rook_attacks(king_sq, &board_state.get_combined_bb());
-        let rook_qs = board_state.get_piece_bb(Piece::new(side, PieceType::Rook)).intersect(FILE_A_BB);
-
-        if !king_ray.intersect(rook_qs).is_empty() && !board_state.is_check(side, pregen_attacks) {
-            moves.push(Move::new(king_sq, king_sq.move_left(2), piece, None, None, false, false, true));
-        }
+    if can_castle_qs
+        && !king_ray.intersect(rooks.intersect(FILE_A_BB)).is_empty()
+        && !board_state.is_square_attacked(king_sq.move_left(1), enemy, pregen_attacks)
+    {
+        moves.push(Move::new(king_sq, king_sq.move_left(2), piece, None, None, false, false, true));
     }
 }
 
@@ -326,27 +335,27 @@ mod tests {
     #[test]
     fn test_pawn_moves() {
         // Test pawn pushes
-        let (board_state, pregen_attacks) = setup_board("8/8/8/8/8/8/PPPPPPPP/8 w - - 0 1");
+        let (board_state, pregen_attacks) = setup_board("7K/8/8/8/8/8/PPPPPPPP/8 w - - 0 1");
         let mut moves = Vec::new();
         get_pseudo_moves(&board_state, &pregen_attacks, &mut moves);
-        assert_eq!(moves.len(), 16); // 8 pawns * 2 moves each
+        assert_eq!(moves.len(), 19); // 8 pawns * 2 moves each, and 3 king moves
 
         // Test pawn captures
-        let (board_state, pregen_attacks) = setup_board("8/8/8/8/8/1p6/1P6/8 w - - 0 1");
+        let (board_state, pregen_attacks) = setup_board("8/8/8/8/1n6/p1p5/1P6/7K w - - 0 1");
         let mut moves = Vec::new();
         get_pseudo_moves(&board_state, &pregen_attacks, &mut moves);
-        assert_eq!(moves.len(), 3); // One push and two captures
+        assert_eq!(moves.len(), 6); // One push and two captures, and 3 king moves
 
         // Test pawn promotions
-        let (board_state, pregen_attacks) = setup_board("8/P7/8/8/8/8/8/8 w - - 0 1");
+        let (board_state, pregen_attacks) = setup_board("8/P7/8/8/8/8/8/7K w - - 0 1");
         let mut moves = Vec::new();
         get_pseudo_moves(&board_state, &pregen_attacks, &mut moves);
-        assert_eq!(moves.len(), 4); // Four promotion options
+        assert_eq!(moves.len(), 7); // Four promotion options, and 3 king moves
     }
 
     #[test]
     fn test_en_passant() {
-        let (board_state, pregen_attacks) = setup_board("8/8/8/3pP3/8/8/8/8 w - d6 0 1");
+        let (board_state, pregen_attacks) = setup_board("8/8/8/3pP3/8/8/8/7K w - d6 0 1");
         let mut moves = Vec::new();
         get_pseudo_moves(&board_state, &pregen_attacks, &mut moves);
         assert!(moves.iter().any(|m| m.is_en_passant()));
@@ -369,34 +378,34 @@ mod tests {
 
     #[test]
     fn test_knight_moves() {
-        let (board_state, pregen_attacks) = setup_board("8/8/8/8/4N3/8/8/8 w - - 0 1");
+        let (board_state, pregen_attacks) = setup_board("8/8/8/8/4N3/8/8/K7 w - - 0 1");
         let mut moves = Vec::new();
         get_pseudo_moves(&board_state, &pregen_attacks, &mut moves);
-        assert_eq!(moves.len(), 8); // Knight in center has 8 moves
+        assert_eq!(moves.len(), 11); // Knight in center has 8 moves, the king in the corner 3
     }
 
     #[test]
     fn test_bishop_moves() {
-        let (board_state, pregen_attacks) = setup_board("8/8/8/8/4B3/8/8/8 w - - 0 1");
+        let (board_state, pregen_attacks) = setup_board("8/8/8/8/4B3/8/8/K7 w - - 0 1");
         let mut moves = Vec::new();
         get_pseudo_moves(&board_state, &pregen_attacks, &mut moves);
-        assert_eq!(moves.len(), 13); // Bishop in center has 13 moves
+        assert_eq!(moves.len(), 16); // Bishop in center has 13 moves, the king in the corner 3
     }
 
     #[test]
     fn test_rook_moves() {
-        let (board_state, pregen_attacks) = setup_board("8/8/8/8/4R3/8/8/8 w - - 0 1");
+        let (board_state, pregen_attacks) = setup_board("8/8/8/8/4R3/8/8/K7 w - - 0 1");
         let mut moves = Vec::new();
         get_pseudo_moves(&board_state, &pregen_attacks, &mut moves);
-        assert_eq!(moves.len(), 14); // Rook in center has 14 moves
+        assert_eq!(moves.len(), 17); // Rook in center has 14 moves, the king in the corner 3
     }
 
     #[test]
     fn test_queen_moves() {
-        let (board_state, pregen_attacks) = setup_board("8/8/8/8/4Q3/8/8/8 w - - 0 1");
+        let (board_state, pregen_attacks) = setup_board("8/8/8/8/4Q3/8/8/K7 w - - 0 1");
         let mut moves = Vec::new();
         get_pseudo_moves(&board_state, &pregen_attacks, &mut moves);
-        assert_eq!(moves.len(), 27); // Queen in center has 27 moves
+        assert_eq!(moves.len(), 30); // Queen in center has 27 moves, the king in the corner 3
     }
 
     #[test]
@@ -427,15 +436,15 @@ mod tests {
 
     #[test]
     fn test_promotion_captures() {
-        let (board_state, pregen_attacks) = setup_board("8/P7/8/8/8/8/8/8 w - - 0 1");
+        let (board_state, pregen_attacks) = setup_board("8/P7/8/8/8/8/8/7K w - - 0 1");
         let mut moves = Vec::new();
         get_pseudo_moves(&board_state, &pregen_attacks, &mut moves);
-        assert!(moves.iter().all(|m| m.get_promotion().is_some()));
+        assert!(moves.iter().filter(|m| m.get_piece().is_pawn()).all(|m| m.get_promotion().is_some()));
     }
 
     #[test]
     fn test_double_pawn_push() {
-        let (board_state, pregen_attacks) = setup_board("8/8/8/8/8/8/PPPPPPPP/8 w - - 0 1");
+        let (board_state, pregen_attacks) = setup_board("7K/8/8/8/8/8/PPPPPPPP/8 w - - 0 1");
         let mut moves = Vec::new();
         get_pseudo_moves(&board_state, &pregen_attacks, &mut moves);
         assert!(moves.iter().any(|m| m.is_double_pawn_push()));
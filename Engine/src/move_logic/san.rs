@@ -0,0 +1,310 @@
+use std::sync::OnceLock;
+
+use crate::core::{
+    attack_pregen::PregenAttacks,
+    board_state::BoardState,
+    piece::{Color, Piece, PieceType},
+    square::{File, Square},
+    zobrist::ZobristHasher,
+};
+
+use super::{legal_move_gen, move_encode::Move};
+
+/// Hasher used to make moves on scratch boards; the hash values of those boards are never read
+fn scratch_hasher() -> &'static ZobristHasher {
+    static HASHER: OnceLock<ZobristHasher> = OnceLock::new();
+    HASHER.get_or_init(ZobristHasher::new)
+}
+
+/// Returns the SAN letter of a piece type, e.g. `N` for knights
+fn piece_letter(piece_type: PieceType) -> char {
+    Piece::new(Color::White, piece_type).to_char()
+}
+
+/// Parses a SAN piece letter; lowercase letters are accepted except `b`, which is a file
+fn parse_piece_letter(c: char) -> Option<PieceType> {
+    match c {
+        'N' | 'n' => Some(PieceType::Knight),
+        'B' => Some(PieceType::Bishop),
+        'R' | 'r' => Some(PieceType::Rook),
+        'Q' | 'q' => Some(PieceType::Queen),
+        'K' | 'k' => Some(PieceType::King),
+        _ => None,
+    }
+}
+
+fn file_char(square: Square) -> char {
+    (square.get_file() as u8 + b'a') as char
+}
+
+fn rank_char(square: Square) -> char {
+    (square.get_rank() as u8 + b'1') as char
+}
+
+impl Move {
+    /// Formats a legal move in Standard Algebraic Notation (e.g. `Nbd2`, `exd5`, `e8=Q+`, `O-O-O#`)
+    pub fn to_san(self, board_state: &BoardState, pregen_attacks: &PregenAttacks) -> String {
+        let mut legal_moves = Vec::with_capacity(256);
+        legal_move_gen::get_legal_moves(board_state, pregen_attacks, &mut legal_moves);
+        debug_assert!(legal_moves.contains(&self), "{} is not legal", self);
+
+        let piece_type = self.get_piece().get_type();
+        let from = self.get_from();
+        let to = self.get_to();
+        let mut san = String::with_capacity(8);
+
+        if self.is_castling() {
+            san.push_str(if to.get_file() == File::FG { "O-O" } else { "O-O-O" });
+        } else if piece_type == PieceType::Pawn {
+            if self.is_capture() {
+                san.push(file_char(from));
+                san.push('x');
+            }
+            san.push_str(&to.to_string());
+            if let Some(promotion) = self.get_promotion() {
+                san.push('=');
+                san.push(piece_letter(promotion.get_type()));
+            }
+        } else {
+            san.push(piece_letter(piece_type));
+
+            // Name the file, rank or square of departure when another piece of the same kind can reach the square
+            let rivals: Vec<Square> = legal_moves
+                .iter()
+                .filter(|m| m.get_piece() == self.get_piece() && m.get_to() == to && m.get_from() != from)
+                .map(|m| m.get_from())
+                .collect();
+            if !rivals.is_empty() {
+                if rivals.iter().all(|sq| sq.get_file() != from.get_file()) {
+                    san.push(file_char(from));
+                } else if rivals.iter().all(|sq| sq.get_rank() != from.get_rank()) {
+                    san.push(rank_char(from));
+                } else {
+                    san.push_str(&from.to_string());
+                }
+            }
+
+            if self.is_capture() {
+                san.push('x');
+            }
+            san.push_str(&to.to_string());
+        }
+
+        let mut after = board_state.clone();
+        after.make_move(self, scratch_hasher());
+        if after.is_check(after.get_side(), pregen_attacks) {
+            san.push(if legal_move_gen::has_legal_moves(&after, pregen_attacks) {
+                '+'
+            } else {
+                '#'
+            });
+        }
+        san
+    }
+
+    /// Parses a move in Standard Algebraic Notation, returning it only if it is legal.
+    /// Common variants are accepted: coordinate (`e2e4`) and long (`Ng1-f3`) notation, `0-0` castling,
+    /// promotions without `=` or in lowercase, `:` for captures, and check, en passant and annotation suffixes.
+    pub fn from_san(san: &str, board_state: &BoardState, pregen_attacks: &PregenAttacks) -> Result<Move, &'static str> {
+        let mut legal_moves = Vec::with_capacity(256);
+        legal_move_gen::get_legal_moves(board_state, pregen_attacks, &mut legal_moves);
+
+        let mut text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        text = text.strip_suffix("e.p.").or_else(|| text.strip_suffix("ep")).unwrap_or(text).trim_end();
+        if text.is_empty() {
+            return Err("Empty move");
+        }
+
+        // Castling
+        let castling = match text.replace('0', "O").to_ascii_uppercase().as_str() {
+            "O-O" => Some(File::FG),
+            "O-O-O" => Some(File::FC),
+            _ => None,
+        };
+        if let Some(file) = castling {
+            return legal_moves
+                .into_iter()
+                .find(|m| m.is_castling() && m.get_to().get_file() == file)
+                .ok_or("Illegal castling");
+        }
+
+        // Coordinate notation
+        if let Some(m) = legal_moves.iter().find(|m| m.to_string().eq_ignore_ascii_case(text)) {
+            return Ok(*m);
+        }
+
+        let mut chars: Vec<char> = text.chars().filter(|c| !matches!(c, 'x' | 'X' | ':' | '-' | '=')).collect();
+
+        let piece_type = match chars.first().copied().and_then(parse_piece_letter) {
+            Some(piece_type) => {
+                chars.remove(0);
+                piece_type
+            }
+            None => PieceType::Pawn,
+        };
+
+        // A promotion letter follows the destination rank
+        let mut promotion = None;
+        if chars.len() >= 3 && chars[chars.len() - 2].is_ascii_digit() {
+            let letter = chars[chars.len() - 1].to_ascii_uppercase();
+            promotion = Some(
+                parse_piece_letter(letter)
+                    .filter(|t| *t != PieceType::King)
+                    .ok_or("Invalid promotion piece")?,
+            );
+            chars.pop();
+        }
+
+        if chars.len() < 2 {
+            return Err("Missing destination square");
+        }
+        let to_str: String = chars.split_off(chars.len() - 2).into_iter().collect();
+        let to = Square::from_string(&to_str)?;
+
+        // Whatever is left names the departure file and/or rank
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in chars {
+            match c {
+                'a'..='h' if from_file.is_none() => from_file = Some(c as u8 - b'a'),
+                '1'..='8' if from_rank.is_none() => from_rank = Some(c as u8 - b'1'),
+                _ => return Err("Invalid SAN"),
+            }
+        }
+
+        let mut candidates = legal_moves.into_iter().filter(|m| {
+            m.get_piece().get_type() == piece_type
+                && m.get_to() == to
+                && m.get_promotion().map(|p| p.get_type()) == promotion
+                && from_file.is_none_or(|f| m.get_from().get_file() as u8 == f)
+                && from_rank.is_none_or(|r| m.get_from().get_rank() as u8 == r)
+        });
+
+        match (candidates.next(), candidates.next()) {
+            (Some(m), None) => Ok(m),
+            (Some(_), Some(_)) => Err("Ambiguous move"),
+            (None, _) => Err("Illegal move"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::move_logic::test_position::Position;
+
+    impl Position {
+        /// SAN of the legal move given in coordinate notation
+        fn san(&self, coordinate: &str) -> String {
+            self.find(coordinate).to_san(&self.board_state, &self.pregen_attacks)
+        }
+
+        /// Coordinate notation of the move parsed from SAN
+        fn parse(&self, san: &str) -> Result<String, &'static str> {
+            Move::from_san(san, &self.board_state, &self.pregen_attacks).map(|m| m.to_string())
+        }
+    }
+
+    #[test]
+    fn test_to_san() {
+        let start = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(start.san("g1f3"), "Nf3");
+        assert_eq!(start.san("e2e4"), "e4");
+
+        let kiwipete = Position::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(kiwipete.san("e1g1"), "O-O");
+        assert_eq!(kiwipete.san("e1c1"), "O-O-O");
+        assert_eq!(kiwipete.san("d5e6"), "dxe6");
+        assert_eq!(kiwipete.san("e5f7"), "Nxf7");
+        assert_eq!(kiwipete.san("f3f6"), "Qxf6");
+        assert_eq!(kiwipete.san("g2h3"), "gxh3");
+
+        let promotions = Position::new("5n1k/4P3/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(promotions.san("e7e8q"), "e8=Q");
+        assert_eq!(promotions.san("e7f8n"), "exf8=N");
+        assert_eq!(promotions.san("e7f8r"), "exf8=R+");
+
+        let en_passant = Position::new("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(en_passant.san("e5d6"), "exd6");
+    }
+
+    #[test]
+    fn test_disambiguation_and_suffixes() {
+        let rooks = Position::new("7k/8/8/8/8/8/8/R4RK1 w - - 0 1");
+        assert_eq!(rooks.san("a1d1"), "Rad1");
+        assert_eq!(rooks.san("f1d1"), "Rfd1");
+        assert_eq!(rooks.san("f1f7"), "Rf7");
+
+        let knights = Position::new("7k/8/8/6N1/8/6N1/8/K7 w - - 0 1");
+        assert_eq!(knights.san("g5e4"), "N5e4");
+        assert_eq!(knights.san("g3e4"), "N3e4");
+
+        let queens = Position::new("7k/8/8/8/2Q1Q3/8/4Q3/K7 w - - 0 1");
+        assert_eq!(queens.san("e4d3"), "Qe4d3");
+        assert_eq!(queens.san("c4d3"), "Qcd3");
+        assert_eq!(queens.san("e2d3"), "Q2d3");
+
+        let back_rank = Position::new("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(back_rank.san("a1a8"), "Ra8#");
+        assert_eq!(back_rank.san("a1a7"), "Ra7");
+    }
+
+    #[test]
+    fn test_from_san() {
+        let kiwipete = Position::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(kiwipete.parse("O-O"), Ok("e1g1".to_string()));
+        assert_eq!(kiwipete.parse("0-0-0"), Ok("e1c1".to_string()));
+        assert_eq!(kiwipete.parse("Nxf7"), Ok("e5f7".to_string()));
+        assert_eq!(kiwipete.parse("Ne5xf7+!?"), Ok("e5f7".to_string()));
+        assert_eq!(kiwipete.parse("Qf3-f6"), Ok("f3f6".to_string()));
+        assert_eq!(kiwipete.parse("g2h3"), Ok("g2h3".to_string()));
+        assert_eq!(kiwipete.parse("gxh3"), Ok("g2h3".to_string()));
+        assert_eq!(kiwipete.parse("Nb5"), Ok("c3b5".to_string()));
+        assert_eq!(kiwipete.parse("Bh6"), Ok("d2h6".to_string()));
+        assert_eq!(kiwipete.parse("Ke3"), Err("Illegal move"));
+        assert!(kiwipete.parse("Zz9").is_err());
+        assert!(kiwipete.parse("").is_err());
+
+        let rooks = Position::new("7k/8/8/8/8/8/8/R4RK1 w - - 0 1");
+        assert_eq!(rooks.parse("Rd1"), Err("Ambiguous move"));
+        assert_eq!(rooks.parse("R1d1"), Err("Ambiguous move"));
+        assert_eq!(rooks.parse("Rad1"), Ok("a1d1".to_string()));
+        assert_eq!(rooks.parse("rfd1"), Ok("f1d1".to_string()));
+
+        let promotions = Position::new("5n1k/4P3/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(promotions.parse("e8=Q"), Ok("e7e8q".to_string()));
+        assert_eq!(promotions.parse("e8Q"), Ok("e7e8q".to_string()));
+        assert_eq!(promotions.parse("exf8=n"), Ok("e7f8n".to_string()));
+        assert_eq!(promotions.parse("e7e8r"), Ok("e7e8r".to_string()));
+        assert_eq!(promotions.parse("e8"), Err("Illegal move"));
+        assert_eq!(promotions.parse("e8=K"), Err("Invalid promotion piece"));
+
+        let en_passant = Position::new("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(en_passant.parse("exd6 e.p."), Ok("e5d6".to_string()));
+        assert_eq!(en_passant.parse("ed6"), Ok("e5d6".to_string()));
+    }
+
+    #[test]
+    fn test_san_round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1",
+            "7k/8/8/8/2Q1Q3/8/4Q3/K7 w - - 0 1",
+            "5n1k/4P3/8/8/8/8/8/K7 w - - 0 1",
+        ] {
+            let position = Position::new(fen);
+            for &m in &position.moves {
+                let san = m.to_san(&position.board_state, &position.pregen_attacks);
+                assert_eq!(
+                    Move::from_san(&san, &position.board_state, &position.pregen_attacks),
+                    Ok(m),
+                    "{} in {}",
+                    san,
+                    fen
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::core::{attack_pregen::PregenAttacks, board_state::BoardState, zobrist::ZobristHasher};
+
+use super::{legal_move_gen, move_encode::Move};
+
+/// A position with its legal moves, shared by the move logic tests
+pub struct Position {
+    pub board_state: BoardState,
+    pub pregen_attacks: PregenAttacks,
+    pub moves: Vec<Move>,
+}
+
+impl Position {
+    pub fn new(fen: &str) -> Self {
+        let board_state = BoardState::new(Some(fen), &ZobristHasher::new()).unwrap();
+        let pregen_attacks = PregenAttacks::init();
+        let mut moves = Vec::new();
+        legal_move_gen::get_legal_moves(&board_state, &pregen_attacks, &mut moves);
+        Self {
+            board_state,
+            pregen_attacks,
+            moves,
+        }
+    }
+
+    /// Returns the legal move given in coordinate notation
+    pub fn find(&self, coordinate: &str) -> Move {
+        *self.moves.iter().find(|m| m.to_string() == coordinate).unwrap()
+    }
+}
@@ -0,0 +1,217 @@
+use std::time::Duration;
+
+use crate::core::piece::Color;
+
+use super::{move_encode::Move, move_eval::SearchLimits};
+
+/// Default time reserved per move for communication and process overhead, in milliseconds
+pub const DEFAULT_MOVE_OVERHEAD_MS: u64 = 10;
+/// Largest accepted move overhead, in milliseconds
+pub const MAX_MOVE_OVERHEAD_MS: u64 = 5000;
+
+/// Moves assumed to remain in the game when the time control has no `movestogo`
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/// Score drop between two iterations, in centipawns, at which the time extension is largest
+const MAX_SCORE_DROP: i32 = 200;
+/// Iterations without a best move change after which the PV is considered stable
+const STABLE_ITERATIONS: u32 = 4;
+
+/// Decides how long a search may run: a hard limit checked during the search, and a soft limit checked after every
+/// iteration that grows when the best move is unstable or the score drops, and shrinks when the PV is stable
+#[derive(Clone, Debug)]
+pub struct TimeManager {
+    /// Time the search aims to use when the best move is neither unstable nor stable
+    optimum: Duration,
+    /// Time after which the search is stopped, even in the middle of an iteration
+    maximum: Duration,
+    max_nodes: u64,
+    /// Whether the soft limit applies, i.e. the search is playing on a clock
+    adaptive: bool,
+    /// Only one legal move, so there is nothing to think about
+    single_reply: bool,
+    last_best_move: Option<Move>,
+    last_score: Option<i32>,
+    /// Recent best move changes, halved after every iteration
+    best_move_changes: f64,
+    /// Iterations since the best move last changed
+    stable_iterations: u32,
+    /// Score drop of the last iteration, in centipawns
+    score_drop: i32,
+}
+
+impl TimeManager {
+    /// Creates the time manager for a search by `side` with `root_moves` legal moves
+    pub fn new(limits: &SearchLimits, side: Color, move_overhead: Duration, root_moves: usize) -> Self {
+        let mut time_manager = Self {
+            optimum: Duration::MAX,
+            maximum: Duration::MAX,
+            max_nodes: limits.nodes.unwrap_or(u64::MAX),
+            adaptive: false,
+            single_reply: root_moves == 1,
+            last_best_move: None,
+            last_score: None,
+            best_move_changes: 0.0,
+            stable_iterations: 0,
+            score_drop: 0,
+        };
+
+        if limits.infinite {
+            return time_manager;
+        }
+
+        // Fixed time per move
+        if let Some(move_time) = limits.move_time {
+            let move_time = move_time.saturating_sub(move_overhead).max(Duration::from_millis(1));
+            time_manager.optimum = move_time;
+            time_manager.maximum = move_time;
+            return time_manager;
+        }
+
+        let (Some(time_left), increment) = limits.clock_for(side) else {
+            // No clock: search until stopped or another limit is reached
+            return time_manager;
+        };
+
+        let moves_to_go = limits.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        // Keep the overhead for every move still to be played in this time control
+        let time_left = time_left.saturating_sub(move_overhead * moves_to_go.min(DEFAULT_MOVES_TO_GO));
+        let optimum = (time_left / moves_to_go + increment * 3 / 4).min(time_left / 2);
+
+        time_manager.optimum = optimum.max(Duration::from_millis(1));
+        time_manager.maximum = (optimum * 3).min(time_left * 4 / 5).max(time_manager.optimum);
+        time_manager.adaptive = true;
+        time_manager
+    }
+
+    /// Checks the hard limits, which end the search even in the middle of an iteration
+    #[inline]
+    pub fn should_stop(&self, elapsed: Duration, nodes: u64) -> bool {
+        elapsed >= self.maximum || nodes >= self.max_nodes
+    }
+
+    /// Records the result of a completed iteration
+    pub fn update(&mut self, best_move: Move, score: i32) {
+        self.best_move_changes /= 2.0;
+        if self.last_best_move.is_some_and(|m| m != best_move) {
+            self.best_move_changes += 1.0;
+            self.stable_iterations = 0;
+        } else {
+            self.stable_iterations += 1;
+        }
+        self.last_best_move = Some(best_move);
+        self.score_drop = self.last_score.map_or(0, |last| (last - score).clamp(0, MAX_SCORE_DROP));
+        self.last_score = Some(score);
+    }
+
+    /// Checks if another iteration should be started after `elapsed` time
+    pub fn should_stop_iteration(&self, elapsed: Duration) -> bool {
+        if !self.adaptive {
+            return elapsed >= self.maximum;
+        }
+        if self.single_reply {
+            return true;
+        }
+        // The next iteration takes about as long as all previous ones together, and an
+        // unfinished iteration is wasted, so do not start one that would end past the soft limit
+        elapsed * 2 >= self.soft_limit()
+    }
+
+    /// Optimum time scaled by the stability of the best move and score
+    fn soft_limit(&self) -> Duration {
+        let mut scale = 1.0 + 0.75 * self.best_move_changes;
+        scale *= 1.0 + 0.5 * self.score_drop as f64 / MAX_SCORE_DROP as f64;
+        if self.stable_iterations >= STABLE_ITERATIONS {
+            scale *= 0.6;
+        }
+        self.optimum.mul_f64(scale).min(self.maximum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{piece::Piece, square::Square};
+
+    const OVERHEAD: Duration = Duration::from_millis(DEFAULT_MOVE_OVERHEAD_MS);
+
+    fn clock(time_left_ms: u64, increment_ms: u64, moves_to_go: Option<u32>) -> TimeManager {
+        let limits = SearchLimits {
+            wtime: Some(Duration::from_millis(time_left_ms)),
+            winc: Some(Duration::from_millis(increment_ms)),
+            moves_to_go,
+            ..Default::default()
+        };
+        TimeManager::new(&limits, Color::White, OVERHEAD, 20)
+    }
+
+    fn test_move(to: Square) -> Move {
+        Move::new(Square::E2, to, Piece::WPawn, None, None, false, false, false)
+    }
+
+    #[test]
+    fn test_clock_allocation() {
+        let time_manager = clock(60_000, 0, None);
+        assert!(time_manager.optimum > Duration::from_millis(1500) && time_manager.optimum < Duration::from_millis(2500));
+        assert!(time_manager.maximum > time_manager.optimum);
+
+        // The increment adds to the time per move
+        assert!(clock(60_000, 1000, None).optimum > time_manager.optimum);
+        // The last move before the time control may use more, but never the whole clock
+        let last_move = clock(10_000, 0, Some(1));
+        assert!(last_move.optimum >= Duration::from_secs(4));
+        assert!(last_move.maximum <= Duration::from_secs(8));
+        // Sub-second clocks still get some time
+        let scramble = clock(50, 0, None);
+        assert!(scramble.optimum > Duration::ZERO && scramble.maximum < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_move_time_and_nodes() {
+        let limits = SearchLimits {
+            move_time: Some(Duration::from_millis(100)),
+            nodes: Some(5000),
+            ..Default::default()
+        };
+        let time_manager = TimeManager::new(&limits, Color::Black, OVERHEAD, 20);
+        assert!(!time_manager.should_stop(Duration::from_millis(80), 0));
+        assert!(time_manager.should_stop(Duration::from_millis(90), 0));
+        assert!(time_manager.should_stop(Duration::ZERO, 5000));
+
+        let infinite = TimeManager::new(&SearchLimits { infinite: true, ..limits }, Color::Black, OVERHEAD, 20);
+        assert!(!infinite.should_stop(Duration::from_secs(3600), 0));
+    }
+
+    #[test]
+    fn test_soft_limit_adapts_to_stability() {
+        let mut time_manager = clock(60_000, 0, None);
+        time_manager.update(test_move(Square::E4), 20);
+        let base = time_manager.soft_limit();
+
+        // A new best move extends the time
+        time_manager.update(test_move(Square::E3), 20);
+        let unstable = time_manager.soft_limit();
+        assert!(unstable > base);
+
+        // So does a falling score
+        time_manager.update(test_move(Square::E3), -100);
+        assert!(time_manager.soft_limit() > clock(60_000, 0, None).optimum);
+
+        // A best move that stays the same for several iterations shortens it
+        for _ in 0..STABLE_ITERATIONS + 2 {
+            time_manager.update(test_move(Square::E3), -100);
+        }
+        assert!(time_manager.soft_limit() < base);
+        assert!(time_manager.should_stop_iteration(time_manager.soft_limit() / 2));
+        assert!(!time_manager.should_stop_iteration(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_single_reply() {
+        let limits = SearchLimits {
+            btime: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let time_manager = TimeManager::new(&limits, Color::Black, OVERHEAD, 1);
+        assert!(time_manager.should_stop_iteration(Duration::ZERO));
+    }
+}
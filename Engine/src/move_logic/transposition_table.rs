@@ -0,0 +1,411 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use super::{
+    move_encode::Move,
+    move_eval::{MATE_SCORE, MAX_PLY},
+};
+
+/// Default transposition table size in megabytes
+pub const DEFAULT_TT_SIZE_MB: usize = 16;
+/// Largest transposition table size accepted, in megabytes
+pub const MAX_TT_SIZE_MB: usize = 4096;
+
+/// Number of entries sharing one index
+const BUCKET_SIZE: usize = 4;
+
+// Layout of a packed entry: move (27 bits), score (20 bits, signed), depth (7 bits), entry type (2 bits), age (8 bits)
+const MOVE_BITS: u64 = (1 << 27) - 1;
+const SCORE_SHIFT: u32 = 27;
+const SCORE_BITS: u64 = (1 << 20) - 1;
+const DEPTH_SHIFT: u32 = 47;
+const DEPTH_BITS: u64 = (1 << 7) - 1;
+const TYPE_SHIFT: u32 = 54;
+const AGE_SHIFT: u32 = 56;
+
+// Transposition table entry types
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EntryType {
+    Exact,
+    LowerBound,
+    UpperBound,
+}
+
+impl EntryType {
+    /// Encoding used in packed entries; never zero, so a packed entry is never zero either
+    #[inline(always)]
+    const fn to_bits(self) -> u64 {
+        match self {
+            EntryType::Exact => 1,
+            EntryType::LowerBound => 2,
+            EntryType::UpperBound => 3,
+        }
+    }
+
+    #[inline(always)]
+    const fn from_bits(bits: u64) -> Self {
+        match bits {
+            1 => EntryType::Exact,
+            2 => EntryType::LowerBound,
+            _ => EntryType::UpperBound,
+        }
+    }
+}
+
+/// Entry stored in the transposition table
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TTEntry {
+    best_move: Option<Move>,
+    /// Score with mate scores stored relative to this node rather than the root
+    score: i32,
+    depth: u8,
+    entry_type: EntryType,
+    /// Search generation the entry was written in
+    age: u8,
+}
+
+impl TTEntry {
+    /// Creates an entry for a node searched at `ply` from the root
+    pub fn new(best_move: Option<Move>, score: i32, depth: u8, entry_type: EntryType, ply: u8) -> Self {
+        Self {
+            best_move,
+            score: score_to_tt(score, ply),
+            depth: depth.min(DEPTH_BITS as u8),
+            entry_type,
+            age: 0,
+        }
+    }
+
+    /// Returns the score as seen from the root when the entry is probed at `ply`
+    #[inline(always)]
+    pub fn get_score(&self, ply: u8) -> i32 {
+        score_from_tt(self.score, ply)
+    }
+
+    #[inline(always)]
+    pub fn get_best_move(&self) -> Option<Move> {
+        self.best_move
+    }
+
+    #[inline(always)]
+    pub fn get_depth(&self) -> u8 {
+        self.depth
+    }
+
+    #[inline(always)]
+    pub fn get_entry_type(&self) -> EntryType {
+        self.entry_type
+    }
+
+    #[inline(always)]
+    fn pack(&self) -> u64 {
+        debug_assert!(
+            (self.score as i64).unsigned_abs() <= SCORE_BITS >> 1,
+            "score out of range: {}",
+            self.score
+        );
+        self.best_move.map_or(0, |m| m.get_bits() as u64)
+            | (self.score as u64 & SCORE_BITS) << SCORE_SHIFT
+            | (self.depth as u64) << DEPTH_SHIFT
+            | self.entry_type.to_bits() << TYPE_SHIFT
+            | (self.age as u64) << AGE_SHIFT
+    }
+
+    #[inline(always)]
+    fn unpack(data: u64) -> Self {
+        let move_bits = (data & MOVE_BITS) as u32;
+        Self {
+            best_move: (move_bits != 0).then(|| Move::from_bits(move_bits)),
+            // Sign-extend the 20-bit score
+            score: ((((data >> SCORE_SHIFT) & SCORE_BITS) << 44) as i64 >> 44) as i32,
+            depth: ((data >> DEPTH_SHIFT) & DEPTH_BITS) as u8,
+            entry_type: EntryType::from_bits((data >> TYPE_SHIFT) & 0b11),
+            age: (data >> AGE_SHIFT) as u8,
+        }
+    }
+}
+
+/// One entry of a bucket. `key` holds the zobrist hash XORed with `data`, so an entry torn by
+/// concurrent writes from two threads fails verification instead of being read as valid.
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    /// Returns the packed entry and the hash it was stored for, or None if the slot is empty
+    #[inline(always)]
+    fn load(&self) -> Option<(u64, u64)> {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.key.load(Ordering::Relaxed);
+        (data != 0).then_some((key ^ data, data))
+    }
+
+    #[inline(always)]
+    fn store(&self, hash: u64, data: u64) {
+        self.key.store(hash ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    fn clear(&self) {
+        self.key.store(0, Ordering::Relaxed);
+        self.data.store(0, Ordering::Relaxed);
+    }
+}
+
+type Bucket = [Slot; BUCKET_SIZE];
+
+/// Fixed-size hash table of search results, indexed by zobrist hash.
+/// Entries are read and written without locking, so one table can be shared by all search threads.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    /// Incremented for every search so entries from old searches are replaced first
+    age: AtomicU8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_TT_SIZE_MB)
+    }
+}
+
+impl TranspositionTable {
+    /// Creates a table using about `size_mb` megabytes
+    pub fn new(size_mb: usize) -> Self {
+        Self {
+            buckets: new_buckets(size_mb),
+            age: AtomicU8::new(0),
+        }
+    }
+
+    /// Reallocates the table to about `size_mb` megabytes, discarding all entries
+    pub fn resize(&mut self, size_mb: usize) {
+        self.buckets = new_buckets(size_mb);
+        self.age = AtomicU8::new(0);
+    }
+
+    /// Removes all entries
+    pub fn clear(&self) {
+        self.buckets.iter().flatten().for_each(Slot::clear);
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Starts a new search generation
+    pub fn new_search(&self) {
+        self.age.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the entry stored for `hash`, if any
+    pub fn probe(&self, hash: u64) -> Option<TTEntry> {
+        self.buckets[self.index(hash)]
+            .iter()
+            .filter_map(Slot::load)
+            .find(|&(key, _)| key == hash)
+            .map(|(_, data)| TTEntry::unpack(data))
+    }
+
+    /// Stores an entry for `hash`, replacing the least valuable entry of its bucket
+    pub fn store(&self, hash: u64, mut entry: TTEntry) {
+        let age = self.age.load(Ordering::Relaxed);
+        entry.age = age;
+        let bucket = &self.buckets[self.index(hash)];
+
+        // Update an existing entry for the same position, keeping its move if the new search found none
+        if let Some((slot, data)) = bucket
+            .iter()
+            .find_map(|slot| slot.load().filter(|&(key, _)| key == hash).map(|(_, data)| (slot, data)))
+        {
+            let old = TTEntry::unpack(data);
+            if entry.best_move.is_none() {
+                entry.best_move = old.best_move;
+            }
+            if entry.entry_type == EntryType::Exact || old.age != age || entry.depth + 2 >= old.depth {
+                slot.store(hash, entry.pack());
+            }
+            return;
+        }
+
+        // Otherwise use an empty slot, or evict the shallowest entry, counting older searches as shallower
+        let victim = bucket
+            .iter()
+            .min_by_key(|slot| match slot.load() {
+                None => i32::MIN,
+                Some((_, data)) => {
+                    let e = TTEntry::unpack(data);
+                    e.depth as i32 - 8 * age.wrapping_sub(e.age) as i32
+                }
+            })
+            .unwrap();
+        victim.store(hash, entry.pack());
+    }
+
+    /// Permille of sampled entries written during the current search
+    pub fn hashfull(&self) -> u32 {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = self.buckets.len().min(1000 / BUCKET_SIZE);
+        let used = self.buckets[..sample]
+            .iter()
+            .flatten()
+            .filter_map(Slot::load)
+            .filter(|&(_, data)| TTEntry::unpack(data).age == age)
+            .count();
+        (used * 1000 / (sample * BUCKET_SIZE)) as u32
+    }
+
+    #[inline(always)]
+    fn index(&self, hash: u64) -> usize {
+        // Maps the lower half of the hash onto the table without requiring a power-of-two size
+        (((hash & 0xFFFF_FFFF) * self.buckets.len() as u64) >> 32) as usize
+    }
+}
+
+fn new_buckets(size_mb: usize) -> Vec<Bucket> {
+    let bytes = size_mb.clamp(1, MAX_TT_SIZE_MB) * 1024 * 1024;
+    let count = (bytes / std::mem::size_of::<Bucket>()).max(1);
+    (0..count).map(|_| Bucket::default()).collect()
+}
+
+/// Mate scores are stored as distance from the node so they stay valid when reached through another path
+#[inline(always)]
+fn score_to_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_SCORE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= -MATE_SCORE + MAX_PLY as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+#[inline(always)]
+fn score_from_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_SCORE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= -MATE_SCORE + MAX_PLY as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{piece::Piece, square::Square};
+
+    fn test_move() -> Move {
+        Move::new(Square::E2, Square::E4, Piece::WPawn, None, None, true, false, false)
+    }
+
+    #[test]
+    fn test_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let hash = 0x1234_5678_9abc_def0;
+        tt.store(hash, TTEntry::new(Some(test_move()), 42, 5, EntryType::Exact, 3));
+
+        let entry = tt.probe(hash).unwrap();
+        assert_eq!(entry.get_score(3), 42);
+        assert_eq!(entry.get_depth(), 5);
+        assert_eq!(entry.get_best_move(), Some(test_move()));
+        assert_eq!(entry.get_entry_type(), EntryType::Exact);
+
+        // Same index, different hash
+        assert!(tt.probe(hash ^ (1 << 40)).is_none());
+
+        tt.clear();
+        assert!(tt.probe(hash).is_none());
+    }
+
+    #[test]
+    fn test_mate_score_adjustment() {
+        let tt = TranspositionTable::new(1);
+        let hash = 42;
+        // Mate in 3 plies from a node at ply 4 is mate in 7 plies from the root
+        tt.store(hash, TTEntry::new(None, MATE_SCORE - 7, 3, EntryType::Exact, 4));
+        // Reached at ply 2 through a shorter path, it is mate in 5 plies
+        assert_eq!(tt.probe(hash).unwrap().get_score(2), MATE_SCORE - 5);
+
+        tt.store(hash, TTEntry::new(None, -MATE_SCORE + 6, 3, EntryType::Exact, 6));
+        assert_eq!(tt.probe(hash).unwrap().get_score(0), -MATE_SCORE);
+    }
+
+    #[test]
+    fn test_replacement_keeps_best_move_and_deep_entries() {
+        let tt = TranspositionTable::new(1);
+        let hash = 7;
+        tt.store(hash, TTEntry::new(Some(test_move()), 10, 8, EntryType::LowerBound, 0));
+
+        // A much shallower result from the same search does not overwrite a deep entry
+        tt.store(hash, TTEntry::new(None, 20, 2, EntryType::UpperBound, 0));
+        assert_eq!(tt.probe(hash).unwrap().get_depth(), 8);
+
+        // A result from a later search does, but keeps the stored move
+        tt.new_search();
+        tt.store(hash, TTEntry::new(None, 20, 2, EntryType::UpperBound, 0));
+        let entry = tt.probe(hash).unwrap();
+        assert_eq!(entry.get_depth(), 2);
+        assert_eq!(entry.get_best_move(), Some(test_move()));
+    }
+
+    #[test]
+    fn test_bucket_evicts_shallowest() {
+        let tt = TranspositionTable::new(1);
+        // Hashes differing only in the upper half share a bucket
+        let hashes: Vec<u64> = (0..=BUCKET_SIZE as u64).map(|i| (i << 32) | 1).collect();
+        for (i, &hash) in hashes.iter().take(BUCKET_SIZE).enumerate() {
+            tt.store(hash, TTEntry::new(None, 0, 10 - i as u8, EntryType::Exact, 0));
+        }
+        tt.store(hashes[BUCKET_SIZE], TTEntry::new(None, 0, 9, EntryType::Exact, 0));
+
+        assert!(tt.probe(hashes[BUCKET_SIZE - 1]).is_none());
+        assert!(tt.probe(hashes[0]).is_some());
+        assert!(tt.probe(hashes[BUCKET_SIZE]).is_some());
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut tt = TranspositionTable::new(1);
+        let buckets = tt.buckets.len();
+        tt.resize(4);
+        assert_eq!(tt.buckets.len(), buckets * 4);
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn test_pack_round_trip() {
+        for (best_move, score) in [
+            (Some(test_move()), -MATE_SCORE + 3),
+            (None, MATE_SCORE - 1),
+            (Some(test_move()), 0),
+            (None, -42),
+        ] {
+            for entry_type in [EntryType::Exact, EntryType::LowerBound, EntryType::UpperBound] {
+                let mut entry = TTEntry::new(best_move, score, MAX_PLY, entry_type, 0);
+                entry.age = 255;
+                assert_eq!(TTEntry::unpack(entry.pack()), entry);
+            }
+        }
+    }
+
+    #[test]
+    fn test_concurrent_access() {
+        let tt = TranspositionTable::new(1);
+        let hash = 0xdead_beef_0000_0001;
+        // Threads overwrite the same entry; every probe must see one complete entry, never a mix of two
+        std::thread::scope(|s| {
+            for depth in 1..=4u8 {
+                let tt = &tt;
+                s.spawn(move || {
+                    for _ in 0..10_000 {
+                        tt.store(hash, TTEntry::new(None, depth as i32 * 100, depth, EntryType::Exact, 0));
+                        if let Some(entry) = tt.probe(hash) {
+                            assert_eq!(entry.get_score(0), entry.get_depth() as i32 * 100);
+                        }
+                    }
+                });
+            }
+        });
+        assert!(tt.probe(hash).is_some());
+    }
+}